@@ -0,0 +1,155 @@
+use anyhow::Result;
+use ethers::signers::LocalWallet;
+use ethers::types::transaction::eip712::{Eip712, TypedData};
+use ethers::types::{H160, H256, Signature};
+use ethers::utils::keccak256;
+use hyperliquid_rust_sdk::{ClientOrder, ClientOrderRequest};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+/// 签名及发送时使用的小数位数
+const WIRE_DECIMALS: usize = 8;
+
+/// 按交易所签名规则格式化数值：保留8位小数并去掉末尾的0
+pub fn wire_float(x: f64) -> String {
+    let mut x = format!("{:.*}", WIRE_DECIMALS, x);
+    while x.ends_with('0') {
+        x.pop();
+    }
+    if x.ends_with('.') {
+        x.pop();
+    }
+    if x == "-0" { "0".to_string() } else { x }
+}
+
+/// 请求及成交记录中 cloid 的格式：0x 加 32 位十六进制
+pub fn wire_cloid(cloid: &Uuid) -> String {
+    let hex: String = cloid
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("0x{}", hex)
+}
+
+/// 下单 action，`orders` 为 (标的编号, 订单)
+pub fn order_action(orders: &[(u32, &ClientOrderRequest)]) -> Value {
+    let orders: Vec<Value> = orders
+        .iter()
+        .map(|(asset, order)| {
+            let order_type = match &order.order_type {
+                ClientOrder::Limit(limit) => json!({ "limit": { "tif": limit.tif } }),
+                ClientOrder::Trigger(trigger) => json!({
+                    "trigger": {
+                        "isMarket": trigger.is_market,
+                        "triggerPx": wire_float(trigger.trigger_px),
+                        "tpsl": trigger.tpsl,
+                    }
+                }),
+            };
+            let mut wire = json!({
+                "a": asset,
+                "b": order.is_buy,
+                "p": wire_float(order.limit_px),
+                "s": wire_float(order.sz),
+                "r": order.reduce_only,
+                "t": order_type,
+            });
+            if let Some(cloid) = &order.cloid {
+                wire["c"] = Value::String(wire_cloid(cloid));
+            }
+            wire
+        })
+        .collect();
+    json!({ "type": "order", "orders": orders, "grouping": "na" })
+}
+
+/// 撤单 action，`cancels` 为 (标的编号, 订单ID)
+pub fn cancel_action(cancels: &[(u32, u64)]) -> Value {
+    let cancels: Vec<Value> = cancels
+        .iter()
+        .map(|(asset, oid)| json!({ "a": asset, "o": oid }))
+        .collect();
+    json!({ "type": "cancel", "cancels": cancels })
+}
+
+/// 定时撤销所有挂单的 action，`time` 为毫秒时间戳，为 None 时取消已设置的定时撤单
+pub fn schedule_cancel_action(time: Option<u64>) -> Value {
+    match time {
+        Some(time) => json!({ "type": "scheduleCancel", "time": time }),
+        None => json!({ "type": "scheduleCancel" }),
+    }
+}
+
+/// 待签名的请求体，签名前与 `/exchange` 请求中的 action、nonce 完全一致
+pub fn unsigned_payload(action: Value, nonce: u64) -> Value {
+    json!({ "action": action, "nonce": nonce, "vaultAddress": null })
+}
+
+/// L1 action 的 connection id：msgpack 编码的 action + nonce + vault 地址的 keccak 哈希
+fn connection_id(action: &Value, nonce: u64, vault_address: Option<H160>) -> Result<H256> {
+    let mut bytes = rmp_serde::to_vec_named(action)?;
+    bytes.extend(nonce.to_be_bytes());
+    match vault_address {
+        Some(vault_address) => {
+            bytes.push(1);
+            bytes.extend(vault_address.to_fixed_bytes());
+        }
+        None => bytes.push(0),
+    }
+    Ok(H256(keccak256(bytes)))
+}
+
+/// 按交易所的 EIP-712 规则对 L1 action 签名
+///
+/// SDK 只为已封装的 action 提供签名，其余 action(如 scheduleCancel)在这里本地签名
+pub fn sign_l1_action(
+    wallet: &LocalWallet,
+    action: &Value,
+    nonce: u64,
+    vault_address: Option<H160>,
+    is_mainnet: bool,
+) -> Result<Signature> {
+    let typed_data: TypedData = serde_json::from_value(json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" },
+            ],
+            "Agent": [
+                { "name": "source", "type": "string" },
+                { "name": "connectionId", "type": "bytes32" },
+            ],
+        },
+        "primaryType": "Agent",
+        "domain": {
+            "name": "Exchange",
+            "version": "1",
+            "chainId": 1337,
+            "verifyingContract": format!("{:?}", H160::zero()),
+        },
+        "message": {
+            "source": if is_mainnet { "a" } else { "b" },
+            "connectionId": format!("{:?}", connection_id(action, nonce, vault_address)?),
+        },
+    }))?;
+    let hash = typed_data.encode_eip712()?;
+    Ok(wallet.sign_hash(H256(hash))?)
+}
+
+/// 已签名的 `/exchange` 请求体
+pub fn signed_payload(
+    action: Value,
+    signature: Signature,
+    nonce: u64,
+    vault_address: Option<H160>,
+) -> Value {
+    json!({
+        "action": action,
+        "signature": signature,
+        "nonce": nonce,
+        "vaultAddress": vault_address,
+    })
+}
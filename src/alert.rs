@@ -0,0 +1,591 @@
+use crate::backend::Backend;
+use crate::error::ToolsetError;
+use crate::store;
+use crate::toolset::{Toolset, now_ms};
+use crate::types::{Position, UserFill};
+use crate::ui;
+use anyhow::Result;
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::Duration;
+
+/// 涨跌幅提醒的最短时间窗口
+pub const MIN_CHANGE_WINDOW: Duration = Duration::from_secs(60);
+/// 时间窗口不超过该值时使用 1 分钟K线，否则使用 1 小时K线
+const MINUTE_CANDLE_WINDOW: u64 = 3 * 86400;
+/// 命令钩子及 Webhook 的超时时间
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 提醒条件
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AlertCondition {
+    /// 中间价高于该价格
+    Above(f64),
+    /// 中间价低于该价格
+    Below(f64),
+    /// `window` 秒内涨跌幅的绝对值达到 `percent`，0.05 表示 5%
+    PctChange { percent: f64, window: u64 },
+    /// 仓位的强平价与中间价的距离不超过中间价的 `percent`
+    LiqDistance { percent: f64 },
+    /// 有新的成交
+    Fill,
+}
+
+/// 提醒的通知方式
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AlertChannel {
+    /// 终端响铃
+    Bell,
+    /// 桌面通知，Linux 使用 notify-send，macOS 使用 osascript
+    Desktop,
+    /// 通过 `sh -c` 执行的命令，提醒内容在 HLT_ALERT_* 环境变量中
+    Command(String),
+    /// 以 JSON 请求体 POST 到该地址
+    Webhook(String),
+}
+
+impl fmt::Display for AlertChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertChannel::Bell => write!(f, "bell"),
+            AlertChannel::Desktop => write!(f, "desktop"),
+            AlertChannel::Command(command) => write!(f, "exec: {}", command),
+            AlertChannel::Webhook(url) => write!(f, "webhook: {}", url),
+        }
+    }
+}
+
+/// 价格、涨跌幅、强平距离或成交提醒，由 `hlt alert watch` 检查
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub id: u64,
+    pub asset: String,
+    pub condition: AlertCondition,
+    pub channels: Vec<AlertChannel>,
+    /// 冷却时间，秒；条件持续成立时每个冷却时间最多通知一次，成交提醒不受限制
+    pub cooldown: u64,
+    /// 已触发次数
+    pub fired: u32,
+    /// 上次触发时间，毫秒时间戳
+    pub last_fired: Option<u64>,
+    /// 成交提醒已通知到的成交时间，毫秒时间戳
+    pub fills_since: u64,
+    pub created_at: u64,
+}
+
+impl Alert {
+    pub fn new(
+        asset: &str,
+        condition: AlertCondition,
+        channels: Vec<AlertChannel>,
+        cooldown: Duration,
+    ) -> Self {
+        let now = now_ms();
+        Self {
+            id: 0,
+            asset: asset.to_string(),
+            condition,
+            channels,
+            cooldown: cooldown.as_secs(),
+            fired: 0,
+            last_fired: None,
+            fills_since: now,
+            created_at: now,
+        }
+    }
+
+    /// 冷却时间是否已过
+    fn ready(&self, now: u64) -> bool {
+        self.last_fired
+            .is_none_or(|last| now.saturating_sub(last) >= self.cooldown * 1000)
+    }
+
+    /// 条件的描述，如 BTC above 100000
+    pub fn describe(&self) -> String {
+        match &self.condition {
+            AlertCondition::Above(price) => format!("{} above {}", self.asset, price),
+            AlertCondition::Below(price) => format!("{} below {}", self.asset, price),
+            AlertCondition::PctChange { percent, window } => format!(
+                "{} moves {}% in {}",
+                self.asset,
+                percent * 100.0,
+                ui::interval(*window)
+            ),
+            AlertCondition::LiqDistance { percent } => {
+                format!("{} within {}% of liquidation", self.asset, percent * 100.0)
+            }
+            AlertCondition::Fill => format!("{} fills", self.asset),
+        }
+    }
+}
+
+/// 一次触发的提醒，也是 Webhook 的请求体
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEvent {
+    pub id: u64,
+    pub asset: String,
+    pub condition: String,
+    pub message: String,
+    /// 触发时的价格、涨跌幅或强平距离
+    pub value: Option<f64>,
+    /// 触发时间，毫秒时间戳
+    pub time: u64,
+    /// 发送失败的通知
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl AlertEvent {
+    fn new(alert: &Alert, message: String, value: Option<f64>) -> Self {
+        Self {
+            id: alert.id,
+            asset: alert.asset.clone(),
+            condition: alert.describe(),
+            message,
+            value,
+            time: now_ms(),
+            errors: vec![],
+        }
+    }
+
+    /// 发送测试通知使用的提醒
+    pub fn test(alert: &Alert) -> Self {
+        Self::new(
+            alert,
+            format!("Test notification for {}", alert.describe()),
+            None,
+        )
+    }
+}
+
+fn alerts_file(address: H160) -> String {
+    format!("alerts-{:?}.json", address)
+}
+
+/// 账户的所有提醒，按 ID 索引
+pub fn load(address: H160) -> Result<BTreeMap<u64, Alert>> {
+    store::load(&alerts_file(address))
+}
+
+pub fn save(address: H160, alerts: &BTreeMap<u64, Alert>) -> Result<()> {
+    store::save(&alerts_file(address), alerts)
+}
+
+/// 保存检查后的触发状态，检查期间被删除的提醒不会恢复，新增的提醒保留
+pub fn save_progress(address: H160, alerts: &BTreeMap<u64, Alert>) -> Result<()> {
+    let mut saved = load(address)?;
+    for (id, alert) in alerts {
+        if let Some(saved) = saved.get_mut(id) {
+            saved.fired = alert.fired;
+            saved.last_fired = alert.last_fired;
+            saved.fills_since = alert.fills_since;
+        }
+    }
+    save(address, &saved)
+}
+
+/// 一次检查使用的行情，仓位及成交按需获取
+struct Snapshot {
+    mids: HashMap<String, f64>,
+    positions: Option<Vec<Position>>,
+    fills: Option<Vec<UserFill>>,
+}
+
+impl Snapshot {
+    async fn positions<B: Backend>(&mut self, toolset: &Toolset<B>) -> Result<&[Position]> {
+        if self.positions.is_none() {
+            self.positions = Some(toolset.positions().await?);
+        }
+        Ok(self.positions.as_deref().unwrap_or_default())
+    }
+
+    /// 所有成交提醒中最早的起始时间之后的成交
+    async fn fills<B: Backend>(&mut self, toolset: &Toolset<B>, since: u64) -> Result<&[UserFill]> {
+        if self.fills.is_none() {
+            let fills = toolset
+                .backend()
+                .user_fills_by_time(toolset.address(), since)
+                .await?;
+            self.fills = Some(fills);
+        }
+        Ok(self.fills.as_deref().unwrap_or_default())
+    }
+}
+
+/// `window` 秒内的涨跌幅，以窗口内第一根K线的开盘价为基准，没有K线时为 None
+async fn change<B: Backend>(
+    toolset: &Toolset<B>,
+    asset: &str,
+    mid: f64,
+    window: u64,
+) -> Result<Option<(f64, f64)>> {
+    let now = now_ms();
+    let interval = if window <= MINUTE_CANDLE_WINDOW {
+        "1m"
+    } else {
+        "1h"
+    };
+    let candles = toolset
+        .backend()
+        .candles(asset, interval, now.saturating_sub(window * 1000))
+        .await?;
+    Ok(candles
+        .first()
+        .filter(|c| c.open > 0.0)
+        .map(|c| (mid / c.open - 1.0, c.open)))
+}
+
+/// 检查提醒的条件，成立时返回提醒内容
+async fn evaluate<B: Backend>(
+    toolset: &Toolset<B>,
+    snapshot: &mut Snapshot,
+    alert: &Alert,
+    fills_since: u64,
+) -> Result<Option<AlertEvent>> {
+    let mid = snapshot.mids.get(&alert.asset).copied();
+    let event = match &alert.condition {
+        AlertCondition::Above(price) => mid.filter(|mid| mid > price).map(|mid| {
+            let message = format!("{} price {} is above {}", alert.asset, mid, price);
+            AlertEvent::new(alert, message, Some(mid))
+        }),
+        AlertCondition::Below(price) => mid.filter(|mid| mid < price).map(|mid| {
+            let message = format!("{} price {} is below {}", alert.asset, mid, price);
+            AlertEvent::new(alert, message, Some(mid))
+        }),
+        AlertCondition::PctChange { percent, window } => {
+            let Some(mid) = mid else {
+                return Ok(None);
+            };
+            change(toolset, &alert.asset, mid, *window)
+                .await?
+                .filter(|(change, _)| change.abs() >= *percent)
+                .map(|(change, base)| {
+                    let message = format!(
+                        "{} moved {:+.2}% in {} ({} → {})",
+                        alert.asset,
+                        change * 100.0,
+                        ui::interval(*window),
+                        base,
+                        mid
+                    );
+                    AlertEvent::new(alert, message, Some(change))
+                })
+        }
+        AlertCondition::LiqDistance { percent } => {
+            let Some(mid) = mid else {
+                return Ok(None);
+            };
+            let position = snapshot
+                .positions(toolset)
+                .await?
+                .iter()
+                .find(|p| p.asset == alert.asset && p.quantity != 0.0)
+                .map(|p| (p.quantity, p.liquidation_price));
+            position.and_then(|(quantity, liquidation)| {
+                let liquidation = liquidation?;
+                let distance = (mid - liquidation).abs() / mid;
+                (distance <= *percent).then(|| {
+                    let message = format!(
+                        "{} {} is {:.2}% from liquidation at {} (price {})",
+                        alert.asset,
+                        if quantity > 0.0 { "long" } else { "short" },
+                        distance * 100.0,
+                        liquidation,
+                        mid
+                    );
+                    AlertEvent::new(alert, message, Some(distance))
+                })
+            })
+        }
+        AlertCondition::Fill => {
+            let fills: Vec<&UserFill> = snapshot
+                .fills(toolset, fills_since)
+                .await?
+                .iter()
+                .filter(|f| f.coin == alert.asset && f.time > alert.fills_since)
+                .collect();
+            (!fills.is_empty()).then(|| {
+                let details: Vec<String> = fills
+                    .iter()
+                    .map(|f| {
+                        format!(
+                            "{} {} @ {}",
+                            if f.side == "B" { "buy" } else { "sell" },
+                            f.sz,
+                            f.px
+                        )
+                    })
+                    .collect();
+                let message = format!(
+                    "{} {} filled: {}",
+                    fills.len(),
+                    alert.asset,
+                    details.join(", ")
+                );
+                let price = fills.last().and_then(|f| f.px.parse().ok());
+                AlertEvent::new(alert, message, price)
+            })
+        }
+    };
+    Ok(event)
+}
+
+/// 桌面通知
+async fn notify_desktop(event: &AlertEvent) -> Result<()> {
+    let title = format!("hlt alert #{}", event.id);
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = tokio::process::Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {:?} with title {:?}",
+            event.message, title
+        ));
+        command
+    } else {
+        let mut command = tokio::process::Command::new("notify-send");
+        command.arg(&title).arg(&event.message);
+        command
+    };
+    let status = command.status().await?;
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "desktop notification exited with {}",
+            status
+        ));
+    }
+    Ok(())
+}
+
+/// 执行命令钩子，提醒内容通过环境变量传递，HLT_ALERT_JSON 为完整的 JSON
+async fn notify_command(command: &str, event: &AlertEvent) -> Result<()> {
+    let status = tokio::time::timeout(
+        NOTIFY_TIMEOUT,
+        tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("HLT_ALERT_ID", event.id.to_string())
+            .env("HLT_ALERT_ASSET", &event.asset)
+            .env("HLT_ALERT_CONDITION", &event.condition)
+            .env("HLT_ALERT_MESSAGE", &event.message)
+            .env(
+                "HLT_ALERT_VALUE",
+                event.value.map(|v| v.to_string()).unwrap_or_default(),
+            )
+            .env("HLT_ALERT_JSON", serde_json::to_string(event)?)
+            .kill_on_drop(true)
+            .status(),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("command timed out after {}s", NOTIFY_TIMEOUT.as_secs()))??;
+    if !status.success() {
+        return Err(anyhow::anyhow!("command exited with {}", status));
+    }
+    Ok(())
+}
+
+/// 以 JSON 请求体 POST 提醒，非 2xx 响应视为失败
+async fn notify_webhook(url: &str, event: &AlertEvent) -> Result<()> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(NOTIFY_TIMEOUT)
+        .json(event)
+        .send()
+        .await
+        .map_err(|e| ToolsetError::Network(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(
+            ToolsetError::Network(format!("webhook returned {}", response.status())).into(),
+        );
+    }
+    Ok(())
+}
+
+/// 按提醒的所有通知方式发送，失败的通知记录在 `event.errors` 中
+pub async fn notify(channels: &[AlertChannel], event: &mut AlertEvent) {
+    for channel in channels {
+        let result = match channel {
+            // 响铃写入 stderr，消息由调用方按输出格式打印
+            AlertChannel::Bell => {
+                eprint!("\x07");
+                Ok(())
+            }
+            AlertChannel::Desktop => notify_desktop(event).await,
+            AlertChannel::Command(command) => notify_command(command, event).await,
+            AlertChannel::Webhook(url) => notify_webhook(url, event).await,
+        };
+        if let Err(error) = result {
+            event.errors.push(format!("{}: {}", channel, error));
+        }
+    }
+}
+
+/// 检查一次所有提醒，条件成立且冷却时间已过的提醒发送通知并更新触发时间
+///
+/// 网络错误返回 Err，已检查的提醒的状态保留在 `alerts` 中
+pub async fn tick<B: Backend>(
+    toolset: &Toolset<B>,
+    alerts: &mut BTreeMap<u64, Alert>,
+) -> Result<Vec<AlertEvent>> {
+    let mids = toolset
+        .backend()
+        .all_mids()
+        .await?
+        .into_iter()
+        .filter_map(|(asset, mid)| Some((asset, mid.parse().ok()?)))
+        .collect();
+    let mut snapshot = Snapshot {
+        mids,
+        positions: None,
+        fills: None,
+    };
+    let fills_since = alerts
+        .values()
+        .filter(|a| a.condition == AlertCondition::Fill)
+        .map(|a| a.fills_since + 1)
+        .min()
+        .unwrap_or_default();
+
+    let mut events = vec![];
+    for alert in alerts.values_mut() {
+        let now = now_ms();
+        let is_fill = alert.condition == AlertCondition::Fill;
+        if !is_fill && !alert.ready(now) {
+            continue;
+        }
+        let Some(mut event) = evaluate(toolset, &mut snapshot, alert, fills_since).await? else {
+            continue;
+        };
+        if is_fill && let Some(fills) = &snapshot.fills {
+            alert.fills_since = fills
+                .iter()
+                .filter(|f| f.coin == alert.asset)
+                .map(|f| f.time)
+                .fold(alert.fills_since, u64::max);
+        }
+        notify(&alert.channels, &mut event).await;
+        alert.fired += 1;
+        alert.last_fired = Some(event.time);
+        events.push(event);
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::test_support::toolset;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    type Received = Arc<Mutex<Vec<Value>>>;
+
+    /// 本地 Webhook：`/ok` 记录请求体并返回 200，`/fail` 返回 500
+    fn webhook() -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route(
+                "/ok",
+                post(
+                    |State(received): State<Received>, Json(body): Json<Value>| async move {
+                        received.lock().unwrap().push(body);
+                        StatusCode::OK
+                    },
+                ),
+            )
+            .route(
+                "/fail",
+                post(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            )
+            .with_state(received.clone());
+        let server = axum::Server::bind(&([127, 0, 0, 1], 0).into()).serve(app.into_make_service());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        (url, received)
+    }
+
+    fn alerts(alert: Alert) -> BTreeMap<u64, Alert> {
+        BTreeMap::from([(1, Alert { id: 1, ..alert })])
+    }
+
+    #[tokio::test]
+    async fn webhook_receives_the_event_once_per_cooldown() {
+        let toolset = toolset();
+        let (url, received) = webhook();
+        let channel = AlertChannel::Webhook(format!("{}/ok", url));
+        let alert = Alert::new(
+            "BTC",
+            AlertCondition::Above(49_000.0),
+            vec![channel],
+            Duration::from_secs(60),
+        );
+        let mut alerts = alerts(alert);
+
+        let events = tick(&toolset, &mut alerts).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].errors.is_empty());
+        let body = received.lock().unwrap().clone();
+        assert_eq!(body.len(), 1);
+        assert_eq!(
+            body[0],
+            serde_json::json!({
+                "id": 1,
+                "asset": "BTC",
+                "condition": "BTC above 49000",
+                "message": "BTC price 50000 is above 49000",
+                "value": 50000.0,
+                "time": events[0].time,
+            })
+        );
+
+        // 冷却时间内条件仍成立，不再通知
+        assert!(tick(&toolset, &mut alerts).await.unwrap().is_empty());
+        assert_eq!(received.lock().unwrap().len(), 1);
+        assert_eq!(alerts[&1].fired, 1);
+
+        alerts.get_mut(&1).unwrap().last_fired = Some(0);
+        assert_eq!(tick(&toolset, &mut alerts).await.unwrap().len(), 1);
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn webhook_error_status_is_recorded_in_the_event() {
+        let toolset = toolset();
+        let (url, received) = webhook();
+        let channel = AlertChannel::Webhook(format!("{}/fail", url));
+        let alert = Alert::new(
+            "ETH",
+            AlertCondition::Below(3_100.0),
+            vec![channel.clone()],
+            Duration::from_secs(60),
+        );
+        let mut alerts = alerts(alert);
+
+        let events = tick(&toolset, &mut alerts).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].errors,
+            [format!(
+                "{}: Network error: webhook returned 500 Internal Server Error",
+                channel
+            )]
+        );
+        assert!(received.lock().unwrap().is_empty());
+
+        let error = notify_webhook(&format!("{}/fail", url), &events[0])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ToolsetError>(),
+            Some(ToolsetError::Network(_))
+        ));
+    }
+}
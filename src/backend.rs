@@ -0,0 +1,207 @@
+use crate::action;
+use crate::hyperliquid;
+use crate::types::{Candle, FeeSummary, MarketInfo, SpotMarket, UserFill};
+use anyhow::Result;
+use ethers::types::H160;
+use hyperliquid_rust_sdk::{
+    ClientCancelRequest, ClientModifyRequest, ClientOrderRequest, ExchangeClient,
+    ExchangeResponseStatus, InfoClient, L2SnapshotResponse, OpenOrdersResponse,
+    RecentTradesResponse, UserStateResponse, UserTokenBalanceResponse,
+};
+use std::collections::HashMap;
+use std::future::Future;
+
+/// `hlt` 用到的行情及账户查询接口
+pub trait InfoBackend: Send + Sync {
+    /// 所有标的的中间价
+    fn all_mids(&self) -> impl Future<Output = Result<HashMap<String, String>>> + Send;
+
+    fn user_state(&self, address: H160) -> impl Future<Output = Result<UserStateResponse>> + Send;
+
+    fn user_token_balances(
+        &self,
+        address: H160,
+    ) -> impl Future<Output = Result<UserTokenBalanceResponse>> + Send;
+
+    fn open_orders(
+        &self,
+        address: H160,
+    ) -> impl Future<Output = Result<Vec<OpenOrdersResponse>>> + Send;
+
+    /// 下单时设置了 cloid 的挂单，订单ID -> cloid
+    fn open_order_cloids(
+        &self,
+        address: H160,
+    ) -> impl Future<Output = Result<HashMap<u64, String>>> + Send;
+
+    fn recent_trades(
+        &self,
+        asset: &str,
+    ) -> impl Future<Output = Result<Vec<RecentTradesResponse>>> + Send;
+
+    /// 所有永续合约的元数据及市场状态
+    fn markets(&self) -> impl Future<Output = Result<Vec<MarketInfo>>> + Send;
+
+    fn fees(&self, address: H160) -> impl Future<Output = Result<FeeSummary>> + Send;
+
+    /// 指定时间之后的成交记录
+    fn user_fills_by_time(
+        &self,
+        address: H160,
+        start_time: u64,
+    ) -> impl Future<Output = Result<Vec<UserFill>>> + Send;
+
+    /// 现货交易对名称 -> (基础代币, 计价代币)
+    fn spot_pairs(&self) -> impl Future<Output = Result<HashMap<String, (String, String)>>> + Send;
+
+    /// 所有现货交易对的精度及中间价
+    fn spot_markets(&self) -> impl Future<Output = Result<Vec<SpotMarket>>> + Send;
+
+    /// 标的 `start_time` 之后的K线，按开盘时间排序
+    fn candles(
+        &self,
+        asset: &str,
+        interval: &str,
+        start_time: u64,
+    ) -> impl Future<Output = Result<Vec<Candle>>> + Send;
+
+    /// 标的的盘口快照，`levels` 依次为买盘及卖盘，都从最优价开始
+    fn l2_book(&self, asset: &str) -> impl Future<Output = Result<L2SnapshotResponse>> + Send;
+}
+
+/// `hlt` 用到的交易接口
+pub trait ExchangeBackend: Send + Sync {
+    /// 标的在交易所中的编号，未知标的返回 None
+    fn asset_index(&self, asset: &str) -> Option<u32>;
+
+    /// 标的的数量精度，未知标的返回 None
+    fn sz_decimals(&self, asset: &str) -> Option<u32>;
+
+    fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send;
+
+    fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send;
+
+    /// 修改挂单，交易所可能为修改后的订单分配新的订单ID
+    fn bulk_modify(
+        &self,
+        modifies: Vec<ClientModifyRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send;
+
+    /// 在指定时间(毫秒时间戳)撤销所有挂单，为 None 时取消已设置的定时撤单
+    fn schedule_cancel(
+        &self,
+        time: Option<u64>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + Send;
+}
+
+/// 同时提供查询及交易接口的后端
+pub trait Backend: InfoBackend + ExchangeBackend {}
+
+impl<T: InfoBackend + ExchangeBackend> Backend for T {}
+
+/// 基于 SDK 客户端访问 Hyperliquid 的后端
+pub struct HyperliquidBackend {
+    pub info_client: InfoClient,
+    pub exchange_client: ExchangeClient,
+    /// 现货交易对(基础代币/计价代币) -> 数量精度，SDK 只保存了永续合约的精度
+    pub spot_sz_decimals: HashMap<String, u32>,
+}
+
+impl InfoBackend for HyperliquidBackend {
+    async fn all_mids(&self) -> Result<HashMap<String, String>> {
+        Ok(self.info_client.all_mids().await?)
+    }
+
+    async fn user_state(&self, address: H160) -> Result<UserStateResponse> {
+        Ok(self.info_client.user_state(address).await?)
+    }
+
+    async fn user_token_balances(&self, address: H160) -> Result<UserTokenBalanceResponse> {
+        Ok(self.info_client.user_token_balances(address).await?)
+    }
+
+    async fn open_orders(&self, address: H160) -> Result<Vec<OpenOrdersResponse>> {
+        Ok(self.info_client.open_orders(address).await?)
+    }
+
+    async fn open_order_cloids(&self, address: H160) -> Result<HashMap<u64, String>> {
+        hyperliquid::open_order_cloids(&self.info_client, address).await
+    }
+
+    async fn recent_trades(&self, asset: &str) -> Result<Vec<RecentTradesResponse>> {
+        Ok(self.info_client.recent_trades(asset.to_string()).await?)
+    }
+
+    async fn markets(&self) -> Result<Vec<MarketInfo>> {
+        hyperliquid::markets(&self.info_client).await
+    }
+
+    async fn fees(&self, address: H160) -> Result<FeeSummary> {
+        hyperliquid::fees(&self.info_client, address).await
+    }
+
+    async fn user_fills_by_time(&self, address: H160, start_time: u64) -> Result<Vec<UserFill>> {
+        hyperliquid::user_fills_by_time(&self.info_client, address, start_time).await
+    }
+
+    async fn spot_pairs(&self) -> Result<HashMap<String, (String, String)>> {
+        hyperliquid::spot_pairs(&self.info_client).await
+    }
+
+    async fn spot_markets(&self) -> Result<Vec<SpotMarket>> {
+        hyperliquid::spot_markets(&self.info_client).await
+    }
+
+    async fn candles(&self, asset: &str, interval: &str, start_time: u64) -> Result<Vec<Candle>> {
+        hyperliquid::candles(&self.info_client, asset, interval, start_time).await
+    }
+
+    async fn l2_book(&self, asset: &str) -> Result<L2SnapshotResponse> {
+        Ok(self.info_client.l2_snapshot(asset.to_string()).await?)
+    }
+}
+
+impl ExchangeBackend for HyperliquidBackend {
+    fn asset_index(&self, asset: &str) -> Option<u32> {
+        self.exchange_client.coin_to_asset.get(asset).copied()
+    }
+
+    fn sz_decimals(&self, asset: &str) -> Option<u32> {
+        self.exchange_client
+            .meta
+            .universe
+            .iter()
+            .find(|m| m.name == asset)
+            .map(|m| m.sz_decimals)
+            .or_else(|| self.spot_sz_decimals.get(asset).copied())
+    }
+
+    async fn bulk_order(&self, orders: Vec<ClientOrderRequest>) -> Result<ExchangeResponseStatus> {
+        Ok(self.exchange_client.bulk_order(orders, None).await?)
+    }
+
+    async fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        Ok(self.exchange_client.bulk_cancel(cancels, None).await?)
+    }
+
+    async fn bulk_modify(
+        &self,
+        modifies: Vec<ClientModifyRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        Ok(self.exchange_client.bulk_modify(modifies, None).await?)
+    }
+
+    async fn schedule_cancel(&self, time: Option<u64>) -> Result<ExchangeResponseStatus> {
+        hyperliquid::exchange_request(&self.exchange_client, action::schedule_cancel_action(time))
+            .await
+    }
+}
@@ -0,0 +1,311 @@
+use crate::error::ToolsetError;
+use crate::order::{LimitType, OrderKind, OrderSide, OrderSpec, TriggerType};
+use crate::types::OrderResult;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 批量文件格式，按扩展名识别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchFormat {
+    Csv,
+    Json,
+    Yaml,
+}
+
+impl BatchFormat {
+    pub fn from_path(path: &Path) -> Result<Self, ToolsetError> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match extension.as_str() {
+            "csv" => Ok(BatchFormat::Csv),
+            "json" => Ok(BatchFormat::Json),
+            "yaml" | "yml" => Ok(BatchFormat::Yaml),
+            _ => Err(ToolsetError::Validation(format!(
+                "Unsupported batch file {}, expected .csv, .json or .yaml",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// 批量下单文件中的一行，字段与 `hlt order` 的参数对应
+///
+/// 设置了 `trigger_price` 时为触发器订单，否则为限价订单
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OrderRow {
+    pub asset: String,
+    /// buy / sell
+    pub side: String,
+    pub quantity: f64,
+    #[serde(default)]
+    pub price: Option<f64>,
+    /// 限价订单类型 Gtc / Ioc / Alo，默认 Gtc
+    #[serde(default)]
+    pub limit: Option<String>,
+    #[serde(default)]
+    pub trigger_price: Option<f64>,
+    /// tp / sl
+    #[serde(default)]
+    pub tpsl: Option<String>,
+    /// 触发后以 `price` 执行，否则按市场价执行
+    #[serde(default)]
+    pub limit_price: Option<bool>,
+    #[serde(default)]
+    pub reduce_only: Option<bool>,
+    #[serde(default)]
+    pub cloid: Option<String>,
+}
+
+fn parse_enum<T: ValueEnum>(field: &str, value: &str) -> Result<T, String> {
+    T::from_str(value, true).map_err(|_| format!("Invalid {}: {}", field, value))
+}
+
+impl OrderRow {
+    pub fn into_spec(self) -> Result<OrderSpec, String> {
+        let kind = match self.trigger_price {
+            Some(trigger_price) => OrderKind::Trigger {
+                trigger_price,
+                is_market: !self.limit_price.unwrap_or(false),
+                tpsl: parse_enum::<TriggerType>(
+                    "tpsl",
+                    self.tpsl
+                        .as_deref()
+                        .ok_or("tpsl is required for trigger orders")?,
+                )?,
+            },
+            None => OrderKind::Limit {
+                tif: match self.limit.as_deref() {
+                    Some(limit) => parse_enum::<LimitType>("limit", limit)?,
+                    None => LimitType::Gtc,
+                },
+            },
+        };
+        let cloid = self
+            .cloid
+            .filter(|c| !c.is_empty())
+            .map(|c| uuid::Uuid::from_str(&c).map_err(|e| format!("Invalid cloid: {}", e)))
+            .transpose()?;
+        Ok(OrderSpec {
+            asset: self.asset,
+            side: parse_enum::<OrderSide>("side", &self.side)?,
+            kind,
+            reduce_only: self.reduce_only.unwrap_or(false),
+            price: self.price,
+            quantity: self.quantity,
+            cloid,
+        })
+    }
+}
+
+/// 批量撤单文件中的一行
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CancelRow {
+    pub asset: String,
+    pub oid: u64,
+}
+
+/// 批量操作中单行的执行结果，写入结果文件
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    /// 文件中的行号，从 1 开始，不含表头
+    pub row: usize,
+    pub asset: String,
+    pub status: String,
+    pub oid: Option<u64>,
+    pub filled_size: Option<f64>,
+    pub average_price: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    pub fn new(row: usize, asset: &str, result: OrderResult) -> Self {
+        Self {
+            row,
+            asset: asset.to_string(),
+            status: result.status,
+            oid: result.oid,
+            filled_size: result.filled_size,
+            average_price: result.average_price,
+            error: result.error,
+        }
+    }
+}
+
+/// 读取批量文件中的所有行
+pub fn load_rows<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let format = BatchFormat::from_path(path)?;
+    let content = fs::read_to_string(path).map_err(|e| {
+        ToolsetError::Validation(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    let invalid = |e: String| ToolsetError::Validation(format!("{}: {}", path.display(), e));
+    let rows = match format {
+        BatchFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<T>, _>>()
+            .map_err(|e| invalid(e.to_string()))?,
+        BatchFormat::Json => serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+        BatchFormat::Yaml => serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+    };
+    Ok(rows)
+}
+
+/// 默认的结果文件：与输入文件同目录，`orders.csv` 的结果为 `orders.result.csv`
+pub fn result_path(input: &Path) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("batch");
+    let extension = input.extension().and_then(|e| e.to_str()).unwrap_or("csv");
+    input.with_file_name(format!("{}.result.{}", stem, extension))
+}
+
+/// 按扩展名对应的格式写入结果文件
+pub fn write_results(path: &Path, results: &[BatchResult]) -> Result<()> {
+    let content = match BatchFormat::from_path(path)? {
+        BatchFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for result in results {
+                writer.serialize(result)?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
+        BatchFormat::Json => serde_json::to_string_pretty(results)?,
+        BatchFormat::Yaml => serde_yaml::to_string(results)?,
+    };
+    fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store;
+
+    fn write(name: &str, content: &str) -> PathBuf {
+        let path = store::data_dir().unwrap().join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn csv_rows_parse_into_limit_and_trigger_orders() {
+        let path = write(
+            "orders.csv",
+            "asset,side,quantity,price,limit,trigger_price,tpsl,limit_price,reduce_only,cloid
+BTC, buy ,0.01,49000,,,,,,
+ETH,sell,0.5,2900,ioc,,,,true,
+BTC,sell,0.01,,,48000,sl,,true,1234567890abcdef1234567890abcdef
+",
+        );
+        let specs: Vec<OrderSpec> = load_rows::<OrderRow>(&path)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.into_spec().unwrap())
+            .collect();
+        assert_eq!(specs.len(), 3);
+        assert_eq!(specs[0].side, OrderSide::Buy);
+        assert!(matches!(
+            specs[0].kind,
+            OrderKind::Limit {
+                tif: LimitType::Gtc
+            }
+        ));
+        assert_eq!(
+            (specs[0].price, specs[0].reduce_only),
+            (Some(49_000.0), false)
+        );
+        assert!(matches!(
+            specs[1].kind,
+            OrderKind::Limit {
+                tif: LimitType::Ioc
+            }
+        ));
+        assert!(specs[1].reduce_only);
+        assert!(matches!(
+            specs[2].kind,
+            OrderKind::Trigger {
+                trigger_price: 48_000.0,
+                is_market: true,
+                tpsl: TriggerType::Sl
+            }
+        ));
+        assert_eq!(
+            specs[2].cloid.unwrap().to_string(),
+            "12345678-90ab-cdef-1234-567890abcdef"
+        );
+    }
+
+    #[test]
+    fn invalid_rows_are_reported() {
+        let row = |yaml: &str| serde_yaml::from_str::<OrderRow>(yaml).unwrap();
+        assert_eq!(
+            row("{asset: BTC, side: long, quantity: 1}")
+                .into_spec()
+                .unwrap_err(),
+            "Invalid side: long"
+        );
+        assert_eq!(
+            row("{asset: BTC, side: buy, quantity: 1, trigger_price: 48000}")
+                .into_spec()
+                .unwrap_err(),
+            "tpsl is required for trigger orders"
+        );
+        assert!(
+            row("{asset: BTC, side: buy, quantity: 1, cloid: nope}")
+                .into_spec()
+                .unwrap_err()
+                .starts_with("Invalid cloid")
+        );
+
+        // 未知字段及格式错误的文件整体拒绝，错误中包含文件名
+        let path = write("orders.json", r#"[{"asset": "BTC", "size": 1}]"#);
+        let error = load_rows::<OrderRow>(&path).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ToolsetError>(),
+            Some(ToolsetError::Validation(message)) if message.contains("orders.json")
+        ));
+        let error = load_rows::<OrderRow>(Path::new("orders.txt")).unwrap_err();
+        assert!(error.to_string().contains("Unsupported batch file"));
+    }
+
+    #[test]
+    fn results_are_written_next_to_the_input() {
+        assert_eq!(
+            result_path(Path::new("/tmp/batch/orders.csv")),
+            Path::new("/tmp/batch/orders.result.csv")
+        );
+        assert_eq!(
+            result_path(Path::new("cancels.yaml")),
+            Path::new("cancels.result.yaml")
+        );
+        assert_eq!(
+            result_path(Path::new("orders")),
+            Path::new("orders.result.csv")
+        );
+
+        let path = store::data_dir().unwrap().join("orders.result.csv");
+        let results = [BatchResult::new(
+            1,
+            "BTC",
+            OrderResult::failed("Insufficient margin"),
+        )];
+        write_results(&path, &results).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "row,asset,status,oid,filledSize,averagePrice,error\n1,BTC,error,,,,Insufficient margin\n"
+        );
+    }
+}
@@ -1,15 +1,36 @@
 use anyhow::Result;
+use chrono::NaiveTime;
 use clap::builder::ArgPredicate;
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Parser, Subcommand};
+use ethers::types::H160;
 
-use hyperliquid_rust_sdk::{
-    ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger,
-    ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus, InfoClient,
+use hyperliquid_toolset::alert::{
+    self, Alert, AlertChannel, AlertCondition, AlertEvent, MIN_CHANGE_WINDOW,
 };
-use hyperliquid_toolset::HyperLiquidConfig;
-use hyperliquid_toolset::tui::LivePanel;
-use hyperliquid_toolset::types::PriceIndex;
+use hyperliquid_toolset::backend::Backend;
+use hyperliquid_toolset::batch::{self, BatchResult, CancelRow, OrderRow};
+use hyperliquid_toolset::config::{Config, ConfirmSettings, DEFAULT_PROFILE};
+use hyperliquid_toolset::cost::CostMethod;
+use hyperliquid_toolset::dca::{self, DcaPlan, MIN_DCA_INTERVAL};
+use hyperliquid_toolset::error::{ErrorReport, ToolsetError};
+use hyperliquid_toolset::exporter::{self, MIN_SCRAPE_INTERVAL};
+use hyperliquid_toolset::grid::{self, Grid, GridSpacing, GridStatus};
+use hyperliquid_toolset::oco::{self, OcoEvent, OcoPair, OcoStatus};
+use hyperliquid_toolset::order::{LimitType, OrderKind, OrderSide, OrderSpec, TriggerType};
+use hyperliquid_toolset::output::OutputFormat;
+use hyperliquid_toolset::paper::{DEFAULT_PAPER_BALANCE, PaperBackend};
+use hyperliquid_toolset::rules::{self, RuleState};
+use hyperliquid_toolset::serve;
+use hyperliquid_toolset::stream;
+use hyperliquid_toolset::trail::{self, TrailDistance, TrailEvent, TrailingStop};
+use hyperliquid_toolset::tui::{LivePanel, TableData};
+use hyperliquid_toolset::types::{self, BookLevel, OrderResult, PriceIndex, ScreenSort, Trade};
 use hyperliquid_toolset::ui;
+use hyperliquid_toolset::{HyperLiquidConfig, Toolset};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::str::FromStr;
 use std::time::Duration;
 use tokio::time::sleep;
@@ -17,26 +38,39 @@ use tokio::time::sleep;
 #[derive(Debug, Parser)]
 #[command(author, version, about="A cmd hyperliquid toolset for traders", long_about = None)]
 struct Cli {
+    /// 输出格式
+    #[arg(long, global = true, default_value = "table")]
+    output: OutputFormat,
+
+    /// 使用的配置，对应 config.toml 中的 [profiles.<名称>]
+    #[arg(long, global = true, default_value = DEFAULT_PROFILE)]
+    profile: String,
+
+    /// 跳过下单确认，超过配置的名义价值阈值时仍需确认
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// 超出风控限制时，经确认后仍然下单
+    #[arg(long, global = true)]
+    override_risk: bool,
+
+    /// 只预览需要签名的操作及请求体，不会签名或发送
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// 模拟交易：使用本地模拟账户，按实时行情撮合，不会动用真实资金
+    #[arg(long, global = true)]
+    paper: bool,
+
+    /// 模拟交易使用录制的行情文件(`hlt markets --output json` 的输出)代替实时行情；
+    /// 录制行情只有中间价，没有盘口，订单不会按盘口深度部分成交
+    #[arg(long, global = true, requires = "paper")]
+    paper_feed: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
-/// 限价订单类型
-/// - Alo: Add Liquidity Only
-/// - Ioc: Immediate or cancel
-/// - Gtc: Good till cancel
-#[derive(Debug, Clone, ValueEnum, Default, PartialEq, Eq)]
-#[clap(rename_all = "verbatim")]
-enum LimitType {
-    /// 订单有效直到取消或成交，适合长期挂单，可能为 Maker 或 Taker
-    #[default]
-    Gtc,
-    /// 立即成交或取消，适合快速执行，通常为 Taker
-    Ioc,
-    /// 仅添加流动性，确保 Maker 费率，适合做市和成本优化
-    Alo,
-}
-
 /// 订单类型
 #[derive(Debug, Clone, Subcommand)]
 enum OrderType {
@@ -60,25 +94,6 @@ enum OrderType {
     },
 }
 
-/// 订单方向
-#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
-#[clap(rename_all = "lower")]
-enum OrderSide {
-    /// 订单方向: buy
-    Buy,
-    /// 订单方向: sell
-    Sell,
-}
-
-#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
-#[clap(rename_all = "lower")]
-enum TriggerType {
-    /// 触发类型: Take Profit 止赢
-    Tp,
-    /// 触发类型: Stop Loss 止损
-    Sl,
-}
-
 #[derive(Debug, Parser)]
 struct OrderRequest {
     /// 交易对
@@ -124,71 +139,576 @@ struct CancelRequest {
 enum Commands {
     /// 获取所有标的价格
     AllMids {
-        /// 是否动态刷新价格
+        /// 是否动态刷新价格，通过 WebSocket 推送更新
+        #[arg(short, long, action)]
+        live: bool,
+        /// 使用录制行情(--paper-feed)时的刷新间隔,单位秒
+        #[arg(short, long, default_value = "5")]
+        interval: Option<u64>,
+    },
+    /// 查看最新公开成交
+    Trades {
+        /// 交易对
+        asset: String,
+        /// 是否动态刷新成交，通过 WebSocket 推送更新
+        #[arg(short, long, action)]
+        live: bool,
+        /// 使用录制行情(--paper-feed)时的刷新间隔,单位秒
+        #[arg(short, long, default_value = "5")]
+        interval: Option<u64>,
+        /// 大单阈值，名义价值(美元)不低于该值的成交会高亮显示
+        #[arg(short = 'n', long, default_value = "100000")]
+        large_notional: f64,
+    },
+    /// 查看盘口
+    Book {
+        /// 交易对
+        asset: String,
+        /// 买卖每侧显示的档数
+        #[arg(short, long, default_value = "10")]
+        depth: usize,
+        /// 是否动态刷新盘口，通过 WebSocket 推送更新
+        #[arg(short, long, action)]
+        live: bool,
+        /// 使用录制行情(--paper-feed)时的刷新间隔,单位秒
+        #[arg(short, long, default_value = "5")]
+        interval: Option<u64>,
+    },
+    /// 查看单个永续合约的市场信息
+    Info {
+        /// 交易对
+        asset: String,
+    },
+    /// 查看所有永续合约的市场信息
+    Markets,
+    /// 按涨跌幅、成交额、未平仓量或资金费率筛选市场
+    Screen {
+        /// 排序方式
+        #[arg(short, long, default_value = "change")]
+        sort: ScreenSort,
+        /// 显示前 N 个市场
+        #[arg(short, long, default_value = "20")]
+        top: usize,
+        /// 最低24小时成交额(美元)
+        #[arg(short, long, default_value = "0")]
+        min_volume: f64,
+        /// 是否动态刷新排名
         #[arg(short, long, action)]
         live: bool,
         /// 刷新时间间隔,单位秒
         #[arg(short, long, default_value = "5")]
         interval: Option<u64>,
     },
+    /// 查看手续费率及 API 请求额度使用情况
+    Fees,
     /// 查看账户余额
     Balance {
         /// 是否显示所有资产
         #[arg(short, long)]
         all: bool,
+        /// 现货平均成本计算方式
+        #[arg(short, long, default_value = "fifo")]
+        cost_method: CostMethod,
     },
     /// 查询活跃订单
-    Orders,
+    Orders {
+        /// 是否动态刷新挂单，通过 WebSocket 订单推送更新
+        #[arg(short, long, action)]
+        live: bool,
+    },
     /// 查看仓位
-    Positions,
+    Positions {
+        /// 是否动态刷新仓位，成交等账户事件后更新仓位，价格推送时重新估算盈亏
+        #[arg(short, long, action)]
+        live: bool,
+    },
     /// 下单
     Order(OrderRequest),
     /// 取消订单
     Cancel(CancelRequest),
+    /// 从文件批量下单或撤单
+    #[command(subcommand)]
+    Batch(BatchCommand),
+    /// 一键平仓：撤销所有挂单，并以只减仓 Ioc 订单平掉所有仓位
+    Flatten {
+        /// 只处理指定标的，可重复指定，默认为所有标的
+        #[arg(short, long)]
+        asset: Vec<String>,
+        /// 保留挂单，只平仓
+        #[arg(long)]
+        keep_orders: bool,
+        /// 平仓订单限价相对中间价的最大滑点，0.05 表示 5%
+        #[arg(long, default_value = "0.05")]
+        slippage: f64,
+        /// 未能全部平仓时按最新仓位重试的时长，如 30s、2m
+        #[arg(long, value_parser = parse_duration, default_value = "30s")]
+        timeout: Duration,
+    },
+    /// OCO(one-cancels-other)：一个限价单加一个止损单，其中一个成交后撤销或缩减另一个
+    #[command(subcommand)]
+    Oco(OcoCommand),
+    /// 网格交易：在价格区间内挂买卖单，成交后在相邻价位挂反向单赚取差价
+    #[command(subcommand)]
+    Grid(GridCommand),
+    /// 定投：按计划定期以市价买入固定金额
+    #[command(subcommand)]
+    Dca(DcaCommand),
+    /// 价格、涨跌幅、强平距离及成交提醒，通过终端响铃、桌面通知、命令或 Webhook 通知
+    #[command(subcommand)]
+    Alert(AlertCommand),
+    /// 条件单规则：按 YAML 规则文件监控价格、比值、资金费率或 EMA，条件成立时下单或平仓
+    #[command(subcommand)]
+    Rules(RulesCommand),
+    /// 追踪止损：跟随仓位有利方向的极值调整只减仓止损单，不带参数时查看所有追踪止损
+    Trail {
+        /// 交易对，已有追踪止损时继续追踪
+        #[arg(short, long)]
+        asset: Option<String>,
+        /// 止损价与极值的距离，如 1.5% 或 500(价差)
+        #[arg(short, long, requires = "asset")]
+        distance: Option<TrailDistance>,
+        /// 价格到达激活价后才开始追踪
+        #[arg(long, requires = "asset")]
+        activation: Option<f64>,
+        /// 检查间隔，如 5s、1m
+        #[arg(short, long, value_parser = parse_duration, default_value = "5s")]
+        interval: Duration,
+        /// 停止追踪并撤销止损单
+        #[arg(long, requires = "asset", conflicts_with_all = ["distance", "activation"])]
+        cancel: bool,
+    },
+    /// 定时撤销所有挂单(dead man's switch)，不带参数时查看当前状态
+    Deadman {
+        /// 多久之后撤销所有挂单，如 60s、5m，至少 5 秒
+        #[arg(long = "in", value_parser = parse_duration, conflicts_with = "clear")]
+        after: Option<Duration>,
+        /// 持续运行，定期延长撤单时间，进程退出后挂单将在到期时被撤销
+        #[arg(long, requires = "after")]
+        heartbeat: bool,
+        /// 心跳间隔，如 20s，默认为 --in 的三分之一
+        #[arg(long, value_parser = parse_duration, requires = "heartbeat")]
+        interval: Option<Duration>,
+        /// 取消已设置的定时撤单
+        #[arg(long)]
+        clear: bool,
+    },
+    /// 本地 HTTP/JSON 接口：其它程序通过同一个进程查询及下单，无需各自持有私钥
+    Serve {
+        /// 监听地址，默认只接受本机连接
+        #[arg(short, long, default_value = "127.0.0.1:8787")]
+        listen: SocketAddr,
+        /// 访问令牌，未指定时读取 HLT_SERVE_TOKEN 环境变量，都未设置时使用数据目录中自动生成的令牌
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Prometheus 指标：定期抓取账户、挂单、价格及资金费率，在 /metrics 上提供
+    Exporter {
+        /// 监听地址，`:9100` 表示监听所有网卡
+        #[arg(short, long, value_parser = exporter::parse_listen, default_value = "127.0.0.1:9100")]
+        listen: SocketAddr,
+        /// 抓取间隔，如 15s
+        #[arg(short, long, value_parser = parse_duration, default_value = "15s")]
+        interval: Duration,
+    },
+    /// 管理模拟交易账户
+    #[command(subcommand)]
+    Paper(PaperCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum BatchCommand {
+    /// 从 CSV/JSON/YAML 文件批量下单，字段与 order 命令的参数对应
+    Place {
+        /// 订单文件
+        file: PathBuf,
+        /// 结果文件，默认为同目录下的 <文件名>.result.<扩展名>
+        #[arg(short, long)]
+        result: Option<PathBuf>,
+    },
+    /// 从 CSV/JSON/YAML 文件批量撤单，每行包含 asset 及 oid
+    Cancel {
+        /// 撤单文件
+        file: PathBuf,
+        /// 结果文件，默认为同目录下的 <文件名>.result.<扩展名>
+        #[arg(short, long)]
+        result: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum OcoCommand {
+    /// 下一个限价单和一个止损单并监控成交，买入时限价在当前价之下、止损在当前价之上，卖出时相反
+    Place {
+        /// 交易对
+        #[arg(short, long)]
+        asset: String,
+        /// 订单方向
+        #[arg(short, long)]
+        side: OrderSide,
+        /// 数量
+        #[arg(short, long)]
+        quantity: f64,
+        /// 限价单价格
+        #[arg(short, long)]
+        limit: f64,
+        /// 止损单触发价
+        #[arg(long)]
+        stop: f64,
+        /// 止损单触发后以该价格限价执行，否则按市场价执行
+        #[arg(long)]
+        stop_limit: Option<f64>,
+        /// 只减仓
+        #[arg(short, long)]
+        reduce_only: bool,
+        /// 只下单不监控，之后可以用 oco watch 监控
+        #[arg(long)]
+        detach: bool,
+        /// 检查成交的间隔，如 2s
+        #[arg(short, long, value_parser = parse_duration, default_value = "2s")]
+        interval: Duration,
+    },
+    /// 监控所有进行中的 OCO，直到全部完成
+    Watch {
+        /// 检查成交的间隔，如 2s
+        #[arg(short, long, value_parser = parse_duration, default_value = "2s")]
+        interval: Duration,
+    },
+    /// 查看所有 OCO
+    List,
+    /// 撤销 OCO 的两个订单
+    Cancel {
+        /// OCO ID
+        id: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum GridCommand {
+    /// 挂出初始网格并持续运行，成交后在相邻价位补反向单；已有运行中的网格时继续运行
+    Start {
+        /// 交易对
+        #[arg(short, long)]
+        asset: String,
+        /// 区间下限
+        #[arg(long)]
+        lower: Option<f64>,
+        /// 区间上限
+        #[arg(long)]
+        upper: Option<f64>,
+        /// 价位数量，包含上下限
+        #[arg(long)]
+        levels: Option<usize>,
+        /// 投入的 USDC，平均分配到每个挂单价位
+        #[arg(long)]
+        investment: Option<f64>,
+        /// 等比网格，默认为等差网格
+        #[arg(long)]
+        geometric: bool,
+        /// 检查成交的间隔，如 5s
+        #[arg(short, long, value_parser = parse_duration, default_value = "5s")]
+        interval: Duration,
+    },
+    /// 停止网格并撤销所有网格挂单，运行中的 grid start 将在下次检查时退出
+    Stop {
+        /// 交易对
+        #[arg(short, long)]
+        asset: String,
+    },
+    /// 查看所有网格及已实现收益
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+enum DcaCommand {
+    /// 添加定投计划，由 dca run 按计划执行
+    Add {
+        /// 交易对，现货时可以是交易对名称(如 UBTC/USDC)或代币名称
+        #[arg(short, long)]
+        asset: String,
+        /// 每次买入的金额(USDC)
+        #[arg(long)]
+        usd: f64,
+        /// 执行间隔，如 1d、12h，至少 1m
+        #[arg(long, value_parser = parse_duration)]
+        every: Duration,
+        /// 第一次执行的当地时间，如 09:00，默认为立即执行
+        #[arg(long, value_parser = parse_time)]
+        at: Option<NaiveTime>,
+        /// 买入现货，默认为永续合约
+        #[arg(long)]
+        spot: bool,
+        /// 价格高于该值时跳过本次买入
+        #[arg(long)]
+        max_price: Option<f64>,
+        /// 买入限价相对中间价的最大滑点，0.01 表示 1%
+        #[arg(long, default_value = "0.01")]
+        slippage: f64,
+    },
+    /// 查看所有定投计划
+    List,
+    /// 删除定投计划
+    Remove {
+        /// 计划 ID
+        id: u64,
+    },
+    /// 查看定投执行记录
+    History {
+        /// 只显示指定计划的记录
+        #[arg(long)]
+        id: Option<u64>,
+        /// 显示最近的记录数
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+    /// 持续运行，执行到期的定投计划，直到 Ctrl-C
+    Run {
+        /// 检查到期计划的间隔，如 30s
+        #[arg(short, long, value_parser = parse_duration, default_value = "30s")]
+        interval: Duration,
+    },
+}
+
+/// 提醒类型
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum AlertKind {
+    /// 价格高于指定值
+    Above,
+    /// 价格低于指定值
+    Below,
+    /// 时间窗口内的涨跌幅达到指定比例
+    PctChange,
+    /// 仓位与强平价的距离小于指定比例
+    LiqDistance,
+    /// 有新的成交
+    Fill,
+}
+
+#[derive(Debug, Subcommand)]
+enum AlertCommand {
+    /// 添加提醒，如 `alert add BTC above 100000`、`alert add BTC pct-change 5% 1h`、`alert add BTC liq-distance 10%`
+    Add {
+        /// 交易对
+        asset: String,
+        /// 提醒类型
+        kind: AlertKind,
+        /// above/below 为价格，pct-change 为比例及时间窗口，liq-distance 为比例，fill 不需要参数
+        args: Vec<String>,
+        /// 终端响铃，未指定其它通知方式时默认使用
+        #[arg(long)]
+        bell: bool,
+        /// 桌面通知
+        #[arg(long)]
+        desktop: bool,
+        /// 触发时通过 sh -c 执行的命令，提醒内容在 HLT_ALERT_* 环境变量中，可重复指定
+        #[arg(long = "exec")]
+        command: Vec<String>,
+        /// 触发时以 JSON 请求体 POST 到该地址，可重复指定
+        #[arg(long)]
+        webhook: Vec<String>,
+        /// 条件持续成立时两次通知的最短间隔，如 5m、1h
+        #[arg(long, value_parser = parse_duration, default_value = "5m")]
+        cooldown: Duration,
+    },
+    /// 查看所有提醒
+    List,
+    /// 删除提醒
+    Remove {
+        /// 提醒 ID
+        id: u64,
+    },
+    /// 通过提醒的所有通知方式发送一条测试通知
+    Test {
+        /// 提醒 ID
+        id: u64,
+    },
+    /// 持续检查所有提醒并发送通知，直到 Ctrl-C
+    Watch {
+        /// 检查间隔，如 10s
+        #[arg(short, long, value_parser = parse_duration, default_value = "10s")]
+        interval: Duration,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum RulesCommand {
+    /// 持续运行，条件由不成立变为成立时执行规则的动作，直到 Ctrl-C 或所有规则都已执行
+    Run {
+        /// 规则文件(YAML)
+        file: PathBuf,
+        /// 检查条件的间隔，如 10s
+        #[arg(short, long, value_parser = parse_duration, default_value = "10s")]
+        interval: Duration,
+        /// 清除文件中规则的状态，已执行过的规则重新开始
+        #[arg(long)]
+        reset: bool,
+    },
+    /// 校验规则文件，并按当前行情显示每条规则的条件及执行次数
+    Check {
+        /// 规则文件(YAML)
+        file: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum PaperCommand {
+    /// 重置模拟账户，清空仓位、挂单及成交记录
+    Reset {
+        /// 初始 USDC 余额
+        #[arg(short, long, default_value_t = DEFAULT_PAPER_BALANCE)]
+        balance: f64,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     dotenv::dotenv().ok();
     let cli = Cli::parse();
-    let hl_config = HyperLiquidConfig::new();
+    let output = cli.output;
 
-    let info_client = InfoClient::new(None, None).await?;
-    let wallet = hl_config.wallet()?;
-    let exchange_client = ExchangeClient::new(None, wallet, None, None, None).await?;
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            let report = ErrorReport::new(&error);
+            if output.is_table() {
+                eprintln!("  🔴 Error: {}", report.message);
+            } else {
+                eprintln!(
+                    "{}",
+                    serde_json::json!({ "error": serde_json::to_value(&report).unwrap_or_default() })
+                );
+            }
+            ExitCode::from(report.exit_code)
+        }
+    }
+}
 
-    match cli.command {
-        Commands::Balance { all } => {
-            let (state, balance) = tokio::join! {
-                info_client.user_state(hl_config.account_address),
-                info_client.user_token_balances(hl_config.account_address),
-            };
-            let state = state?;
-            let balance = balance?;
-            ui::draw_balance_table(state, balance.balances, all);
+async fn run(cli: Cli) -> Result<()> {
+    let output = cli.output;
+    if let Commands::Paper(PaperCommand::Reset { balance }) = cli.command {
+        PaperBackend::reset(balance)?;
+        if output.is_table() {
+            println!("  🟢 Paper account reset with {} USDC", balance);
+        }
+        return Ok(());
+    }
+
+    let profile = Config::load()?.profile(&cli.profile)?;
+    let confirm = profile.confirm.clone();
+    let options = Options {
+        output,
+        dry_run: cli.dry_run,
+        override_risk: cli.override_risk,
+        yes: cli.yes,
+        paper: cli.paper,
+        recorded: cli.paper_feed.is_some(),
+    };
+    if cli.paper {
+        if output.is_table() {
+            println!("  📝 Paper trading");
+        }
+        let backend = PaperBackend::open(cli.paper_feed.as_deref()).await?;
+        // 模拟交易单独计算每日下单数
+        let toolset = Toolset::with_backend(H160::zero(), backend)
+            .with_risk(&format!("paper-{}", cli.profile), profile.risk);
+        execute(toolset, cli.command, options, &confirm).await
+    } else {
+        let hl_config = HyperLiquidConfig::new()?;
+        let toolset = Toolset::new(&hl_config)
+            .await?
+            .with_risk(&cli.profile, profile.risk);
+        execute(toolset, cli.command, options, &confirm).await
+    }
+}
+
+/// 影响命令执行方式的全局参数
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    output: OutputFormat,
+    dry_run: bool,
+    override_risk: bool,
+    yes: bool,
+    /// 模拟账户不在交易所，账户类实时面板没有推送
+    paper: bool,
+    /// 使用录制行情，行情类实时面板改为轮询
+    recorded: bool,
+}
+
+async fn execute<B: Backend + 'static>(
+    toolset: Toolset<B>,
+    command: Commands,
+    options: Options,
+    confirm: &ConfirmSettings,
+) -> Result<()> {
+    let Options {
+        output, dry_run, ..
+    } = options;
+    match command {
+        Commands::Balance { all, cost_method } => {
+            let report = toolset.balances(all, cost_method).await?;
+            if let Some(error) = &report.cost_error {
+                eprintln!("  🟠 Average cost unavailable: {}", error);
+            }
+            output.one(&report, ui::draw_balance_table)?;
         }
-        Commands::Orders => {
-            let orders = info_client.open_orders(hl_config.account_address).await?;
-            ui::draw_orders_table(orders);
+        Commands::Orders { live: true } => {
+            ensure_table(output)?;
+            ensure_account_stream(options)?;
+            let mut ui = LivePanel::with_stream(stream::orders(&toolset).await?);
+            let _ = ui.run_tui().await;
+        }
+        Commands::Orders { live: false } => {
+            let orders = toolset.open_orders().await?;
+            output.rows(&orders, ui::draw_orders_table)?;
+            if output.is_table() {
+                let status = toolset.dead_man_switch()?;
+                if status.cancel_at.is_some() {
+                    ui::draw_dead_man_switch(&status);
+                }
+            }
         }
         Commands::AllMids { live, interval } => {
-            if live {
+            if live && !options.recorded {
+                ensure_table(output)?;
+                let mut ui = LivePanel::with_stream(stream::mids().await?);
+                let _ = ui.run_tui().await;
+            } else if live {
+                ensure_table(output)?;
                 let interval = interval.unwrap_or(5);
                 let mut ui = LivePanel::with_updater(|| async {
                     let _ = sleep(Duration::from_secs(interval)).await;
-                    let result = info_client.all_mids().await;
-                    match result {
-                        Ok(response) => {
-                            let mids: Vec<PriceIndex> = response
-                                .iter()
-                                .filter(|(k, _)| !k.starts_with("@"))
-                                .map(|(k, v)| PriceIndex {
-                                    asset: k.clone(),
-                                    price: v.clone().parse::<f64>().unwrap_or(0.0),
-                                })
-                                .collect();
-                            mids
+                    match toolset.mids().await {
+                        Ok(mids) => mids,
+                        Err(error) => {
+                            println!("  🔴 Error: {}", error);
+                            vec![]
                         }
+                    }
+                });
+                let _ = ui.run_tui().await;
+            } else {
+                let mut mids = toolset.mids().await?;
+                mids.sort_by(PriceIndex::comparator);
+                output.rows(&mids, ui::draw_all_mids_table)?;
+            }
+        }
+        Commands::Trades {
+            asset,
+            live,
+            interval,
+            large_notional,
+        } => {
+            if live && !options.recorded {
+                ensure_table(output)?;
+                let mut ui =
+                    LivePanel::with_stream(stream::trades(&toolset, &asset, large_notional).await?);
+                let _ = ui.run_tui().await;
+            } else if live {
+                ensure_table(output)?;
+                let interval = interval.unwrap_or(5);
+                let mut ui = LivePanel::with_updater(|| async {
+                    let _ = sleep(Duration::from_secs(interval)).await;
+                    match toolset.trades(&asset, large_notional).await {
+                        Ok(trades) => trades,
                         Err(error) => {
                             println!("  🔴 Error: {}", error);
                             vec![]
@@ -197,173 +717,1466 @@ async fn main() -> Result<()> {
                 });
                 let _ = ui.run_tui().await;
             } else {
-                let result = info_client.all_mids().await;
-                match result {
-                    Ok(response) => {
-                        let mids: Vec<PriceIndex> = response
-                            .iter()
-                            .map(|(k, v)| PriceIndex {
-                                asset: k.clone(),
-                                price: v.clone().parse::<f64>().unwrap_or(0.0),
-                            })
-                            .collect();
-                        ui::draw_all_mids_table(&mids);
+                let mut trades = toolset.trades(&asset, large_notional).await?;
+                trades.sort_by(Trade::comparator);
+                output.rows(&trades, |trades| ui::draw_trades_table(&asset, trades))?;
+            }
+        }
+        Commands::Book {
+            asset,
+            depth,
+            live,
+            interval,
+        } => {
+            if live && !options.recorded {
+                ensure_table(output)?;
+                let mut ui = LivePanel::with_stream(stream::book(&asset, depth).await?);
+                let _ = ui.run_tui().await;
+            } else if live {
+                ensure_table(output)?;
+                let interval = interval.unwrap_or(5);
+                let mut ui = LivePanel::with_updater(|| async {
+                    let _ = sleep(Duration::from_secs(interval)).await;
+                    match toolset.book(&asset, depth).await {
+                        Ok(levels) => levels,
+                        Err(error) => {
+                            println!("  🔴 Error: {}", error);
+                            vec![]
+                        }
                     }
+                });
+                let _ = ui.run_tui().await;
+            } else {
+                let mut levels = toolset.book(&asset, depth).await?;
+                levels.sort_by(BookLevel::comparator);
+                output.rows(&levels, |levels| ui::draw_book_table(&asset, levels))?;
+            }
+        }
+        Commands::Info { asset } => {
+            let market = toolset.market(&asset).await?;
+            output.one(&market, ui::draw_market_info)?;
+        }
+        Commands::Markets => {
+            let markets = toolset.markets().await?;
+            output.rows(&markets, ui::draw_markets_table)?;
+        }
+        Commands::Screen {
+            sort,
+            top,
+            min_volume,
+            live,
+            interval,
+        } => {
+            if live {
+                ensure_table(output)?;
+                let interval = interval.unwrap_or(5);
+                let mut ui = LivePanel::with_updater(|| async {
+                    let _ = sleep(Duration::from_secs(interval)).await;
+                    match toolset.markets().await {
+                        Ok(markets) => types::rank_markets(markets, sort, top, min_volume),
+                        Err(error) => {
+                            println!("  🔴 Error: {}", error);
+                            vec![]
+                        }
+                    }
+                });
+                let _ = ui.run_tui().await;
+            } else {
+                let markets = toolset.markets().await?;
+                output.rows(
+                    &types::rank_markets(markets, sort, top, min_volume),
+                    ui::draw_screen_table,
+                )?;
+            }
+        }
+        Commands::Fees => {
+            let fees = toolset.fees().await?;
+            output.one(&fees, ui::draw_fees_table)?;
+        }
+        Commands::Positions { live: true } => {
+            ensure_table(output)?;
+            ensure_account_stream(options)?;
+            let mut ui = LivePanel::with_stream(stream::positions(&toolset).await?);
+            let _ = ui.run_tui().await;
+        }
+        Commands::Positions { live: false } => {
+            let positions = toolset.positions().await?;
+            output.rows(&positions, ui::draw_user_positions_table)?;
+        }
+        Commands::Order(request) => {
+            let spec = request.into_spec()?;
+            if dry_run {
+                let preview = toolset.preview_order(&spec).await?;
+                output.one(&preview, ui::draw_order_preview)?;
+                return Ok(());
+            }
+            if options.override_risk {
+                confirm_risk_override(&toolset, std::slice::from_ref(&spec)).await?;
+            }
+            confirm_orders(&toolset, std::slice::from_ref(&spec), options.yes, confirm).await?;
+            let results = toolset.place_order(&spec).await?;
+            output.rows(&results, ui::draw_order_results)?;
+            if let Some(error) = ToolsetError::from_results(&results) {
+                return Err(error.into());
+            }
+        }
+        Commands::Cancel(CancelRequest { asset, order_id }) => {
+            if dry_run {
+                let preview = toolset.preview_cancel(&asset, order_id).await?;
+                output.one(&preview, ui::draw_cancel_preview)?;
+                return Ok(());
+            }
+            let results = toolset.cancel(&asset, order_id).await?;
+            output.rows(&results, ui::draw_cancel_results)?;
+            if let Some(error) = ToolsetError::from_results(&results) {
+                return Err(error.into());
+            }
+        }
+        Commands::Batch(BatchCommand::Place { file, result }) => {
+            let rows: Vec<OrderRow> = batch::load_rows(&file)?;
+            let mut assets = vec![];
+            let mut specs = vec![];
+            let mut errors = vec![];
+            for (i, row) in rows.into_iter().enumerate() {
+                let asset = row.asset.clone();
+                match row
+                    .into_spec()
+                    .map_err(anyhow::Error::msg)
+                    .and_then(|spec| toolset.validate_order(&spec).map(|_| spec))
+                {
+                    Ok(spec) => specs.push(spec),
                     Err(error) => {
-                        println!("  🔴 Error: {}", error);
+                        let message = match error.downcast_ref::<ToolsetError>() {
+                            Some(ToolsetError::Validation(message)) => message.clone(),
+                            _ => error.to_string(),
+                        };
+                        errors.push(format!("row {}: {}", i + 1, message));
                     }
                 }
+                assets.push(asset);
+            }
+            if !errors.is_empty() {
+                return Err(ToolsetError::Validation(errors.join("; ")).into());
+            }
+            if specs.is_empty() {
+                return Err(
+                    ToolsetError::Validation(format!("{} has no orders", file.display())).into(),
+                );
+            }
+            if dry_run {
+                let previews = toolset.preview_orders(&specs).await?;
+                output.rows(&previews, ui::draw_batch_preview)?;
+                return Ok(());
+            }
+            if options.override_risk {
+                confirm_risk_override(&toolset, &specs).await?;
             }
+            confirm_orders(&toolset, &specs, options.yes, confirm).await?;
+
+            let results = toolset.place_orders(&specs).await?;
+            finish_batch(&file, result, &assets, results, output)?;
         }
-        Commands::Positions => {
-            let result = info_client.user_state(hl_config.account_address).await;
-            match result {
-                Ok(response) => {
-                    let positions = response.asset_positions;
-                    ui::draw_user_positions_table(positions);
+        Commands::Batch(BatchCommand::Cancel { file, result }) => {
+            let rows: Vec<CancelRow> = batch::load_rows(&file)?;
+            let orders: Vec<(String, u64)> = rows.into_iter().map(|r| (r.asset, r.oid)).collect();
+            if orders.is_empty() {
+                return Err(
+                    ToolsetError::Validation(format!("{} has no orders", file.display())).into(),
+                );
+            }
+            if dry_run {
+                let mut previews = vec![];
+                for (asset, oid) in &orders {
+                    previews.push(toolset.preview_cancel(asset, *oid).await?);
                 }
-                Err(error) => {
-                    println!("  🔴 Error: {}", error);
+                output.rows(&previews, |previews| {
+                    previews.iter().for_each(ui::draw_cancel_preview)
+                })?;
+                return Ok(());
+            }
+            let results = toolset.cancel_orders(&orders).await?;
+            let assets: Vec<String> = orders.into_iter().map(|(asset, _)| asset).collect();
+            finish_batch(&file, result, &assets, results, output)?;
+        }
+        Commands::Flatten {
+            asset,
+            keep_orders,
+            slippage,
+            timeout,
+        } => {
+            if !(0.0..1.0).contains(&slippage) {
+                return Err(ToolsetError::Validation(format!(
+                    "--slippage must be between 0 and 1: {}",
+                    slippage
+                ))
+                .into());
+            }
+            if dry_run {
+                let preview = toolset
+                    .preview_flatten(&asset, keep_orders, slippage)
+                    .await?;
+                output.one(&preview, ui::draw_flatten_preview)?;
+                return Ok(());
+            }
+            let specs = toolset.flatten_orders(&asset, slippage).await?;
+            if !specs.is_empty() {
+                if options.override_risk {
+                    confirm_risk_override(&toolset, &specs).await?;
+                }
+                confirm_orders(&toolset, &specs, options.yes, confirm).await?;
+            }
+            let report = toolset
+                .flatten(&asset, keep_orders, slippage, timeout)
+                .await?;
+            output.one(&report, ui::draw_flatten_report)?;
+            if !report.is_flat() {
+                let open: Vec<String> = report
+                    .positions
+                    .iter()
+                    .filter(|p| !p.is_flat())
+                    .map(|p| format!("{} {}", p.asset, p.remaining_quantity))
+                    .collect();
+                let closed = report.positions.len() - open.len();
+                return Err(if closed > 0 {
+                    ToolsetError::PartialFailure {
+                        succeeded: closed,
+                        failed: open.len(),
+                    }
+                } else {
+                    ToolsetError::Rejected(format!("Positions still open: {}", open.join(", ")))
                 }
+                .into());
             }
         }
-        Commands::Order(OrderRequest {
+        Commands::Oco(OcoCommand::Place {
             asset,
             side,
-            reduce_only,
-            order,
-            price,
             quantity,
-            cloid,
+            limit,
+            stop,
+            stop_limit,
+            reduce_only,
+            detach,
+            interval,
         }) => {
-            let order_type = match order {
-                OrderType::Limit { ref limit } => ClientOrder::Limit(ClientLimit {
-                    tif: format!("{:?}", limit),
-                }),
-                OrderType::Trigger {
-                    ref trigger_price,
-                    limit_price,
-                    ref tpsl,
-                } => ClientOrder::Trigger(ClientTrigger {
-                    is_market: !limit_price,
-                    trigger_px: trigger_price.parse::<f64>().unwrap(),
-                    tpsl: format!("{:?}", tpsl).to_lowercase(),
-                }),
-            };
+            let market = toolset.market(&asset).await?;
+            let mut pair = OcoPair::new(
+                &market.asset,
+                side,
+                quantity,
+                limit,
+                stop,
+                stop_limit,
+                reduce_only,
+            );
+            pair.validate(market.mid_price.unwrap_or(market.mark_price))?;
+            let specs = [pair.limit_order(quantity), pair.stop_order(quantity)];
+            for spec in &specs {
+                toolset.validate_order(spec)?;
+            }
+            if dry_run {
+                let previews = toolset.preview_orders(&specs).await?;
+                output.rows(&previews, ui::draw_batch_preview)?;
+                return Ok(());
+            }
+            if options.override_risk {
+                confirm_risk_override(&toolset, &specs).await?;
+            }
+            confirm_orders(&toolset, &specs, options.yes, confirm).await?;
 
-            let price = if let OrderType::Trigger {
-                ref trigger_price,
-                limit_price,
-                ..
-            } = order
-                && !limit_price
-            {
-                // market price actually, so we can miss the price parameter
-                trigger_price.parse::<f64>().unwrap()
-            } else {
-                price.unwrap()
+            let address = toolset.address();
+            let mut pairs = oco::load(address)?;
+            pair.id = pairs.keys().next_back().map_or(1, |id| id + 1);
+            oco::place(&toolset, &mut pair).await?;
+            pairs.insert(pair.id, pair.clone());
+            oco::save(address, &pairs)?;
+            output.rows(std::slice::from_ref(&pair), ui::draw_oco_table)?;
+            if !detach {
+                watch_oco(&toolset, interval, options).await?;
+            }
+        }
+        Commands::Oco(OcoCommand::Watch { interval }) => {
+            watch_oco(&toolset, interval, options).await?;
+        }
+        Commands::Oco(OcoCommand::List) => {
+            let pairs: Vec<OcoPair> = oco::load(toolset.address())?.into_values().collect();
+            output.rows(&pairs, ui::draw_oco_table)?;
+        }
+        Commands::Oco(OcoCommand::Cancel { id }) => {
+            let address = toolset.address();
+            let mut pairs = oco::load(address)?;
+            let pair = pairs
+                .get_mut(&id)
+                .filter(|p| p.status == OcoStatus::Active)
+                .ok_or_else(|| ToolsetError::Validation(format!("No active OCO #{}", id)))?;
+            if dry_run {
+                let mut previews = vec![];
+                for leg in [&pair.limit, &pair.stop] {
+                    if leg.open_size > 0.0 {
+                        previews.push(toolset.preview_cancel(&pair.asset, leg.oid).await?);
+                    }
+                }
+                output.rows(&previews, |previews| {
+                    previews.iter().for_each(ui::draw_cancel_preview)
+                })?;
+                return Ok(());
+            }
+            oco::cancel(&toolset, pair).await?;
+            let pair = pair.clone();
+            oco::save(address, &pairs)?;
+            output.rows(&[pair], ui::draw_oco_table)?;
+        }
+        Commands::Grid(GridCommand::Start {
+            asset,
+            lower,
+            upper,
+            levels,
+            investment,
+            geometric,
+            interval,
+        }) => {
+            let request = GridRequest {
+                asset,
+                range: match (lower, upper, levels, investment) {
+                    (None, None, None, None) => None,
+                    (Some(lower), Some(upper), Some(levels), Some(investment)) => {
+                        Some((lower, upper, levels, investment))
+                    }
+                    _ => {
+                        return Err(ToolsetError::Validation(
+                            "--lower, --upper, --levels and --investment are required to start a grid"
+                                .into(),
+                        )
+                        .into());
+                    }
+                },
+                spacing: if geometric {
+                    GridSpacing::Geometric
+                } else {
+                    GridSpacing::Arithmetic
+                },
+                interval,
             };
-
-            let cloid = if let Some(uuid) = cloid {
-                Some(uuid::Uuid::from_str(&uuid).unwrap())
+            run_grid(&toolset, request, options, confirm).await?;
+        }
+        Commands::Grid(GridCommand::Stop { asset }) => {
+            let asset = toolset.market(&asset).await?.asset;
+            let address = toolset.address();
+            let mut grids = grid::load(address)?;
+            let grid = grids
+                .get_mut(&asset)
+                .filter(|g| g.status == GridStatus::Running)
+                .ok_or_else(|| {
+                    ToolsetError::Validation(format!("No running grid for {}", asset))
+                })?;
+            if dry_run {
+                let orders: Vec<(String, u64)> = grid
+                    .orders
+                    .iter()
+                    .filter_map(|o| o.oid.map(|oid| (asset.clone(), oid)))
+                    .collect();
+                let mut previews = vec![];
+                for (asset, oid) in &orders {
+                    previews.push(toolset.preview_cancel(asset, *oid).await?);
+                }
+                output.rows(&previews, |previews| {
+                    previews.iter().for_each(ui::draw_cancel_preview)
+                })?;
+                return Ok(());
+            }
+            grid::stop(&toolset, grid).await?;
+            let grid = grid.clone();
+            grid::update(address, |grids| grids.insert(asset, grid.clone()))?;
+            output.rows(&[grid], ui::draw_grids_table)?;
+        }
+        Commands::Grid(GridCommand::Status) => {
+            let grids: Vec<Grid> = grid::load(toolset.address())?.into_values().collect();
+            output.rows(&grids, ui::draw_grids_table)?;
+        }
+        Commands::Dca(DcaCommand::Add {
+            asset,
+            usd,
+            every,
+            at,
+            spot,
+            max_price,
+            slippage,
+        }) => {
+            if usd <= 0.0 {
+                return Err(
+                    ToolsetError::Validation(format!("--usd must be positive: {}", usd)).into(),
+                );
+            }
+            if every < MIN_DCA_INTERVAL {
+                return Err(ToolsetError::Validation(format!(
+                    "--every must be at least {}s",
+                    MIN_DCA_INTERVAL.as_secs()
+                ))
+                .into());
+            }
+            if !(0.0..1.0).contains(&slippage) {
+                return Err(ToolsetError::Validation(format!(
+                    "--slippage must be between 0 and 1: {}",
+                    slippage
+                ))
+                .into());
+            }
+            let (asset, mid, sz_decimals) = if spot {
+                let market = toolset.spot_market(&asset).await?;
+                let mid = market.mid_price.ok_or_else(|| {
+                    ToolsetError::Validation(format!("No price for {}", market.pair))
+                })?;
+                (market.pair, mid, market.sz_decimals)
             } else {
-                None
+                let market = toolset.market(&asset).await?;
+                let mid = market.mid_price.unwrap_or(market.mark_price);
+                (market.asset, mid, market.sz_decimals)
             };
-
-            let order_request = ClientOrderRequest {
+            let address = toolset.address();
+            let mut plans = dca::load(address)?;
+            let plan = DcaPlan {
+                id: plans.keys().next_back().map_or(1, |id| id + 1),
                 asset,
-                is_buy: side == OrderSide::Buy,
-                reduce_only: reduce_only,
-                limit_px: price,
-                sz: quantity,
-                cloid,
-                order_type,
+                spot,
+                usd,
+                every: every.as_secs(),
+                at: at.map(|at| at.format("%H:%M").to_string()),
+                max_price,
+                slippage,
+                next_run: dca::first_run(at),
+                created_at: chrono::Utc::now().timestamp_millis() as u64,
             };
+            // 按当前价格校验订单，避免 runner 执行时才发现数量为 0 或标的无法交易
+            toolset.validate_order(&dca::order_spec(&plan, mid, sz_decimals))?;
+            if dry_run {
+                if output.is_table() {
+                    println!("  🧪 Dry run: the plan was not saved");
+                }
+                output.rows(&[plan], ui::draw_dca_plans)?;
+                return Ok(());
+            }
+            if confirm.required(usd, options.yes) {
+                ui::draw_dca_plans(std::slice::from_ref(&plan));
+                if !ui::confirm("Add this DCA plan?")? {
+                    return Err(ToolsetError::Cancelled.into());
+                }
+            }
+            plans.insert(plan.id, plan.clone());
+            dca::save(address, &plans)?;
+            output.rows(&[plan], ui::draw_dca_plans)?;
+            if output.is_table() {
+                println!("  📅 Plans are executed by `hlt dca run`");
+            }
+        }
+        Commands::Dca(DcaCommand::List) => {
+            let plans: Vec<DcaPlan> = dca::load(toolset.address())?.into_values().collect();
+            output.rows(&plans, ui::draw_dca_plans)?;
+        }
+        Commands::Dca(DcaCommand::Remove { id }) => {
+            let address = toolset.address();
+            let mut plans = dca::load(address)?;
+            let plan = plans
+                .remove(&id)
+                .ok_or_else(|| ToolsetError::Validation(format!("No DCA plan #{}", id)))?;
+            dca::save(address, &plans)?;
+            output.rows(&[plan], ui::draw_dca_plans)?;
+            if output.is_table() {
+                println!("  🟢 DCA plan #{} removed", id);
+            }
+        }
+        Commands::Dca(DcaCommand::History { id, limit }) => {
+            let history: Vec<_> = dca::history(toolset.address())?
+                .into_iter()
+                .filter(|e| id.is_none_or(|id| e.plan_id == id))
+                .collect();
+            let start = history.len().saturating_sub(limit);
+            output.rows(&history[start..], ui::draw_dca_history)?;
+        }
+        Commands::Dca(DcaCommand::Run { interval }) => {
+            run_dca(&toolset, interval, options).await?;
+        }
+        Commands::Alert(AlertCommand::Add {
+            asset,
+            kind,
+            args,
+            bell,
+            desktop,
+            command,
+            webhook,
+            cooldown,
+        }) => {
+            let asset = toolset.market(&asset).await?.asset;
+            let condition = alert_condition(kind, &args)?;
+            if let Some(url) = webhook
+                .iter()
+                .find(|url| !url.starts_with("http://") && !url.starts_with("https://"))
+            {
+                return Err(
+                    ToolsetError::Validation(format!("Invalid webhook URL: {}", url)).into(),
+                );
+            }
+            let mut channels = vec![];
+            if bell || (!desktop && command.is_empty() && webhook.is_empty()) {
+                channels.push(AlertChannel::Bell);
+            }
+            if desktop {
+                channels.push(AlertChannel::Desktop);
+            }
+            channels.extend(command.into_iter().map(AlertChannel::Command));
+            channels.extend(webhook.into_iter().map(AlertChannel::Webhook));
 
-            let order_result = exchange_client.order(order_request, None).await;
-            match order_result {
-                Ok(response) => match response {
-                    ExchangeResponseStatus::Ok(status) => {
-                        println!("Operation result: ");
-                        ui::line();
-                        println!("  Type: {}", status.response_type);
-                        if let Some(data) = status.data {
-                            for status in data.statuses {
-                                match status {
-                                    ExchangeDataStatus::Success => {
-                                        println!("  🟢 Success");
-                                    }
-                                    ExchangeDataStatus::WaitingForTrigger
-                                    | ExchangeDataStatus::WaitingForFill => {
-                                        println!("  🟠 Waiting for fill/trigger");
-                                    }
-                                    ExchangeDataStatus::Filled(filled) => {
-                                        println!(
-                                            "  🟢 Filled: {} @ {}, Order ID: {}",
-                                            filled.total_sz, filled.avg_px, filled.oid,
-                                        );
-                                    }
-                                    ExchangeDataStatus::Resting(data) => {
-                                        println!("  🟢 Order ID: {}", data.oid);
-                                    }
-                                    ExchangeDataStatus::Error(err) => {
-                                        println!("  🔴 Failure: {}", err);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    ExchangeResponseStatus::Err(err) => {
-                        println!("  🔴 Error: {}", err);
+            let address = toolset.address();
+            let mut alerts = alert::load(address)?;
+            let mut new = Alert::new(&asset, condition, channels, cooldown);
+            new.id = alerts.keys().next_back().map_or(1, |id| id + 1);
+            alerts.insert(new.id, new.clone());
+            alert::save(address, &alerts)?;
+            output.rows(&[new], ui::draw_alerts_table)?;
+            if output.is_table() {
+                println!("  🔔 Alerts are checked by `hlt alert watch`");
+            }
+        }
+        Commands::Alert(AlertCommand::List) => {
+            let alerts: Vec<Alert> = alert::load(toolset.address())?.into_values().collect();
+            output.rows(&alerts, ui::draw_alerts_table)?;
+        }
+        Commands::Alert(AlertCommand::Remove { id }) => {
+            let address = toolset.address();
+            let mut alerts = alert::load(address)?;
+            let removed = alerts
+                .remove(&id)
+                .ok_or_else(|| ToolsetError::Validation(format!("No alert #{}", id)))?;
+            alert::save(address, &alerts)?;
+            output.rows(&[removed], ui::draw_alerts_table)?;
+            if output.is_table() {
+                println!("  🟢 Alert #{} removed", id);
+            }
+        }
+        Commands::Alert(AlertCommand::Test { id }) => {
+            let alerts = alert::load(toolset.address())?;
+            let test = alerts
+                .get(&id)
+                .ok_or_else(|| ToolsetError::Validation(format!("No alert #{}", id)))?;
+            let mut event = AlertEvent::test(test);
+            alert::notify(&test.channels, &mut event).await;
+            output.one(&event, ui::draw_alert_event)?;
+            if !event.errors.is_empty() {
+                return Err(ToolsetError::Network(event.errors.join("; ")).into());
+            }
+        }
+        Commands::Alert(AlertCommand::Watch { interval }) => {
+            watch_alerts(&toolset, interval, output).await?;
+        }
+        Commands::Rules(RulesCommand::Run {
+            file,
+            interval,
+            reset,
+        }) => {
+            run_rules(&toolset, &file, interval, reset, options, confirm).await?;
+        }
+        Commands::Rules(RulesCommand::Check { file }) => {
+            let rules = rules::load(&toolset, &file).await?;
+            let states = rules::load_states(toolset.address())?;
+            let statuses = rules::statuses(&toolset, &rules, &states).await?;
+            output.rows(&statuses, ui::draw_rules_table)?;
+        }
+        Commands::Trail {
+            asset,
+            distance,
+            activation,
+            interval,
+            cancel,
+        } => {
+            let Some(asset) = asset else {
+                let trails: Vec<TrailingStop> =
+                    trail::load(toolset.address())?.into_values().collect();
+                output.rows(&trails, ui::draw_trails_table)?;
+                return Ok(());
+            };
+            let asset = toolset.market(&asset).await?.asset;
+            if cancel {
+                cancel_trail(&toolset, &asset, options).await?;
+            } else {
+                let request = TrailRequest {
+                    asset,
+                    distance,
+                    activation,
+                    interval,
+                };
+                run_trail(&toolset, request, options, confirm).await?;
+            }
+        }
+        Commands::Deadman {
+            after,
+            heartbeat,
+            interval,
+            clear,
+        } => {
+            if after.is_none() && !clear {
+                output.one(&toolset.dead_man_switch()?, ui::draw_dead_man_switch)?;
+                return Ok(());
+            }
+            if after.is_some_and(|after| after < MIN_DEAD_MAN_DELAY) {
+                return Err(ToolsetError::Validation(format!(
+                    "--in must be at least {}s",
+                    MIN_DEAD_MAN_DELAY.as_secs()
+                ))
+                .into());
+            }
+            let heartbeat = match after {
+                Some(after) if heartbeat => {
+                    let interval = interval.unwrap_or(after / 3);
+                    if interval.is_zero() || interval >= after {
+                        return Err(ToolsetError::Validation(
+                            "--interval must be shorter than --in".into(),
+                        )
+                        .into());
                     }
-                },
-                Err(err) => {
-                    println!("  🔴 Error: {}", err);
+                    Some((after, interval))
                 }
+                _ => None,
+            };
+            if dry_run {
+                output.one(&toolset.preview_schedule_cancel(after), ui::draw_payload)?;
+                return Ok(());
+            }
+            let status = toolset.schedule_cancel(after).await?;
+            output.one(&status, ui::draw_dead_man_switch)?;
+            if let Some((after, interval)) = heartbeat {
+                run_heartbeat(&toolset, after, interval, output).await?;
             }
         }
-        Commands::Cancel(CancelRequest { asset, order_id }) => {
-            let cancel_request = ClientCancelRequest {
-                asset,
-                oid: order_id,
+        Commands::Serve { listen, token } => {
+            if dry_run || options.override_risk {
+                return Err(ToolsetError::Validation(
+                    "serve does not support --dry-run or --override-risk".into(),
+                )
+                .into());
+            }
+            let token = token.or_else(|| std::env::var("HLT_SERVE_TOKEN").ok());
+            let generated = token.is_none();
+            let token = serve::token(token)?;
+            if !listen.ip().is_loopback() {
+                eprintln!(
+                    "  🟠 Listening on {}, requests from other hosts are accepted",
+                    listen
+                );
+            }
+            if output.is_table() {
+                println!("  🌐 Listening on http://{}", listen);
+                if generated {
+                    println!("  🔑 Bearer token in {}", serve::token_path()?.display());
+                }
+                println!(
+                    "  📒 Requests are logged to {}",
+                    serve::audit_path()?.display()
+                );
+                for endpoint in serve::ENDPOINTS {
+                    println!("     {}", endpoint);
+                }
+            }
+            serve::serve(toolset, listen, token).await?;
+        }
+        Commands::Exporter { listen, interval } => {
+            if interval < MIN_SCRAPE_INTERVAL {
+                return Err(ToolsetError::Validation(format!(
+                    "The interval must be at least {}s",
+                    MIN_SCRAPE_INTERVAL.as_secs()
+                ))
+                .into());
+            }
+            if output.is_table() {
+                println!(
+                    "  📈 Serving metrics on http://{}/metrics, scraping every {}s",
+                    listen,
+                    interval.as_secs()
+                );
+            }
+            exporter::run(&toolset, listen, interval).await?;
+        }
+        Commands::Paper(_) => unreachable!("handled before connecting"),
+    }
+
+    Ok(())
+}
+
+impl OrderRequest {
+    /// 校验命令行参数并转换为下单参数
+    fn into_spec(self) -> Result<OrderSpec, ToolsetError> {
+        let kind = match self.order {
+            OrderType::Limit { limit } => OrderKind::Limit { tif: limit },
+            OrderType::Trigger {
+                trigger_price,
+                limit_price,
+                tpsl,
+            } => OrderKind::Trigger {
+                trigger_price: trigger_price.parse::<f64>().map_err(|_| {
+                    ToolsetError::Validation(format!("Invalid trigger price: {}", trigger_price))
+                })?,
+                // market price actually, so we can miss the price parameter
+                is_market: !limit_price,
+                tpsl,
+            },
+        };
+        let cloid = self
+            .cloid
+            .map(|uuid| {
+                uuid::Uuid::from_str(&uuid)
+                    .map_err(|e| ToolsetError::Validation(format!("Invalid cloid: {}", e)))
+            })
+            .transpose()?;
+        Ok(OrderSpec {
+            asset: self.asset,
+            side: self.side,
+            kind,
+            reduce_only: self.reduce_only,
+            price: self.price,
+            quantity: self.quantity,
+            cloid,
+        })
+    }
+}
+
+/// 定时撤单时间距当前时间的最小间隔，与交易所的限制一致
+const MIN_DEAD_MAN_DELAY: Duration = Duration::from_secs(5);
+
+/// 解析时长，如 30s、5m、1h、1d，不带单位时为秒
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", value))?;
+    let seconds = match unit {
+        "" | "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        "d" => number * 86400,
+        _ => {
+            return Err(format!(
+                "invalid duration unit: {}, expected s, m, h or d",
+                unit
+            ));
+        }
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+/// 解析当地时间，如 09:00
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| format!("invalid time: {}, expected HH:MM", value))
+}
+
+/// 定期延长定时撤单直到 Ctrl-C；单次失败只打印错误，已设置的撤单时间仍然有效
+async fn run_heartbeat<B: Backend>(
+    toolset: &Toolset<B>,
+    after: Duration,
+    interval: Duration,
+    output: OutputFormat,
+) -> Result<()> {
+    if output.is_table() {
+        println!(
+            "  💓 Heartbeat every {}s, press Ctrl-C to stop",
+            interval.as_secs()
+        );
+    }
+    loop {
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+        match toolset.schedule_cancel(Some(after)).await {
+            Ok(status) => output.one(&status, ui::draw_dead_man_switch)?,
+            Err(error) => eprintln!("  🔴 Error: {}", error),
+        }
+    }
+    if output.is_table() {
+        println!("  🟠 Heartbeat stopped, the scheduled cancel is still active");
+        ui::draw_dead_man_switch(&toolset.dead_man_switch()?);
+    }
+    Ok(())
+}
+
+/// 监控所有进行中的 OCO，直到全部完成或 Ctrl-C；每次检查后保存状态
+async fn watch_oco<B: Backend>(
+    toolset: &Toolset<B>,
+    interval: Duration,
+    options: Options,
+) -> Result<()> {
+    let Options {
+        output, dry_run, ..
+    } = options;
+    // 监控会撤销或调整订单，无法只预览
+    if dry_run {
+        return Err(ToolsetError::Validation(
+            "oco watch does not support --dry-run, use `hlt oco list` to review active pairs"
+                .into(),
+        )
+        .into());
+    }
+    let address = toolset.address();
+    if output.is_table() {
+        println!("  👀 Watching OCO orders, press Ctrl-C to stop");
+    }
+    loop {
+        let mut pairs = oco::load(address)?;
+        let active: Vec<u64> = pairs
+            .values()
+            .filter(|p| p.status == OcoStatus::Active)
+            .map(|p| p.id)
+            .collect();
+        if active.is_empty() {
+            if output.is_table() {
+                println!("  ⚪ No active OCO orders");
+            }
+            return Ok(());
+        }
+        for id in active {
+            let Some(pair) = pairs.get_mut(&id) else {
+                continue;
             };
-            match exchange_client.cancel(cancel_request, None).await {
-                Ok(response) => match response {
-                    ExchangeResponseStatus::Ok(status) => {
-                        if status.data.is_some() {
-                            match &status.data.unwrap().statuses[0] {
-                                ExchangeDataStatus::Success => {
-                                    println!("  🟢 Success");
-                                }
-                                ExchangeDataStatus::Filled(filled) => {
-                                    println!(
-                                        "  🟢 Success: {}@{} by {}",
-                                        filled.oid, filled.avg_px, filled.total_sz,
-                                    );
-                                }
-                                ExchangeDataStatus::WaitingForFill
-                                | ExchangeDataStatus::WaitingForTrigger => {
-                                    println!("  🟡 Wating for Fill/Trigger..");
-                                }
-                                ExchangeDataStatus::Resting(reset) => {
-                                    println!("  🔄 Resting Order: {}", reset.oid);
-                                }
-                                ExchangeDataStatus::Error(error) => {
-                                    println!("  🔴 Error: {}", error)
-                                }
-                            }
-                        }
-                    }
-                    ExchangeResponseStatus::Err(error) => {
-                        println!("  🔴 Error: {}", error)
-                    }
-                },
+            match oco::tick(toolset, pair).await {
+                Ok(update) if update.event != OcoEvent::Watching => {
+                    output.one(&update, ui::draw_oco_update)?;
+                }
+                Ok(_) => {}
+                // 网络错误时等待下次检查
+                Err(error) if ToolsetError::is_retryable(&error) => {
+                    eprintln!("  🔴 Error: {}", error);
+                }
                 Err(error) => {
-                    println!("  🔴 Error: {}", error)
+                    oco::save(address, &pairs)?;
+                    return Err(error);
                 }
-            };
+            }
+        }
+        oco::save(address, &pairs)?;
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    if output.is_table() {
+        println!("  🟠 Stopped watching, resume with `hlt oco watch`");
+    }
+    Ok(())
+}
+
+/// 按计划执行定投直到 Ctrl-C，每次检查执行所有到期的计划
+async fn run_dca<B: Backend>(
+    toolset: &Toolset<B>,
+    interval: Duration,
+    options: Options,
+) -> Result<()> {
+    let Options {
+        output, dry_run, ..
+    } = options;
+    if dry_run {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let due: Vec<DcaPlan> = dca::load(toolset.address())?
+            .into_values()
+            .filter(|p| p.is_due(now))
+            .collect();
+        if output.is_table() {
+            println!(
+                "  🧪 Dry run: {} plans are due, nothing was sent",
+                due.len()
+            );
         }
+        output.rows(&due, ui::draw_dca_plans)?;
+        return Ok(());
+    }
+    if output.is_table() {
+        println!("  📅 Running DCA plans, press Ctrl-C to stop");
     }
+    loop {
+        match dca::run_due(toolset).await {
+            Ok(executions) => {
+                for execution in &executions {
+                    output.one(execution, ui::draw_dca_execution)?;
+                }
+            }
+            // 网络错误时未执行的计划不顺延，下次检查时重试
+            Err(error) if ToolsetError::is_retryable(&error) => {
+                eprintln!("  🔴 Error: {}", error);
+            }
+            Err(error) => return Err(error),
+        }
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    if output.is_table() {
+        println!("  🟠 DCA runner stopped");
+    }
+    Ok(())
+}
 
+/// 解析提醒类型的参数
+fn alert_condition(kind: AlertKind, args: &[String]) -> Result<AlertCondition, ToolsetError> {
+    let invalid = |message: String| ToolsetError::Validation(message);
+    let expect = |count: usize, usage: &str| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(invalid(format!("Expected {}", usage)))
+        }
+    };
+    let price = |value: &str| {
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|p| *p > 0.0)
+            .ok_or_else(|| invalid(format!("Invalid price: {}", value)))
+    };
+    // 5% 与 5 都表示 5%
+    let percent = |value: &str| {
+        value
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .ok()
+            .filter(|p| *p > 0.0 && *p < 100.0)
+            .map(|p| p / 100.0)
+            .ok_or_else(|| invalid(format!("Invalid percentage: {}", value)))
+    };
+    let condition = match kind {
+        AlertKind::Above => {
+            expect(1, "a price, e.g. `above 100000`")?;
+            AlertCondition::Above(price(&args[0])?)
+        }
+        AlertKind::Below => {
+            expect(1, "a price, e.g. `below 90000`")?;
+            AlertCondition::Below(price(&args[0])?)
+        }
+        AlertKind::PctChange => {
+            expect(2, "a percentage and a window, e.g. `pct-change 5% 1h`")?;
+            let window = parse_duration(&args[1]).map_err(invalid)?;
+            if window < MIN_CHANGE_WINDOW {
+                return Err(invalid(format!(
+                    "The window must be at least {}s",
+                    MIN_CHANGE_WINDOW.as_secs()
+                )));
+            }
+            AlertCondition::PctChange {
+                percent: percent(&args[0])?,
+                window: window.as_secs(),
+            }
+        }
+        AlertKind::LiqDistance => {
+            expect(1, "a percentage, e.g. `liq-distance 10%`")?;
+            AlertCondition::LiqDistance {
+                percent: percent(&args[0])?,
+            }
+        }
+        AlertKind::Fill => {
+            expect(0, "no arguments for fill alerts")?;
+            AlertCondition::Fill
+        }
+    };
+    Ok(condition)
+}
+
+/// 持续检查所有提醒直到 Ctrl-C，每次检查前重新读取提醒，检查期间新增或删除的提醒在下次检查时生效
+async fn watch_alerts<B: Backend>(
+    toolset: &Toolset<B>,
+    interval: Duration,
+    output: OutputFormat,
+) -> Result<()> {
+    let address = toolset.address();
+    if output.is_table() {
+        println!(
+            "  🔔 Watching {} alerts, press Ctrl-C to stop",
+            alert::load(address)?.len()
+        );
+    }
+    loop {
+        let mut alerts = alert::load(address)?;
+        let result = alert::tick(toolset, &mut alerts).await;
+        alert::save_progress(address, &alerts)?;
+        match result {
+            Ok(events) => {
+                for event in &events {
+                    output.one(event, ui::draw_alert_event)?;
+                }
+            }
+            Err(error) if ToolsetError::is_retryable(&error) => {
+                eprintln!("  🔴 Error: {}", error);
+            }
+            Err(error) => return Err(error),
+        }
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    if output.is_table() {
+        println!("  🟠 Alert watcher stopped");
+    }
+    Ok(())
+}
+
+/// 按规则文件持续检查条件并执行动作，直到 Ctrl-C 或所有非重复规则都已执行；每次检查后保存规则状态
+async fn run_rules<B: Backend>(
+    toolset: &Toolset<B>,
+    file: &Path,
+    interval: Duration,
+    reset: bool,
+    options: Options,
+    confirm: &ConfirmSettings,
+) -> Result<()> {
+    let Options {
+        output, dry_run, ..
+    } = options;
+    let rules = rules::load(toolset, file).await?;
+    let address = toolset.address();
+    let mut states: BTreeMap<String, RuleState> = rules::load_states(address)?
+        .into_iter()
+        .filter(|(name, _)| !reset && rules.iter().any(|r| &r.name == name))
+        .collect();
+    if dry_run {
+        let statuses = rules::statuses(toolset, &rules, &states).await?;
+        if output.is_table() {
+            println!("  🧪 Dry run: rules were evaluated, nothing was sent");
+        }
+        output.rows(&statuses, ui::draw_rules_table)?;
+        return Ok(());
+    }
+    let done = |states: &BTreeMap<String, RuleState>| {
+        rules
+            .iter()
+            .all(|r| states.get(&r.name).is_some_and(|s| s.is_done(r)))
+    };
+    if done(&states) {
+        if output.is_table() {
+            println!("  🟢 All rules have fired, start again with --reset");
+        }
+        return Ok(());
+    }
+
+    let specs = rules::order_specs(toolset, &rules).await?;
+    if !specs.is_empty() {
+        if options.override_risk {
+            confirm_risk_override(toolset, &specs).await?;
+        }
+        confirm_orders(toolset, &specs, options.yes, confirm).await?;
+    }
+    rules::save_states(address, &rules, &states)?;
+    if output.is_table() {
+        println!(
+            "  ⚡ Running {} rules from {}, press Ctrl-C to stop",
+            rules.len(),
+            file.display()
+        );
+    }
+    loop {
+        let result = rules::tick(toolset, &rules, &mut states).await;
+        rules::save_states(address, &rules, &states)?;
+        match result {
+            Ok(events) => {
+                for event in &events {
+                    output.one(event, ui::draw_rule_event)?;
+                }
+            }
+            // 网络错误时出错的规则不更新状态，下次检查时重试
+            Err(error) if ToolsetError::is_retryable(&error) => {
+                eprintln!("  🔴 Error: {}", error);
+            }
+            Err(error) => return Err(error),
+        }
+        if done(&states) {
+            if output.is_table() {
+                println!("  🟢 All rules have fired");
+            }
+            return Ok(());
+        }
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    if output.is_table() {
+        println!("  🟠 Rules stopped");
+    }
+    Ok(())
+}
+
+/// 启动或继续网格的参数
+struct GridRequest {
+    asset: String,
+    /// 新网格的 (下限, 上限, 价位数量, 投入金额)，继续已有网格时为 None
+    range: Option<(f64, f64, usize, f64)>,
+    spacing: GridSpacing,
+    interval: Duration,
+}
+
+/// 挂出初始网格或继续已有网格，直到 Ctrl-C 或网格被 `hlt grid stop` 停止；每次检查后保存状态
+async fn run_grid<B: Backend>(
+    toolset: &Toolset<B>,
+    request: GridRequest,
+    options: Options,
+    confirm: &ConfirmSettings,
+) -> Result<()> {
+    let Options {
+        output, dry_run, ..
+    } = options;
+    let GridRequest {
+        asset,
+        range,
+        spacing,
+        interval,
+    } = request;
+    let market = toolset.market(&asset).await?;
+    let asset = market.asset.clone();
+    let address = toolset.address();
+    let grids = grid::load(address)?;
+    let running = grids
+        .get(&asset)
+        .is_some_and(|g| g.status == GridStatus::Running);
+    match range {
+        Some(_) if running => {
+            return Err(ToolsetError::Validation(format!(
+                "A grid for {} is already running, resume it with `hlt grid start -a {}` or stop it first",
+                asset, asset
+            ))
+            .into());
+        }
+        Some((lower, upper, levels, investment)) => {
+            let mut grid = Grid::new(&market, lower, upper, levels, investment, spacing)?;
+            let specs = grid.pending_specs();
+            for spec in &specs {
+                toolset.validate_order(spec)?;
+            }
+            if dry_run {
+                let previews = toolset.preview_orders(&specs).await?;
+                output.rows(&previews, ui::draw_batch_preview)?;
+                return Ok(());
+            }
+            if options.override_risk {
+                confirm_risk_override(toolset, &specs).await?;
+            }
+            confirm_orders(toolset, &specs, options.yes, confirm).await?;
+            grid::start(toolset, &mut grid).await?;
+            grid::update(address, |grids| grids.insert(asset.clone(), grid.clone()))?;
+            output.rows(&[grid], ui::draw_grids_table)?;
+        }
+        None if !running => {
+            return Err(ToolsetError::Validation(format!(
+                "No running grid for {}, --lower, --upper, --levels and --investment are required to start one",
+                asset
+            ))
+            .into());
+        }
+        None if dry_run => {
+            output.rows(&[grids[&asset].clone()], ui::draw_grids_table)?;
+            return Ok(());
+        }
+        None => {}
+    }
+    if output.is_table() {
+        println!("  🔁 Running {} grid, press Ctrl-C to pause", asset);
+    }
+
+    loop {
+        // `hlt grid stop` 在其他进程中停止网格后退出
+        let Some(mut grid) = grid::load(address)?
+            .remove(&asset)
+            .filter(|g| g.status == GridStatus::Running)
+        else {
+            if output.is_table() {
+                println!("  ⚪ {} grid stopped", asset);
+            }
+            return Ok(());
+        };
+        let result = grid::tick(toolset, &mut grid).await;
+        // 出错时同样保存，已处理的成交不会重复处理，未挂出的订单下次检查时重试
+        let stopped = grid::update(address, |grids| match grids.get_mut(&asset) {
+            Some(saved) if saved.status == GridStatus::Running => {
+                *saved = grid.clone();
+                false
+            }
+            _ => true,
+        })?;
+        if stopped {
+            // 检查期间网格被停止，撤销本次补挂的订单
+            grid::cancel_orders(toolset, &mut grid).await?;
+            continue;
+        }
+        match result {
+            Ok(update)
+                if !update.fills.is_empty()
+                    || !update.replaced.is_empty()
+                    || !update.errors.is_empty() =>
+            {
+                output.one(&update, ui::draw_grid_update)?;
+            }
+            Ok(_) => {}
+            // 网络错误时等待下次检查，已挂出的订单仍然有效
+            Err(error) if ToolsetError::is_retryable(&error) => {
+                eprintln!("  🔴 Error: {}", error);
+            }
+            Err(error) => return Err(error),
+        }
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    if output.is_table() {
+        println!(
+            "  🟠 Grid paused, orders stay on the book; resume with `hlt grid start -a {}` or cancel them with `hlt grid stop -a {}`",
+            asset, asset
+        );
+    }
+    Ok(())
+}
+
+/// 启动或继续追踪止损的参数
+struct TrailRequest {
+    asset: String,
+    distance: Option<TrailDistance>,
+    activation: Option<f64>,
+    interval: Duration,
+}
+
+/// 启动或继续追踪止损，直到仓位平仓或 Ctrl-C；每次检查后保存状态
+async fn run_trail<B: Backend>(
+    toolset: &Toolset<B>,
+    request: TrailRequest,
+    options: Options,
+    confirm: &ConfirmSettings,
+) -> Result<()> {
+    let Options {
+        output, dry_run, ..
+    } = options;
+    let TrailRequest {
+        asset,
+        distance,
+        activation,
+        interval,
+    } = request;
+    let address = toolset.address();
+    let mut trails = trail::load(address)?;
+    let resumed = trails.contains_key(&asset);
+    let mut trail = match trails.get(&asset).cloned() {
+        Some(mut trail) => {
+            if let Some(distance) = distance {
+                trail.distance = distance;
+            }
+            if activation.is_some() {
+                trail.activation = activation;
+            }
+            trail
+        }
+        None => {
+            let distance = distance.ok_or_else(|| {
+                ToolsetError::Validation("--distance is required to start a trailing stop".into())
+            })?;
+            let szi = trail::position_size(toolset, &asset).await?;
+            if szi == 0.0 {
+                return Err(
+                    ToolsetError::Validation(format!("No {} position to trail", asset)).into(),
+                );
+            }
+            TrailingStop::new(&asset, distance, activation, szi)
+        }
+    };
+
+    if dry_run || !resumed {
+        // 按当前价格(未激活时按激活价)计算的第一个止损单
+        let market = toolset.market(&asset).await?;
+        let mid = market.mid_price.unwrap_or(market.mark_price);
+        let mut first = trail.clone();
+        let reference = match first.activation {
+            Some(activation) if first.extreme.is_none() => activation,
+            _ => mid,
+        };
+        first.activation = None;
+        let stop = first.observe(reference).unwrap_or(reference);
+        let spec = first.stop_order(stop, first.size);
+        if dry_run {
+            let preview = toolset.preview_order(&spec).await?;
+            output.one(&preview, ui::draw_order_preview)?;
+            return Ok(());
+        }
+        if options.override_risk {
+            confirm_risk_override(toolset, std::slice::from_ref(&spec)).await?;
+        }
+        confirm_orders(toolset, std::slice::from_ref(&spec), options.yes, confirm).await?;
+    }
+    if output.is_table() {
+        println!(
+            "  📈 {} trailing stop for {} {}, distance {}, press Ctrl-C to stop",
+            if resumed { "Resuming" } else { "Starting" },
+            if trail.is_long { "long" } else { "short" },
+            asset,
+            trail.distance
+        );
+    }
+
+    loop {
+        match trail::tick(toolset, &mut trail).await {
+            Ok(update) => {
+                if update.event == TrailEvent::Closed {
+                    trails.remove(&asset);
+                } else {
+                    trails.insert(asset.clone(), trail.clone());
+                }
+                trail::save(address, &trails)?;
+                output.one(&update, ui::draw_trail_update)?;
+                if update.event == TrailEvent::Closed {
+                    return Ok(());
+                }
+            }
+            // 网络错误时等待下次检查，已有的止损单仍然有效
+            Err(error) if ToolsetError::is_retryable(&error) => {
+                eprintln!("  🔴 Error: {}", error);
+            }
+            Err(error) => {
+                trails.insert(asset.clone(), trail.clone());
+                trail::save(address, &trails)?;
+                return Err(error);
+            }
+        }
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    if output.is_table() {
+        println!(
+            "  🟠 Trailing stopped, the stop order stays at {}; resume with `hlt trail -a {}`",
+            trail
+                .stop_price
+                .map(|p| p.to_string())
+                .unwrap_or("-".to_string()),
+            asset
+        );
+    }
+    Ok(())
+}
+
+/// 停止追踪并撤销止损单
+async fn cancel_trail<B: Backend>(
+    toolset: &Toolset<B>,
+    asset: &str,
+    options: Options,
+) -> Result<()> {
+    let Options {
+        output, dry_run, ..
+    } = options;
+    let address = toolset.address();
+    let mut trails = trail::load(address)?;
+    let trail = trails
+        .remove(asset)
+        .ok_or_else(|| ToolsetError::Validation(format!("No trailing stop for {}", asset)))?;
+    if dry_run {
+        let mut previews = vec![];
+        if let Some(oid) = trail.oid {
+            previews.push(toolset.preview_cancel(asset, oid).await?);
+        }
+        output.rows(&previews, |previews| {
+            previews.iter().for_each(ui::draw_cancel_preview)
+        })?;
+        return Ok(());
+    }
+    let results = match trail.oid {
+        Some(oid) => toolset.cancel(asset, oid).await?,
+        None => vec![],
+    };
+    trail::save(address, &trails)?;
+    output.rows(&results, ui::draw_cancel_results)?;
+    if output.is_table() {
+        println!("  🟢 Trailing stop for {} removed", asset);
+    }
+    Ok(())
+}
+
+/// 输出批量操作结果并写入结果文件，有失败的行时返回错误
+fn finish_batch(
+    file: &Path,
+    result: Option<PathBuf>,
+    assets: &[String],
+    results: Vec<OrderResult>,
+    output: OutputFormat,
+) -> Result<()> {
+    let failure = ToolsetError::from_results(&results);
+    let results: Vec<BatchResult> = results
+        .into_iter()
+        .zip(assets)
+        .enumerate()
+        .map(|(i, (result, asset))| BatchResult::new(i + 1, asset, result))
+        .collect();
+    let result_file = result.unwrap_or_else(|| batch::result_path(file));
+    batch::write_results(&result_file, &results)?;
+    output.rows(&results, ui::draw_batch_results)?;
+    if output.is_table() {
+        println!("  📄 Results written to {}", result_file.display());
+    }
+    match failure {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// 显示违反的风控限制，用户确认后这些订单跳过风控检查
+async fn confirm_risk_override<B: Backend>(
+    toolset: &Toolset<B>,
+    specs: &[OrderSpec],
+) -> Result<()> {
+    let violations = toolset.risk_violations(specs).await?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    ui::draw_risk_violations(&violations);
+    if !ui::confirm("Override risk limits and continue?")? {
+        return Err(ToolsetError::Risk(violations.join("; ")).into());
+    }
+    toolset.override_risk(specs)?;
+    Ok(())
+}
+
+/// 显示订单摘要并要求用户确认，`--yes` 且未超过配置的名义价值阈值时跳过
+async fn confirm_orders<B: Backend>(
+    toolset: &Toolset<B>,
+    specs: &[OrderSpec],
+    yes: bool,
+    settings: &ConfirmSettings,
+) -> Result<()> {
+    if yes && settings.notional_threshold.is_none() {
+        return Ok(());
+    }
+    let orders = toolset.order_summaries(specs).await?;
+    let notional: f64 = orders.iter().map(|o| o.notional).sum();
+    if !settings.required(notional, yes) {
+        return Ok(());
+    }
+    ui::draw_order_confirmation(&orders);
+    if !ui::confirm("Sign and send?")? {
+        return Err(ToolsetError::Cancelled.into());
+    }
+    Ok(())
+}
+
+/// 动态刷新界面只支持表格输出
+fn ensure_table(output: OutputFormat) -> Result<()> {
+    if !output.is_table() {
+        return Err(ToolsetError::Validation("--live only supports table output".into()).into());
+    }
+    Ok(())
+}
+
+/// 账户类实时面板依赖交易所的账户推送，模拟账户不支持
+fn ensure_account_stream(options: Options) -> Result<()> {
+    if options.paper {
+        return Err(ToolsetError::Validation(
+            "--live for orders and positions is not available in paper trading".into(),
+        )
+        .into());
+    }
     Ok(())
 }
@@ -0,0 +1,74 @@
+use crate::error::ToolsetError;
+use crate::risk::RiskLimits;
+use crate::store;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+/// 配置文件名，位于数据目录下
+const CONFIG_FILE: &str = "config.toml";
+/// 未指定时使用的配置
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// `~/.hlt/config.toml`，按 profile 分组
+///
+/// ```toml
+/// [profiles.default.risk]
+/// max_order_notional = 10000
+/// allowed_assets = ["BTC", "ETH"]
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// 单个 profile 的配置
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(default)]
+    pub risk: RiskLimits,
+    #[serde(default)]
+    pub confirm: ConfirmSettings,
+}
+
+/// 下单确认设置
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ConfirmSettings {
+    /// 名义价值(美元)不低于该值的订单即使指定了 `--yes` 也需要确认
+    pub notional_threshold: Option<f64>,
+}
+
+impl ConfirmSettings {
+    /// 是否需要确认，`yes` 为命令行中的 `--yes`
+    pub fn required(&self, notional: f64, yes: bool) -> bool {
+        !yes || self.notional_threshold.is_some_and(|t| notional >= t)
+    }
+}
+
+impl Config {
+    /// 读取配置文件，文件不存在时返回空配置
+    pub fn load() -> Result<Self> {
+        let path = store::data_dir()?.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        toml::from_str(&content).map_err(|e| {
+            ToolsetError::Config(format!("Error parsing {}: {}", path.display(), e)).into()
+        })
+    }
+
+    /// 指定名称的 profile，`default` 未配置时为空配置
+    pub fn profile(&self, name: &str) -> Result<Profile, ToolsetError> {
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None if name == DEFAULT_PROFILE => Ok(Profile::default()),
+            None => Err(ToolsetError::Config(format!("Unknown profile: {}", name))),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::backend::InfoBackend;
+use crate::store;
+use crate::types::UserFill;
+use anyhow::Result;
+use clap::ValueEnum;
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// 单次 userFillsByTime 请求返回的最大成交数
+pub const FILLS_PAGE_SIZE: usize = 2000;
+
+/// 现货成本计算方式
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum CostMethod {
+    /// 先进先出
+    #[default]
+    Fifo,
+    /// 加权平均
+    Average,
+}
+
+/// 本地缓存的现货成交记录
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct FillCache {
+    fills: Vec<UserFill>,
+    /// 已同步到的最新成交时间(含永续成交)，下次从这里继续下载
+    #[serde(default)]
+    synced_until: u64,
+}
+
+/// 现货持仓成本及当前估值
+#[derive(Debug, Clone)]
+pub struct SpotCost {
+    /// 按成交记录计算的持仓数量
+    pub quantity: f64,
+    /// 平均成本(USDC)
+    pub average_cost: f64,
+    /// 当前中间价(USDC)
+    pub mid: Option<f64>,
+}
+
+impl SpotCost {
+    /// 按当前中间价计算的未实现盈亏
+    pub fn pnl(&self, total: f64) -> Option<f64> {
+        self.mid.map(|mid| (mid - self.average_cost) * total)
+    }
+}
+
+fn is_spot_coin(coin: &str) -> bool {
+    coin.starts_with('@') || coin.contains('/')
+}
+
+/// 增量同步现货成交记录，已缓存的成交不会重复下载
+async fn sync_fills<B: InfoBackend>(backend: &B, address: H160) -> Result<Vec<UserFill>> {
+    let cache_name = format!("fills-{:?}.json", address);
+    let mut cache: FillCache = store::load(&cache_name)?;
+    let mut seen: HashSet<u64> = cache.fills.iter().map(|f| f.tid).collect();
+    let mut start_time = cache
+        .fills
+        .iter()
+        .map(|f| f.time)
+        .max()
+        .unwrap_or(0)
+        .max(cache.synced_until);
+
+    loop {
+        let page = backend.user_fills_by_time(address, start_time).await?;
+        let page_len = page.len();
+        let last_time = page.iter().map(|f| f.time).max().unwrap_or(start_time);
+        cache.synced_until = cache.synced_until.max(last_time);
+        cache.fills.extend(
+            page.into_iter()
+                .filter(|f| is_spot_coin(&f.coin) && seen.insert(f.tid)),
+        );
+        if page_len < FILLS_PAGE_SIZE || last_time <= start_time {
+            break;
+        }
+        start_time = last_time;
+    }
+
+    cache.fills.sort_by_key(|f| (f.time, f.tid));
+    store::save(&cache_name, &cache)?;
+    Ok(cache.fills)
+}
+
+/// 根据成交记录计算每个代币的持仓数量及平均成本，仅统计以 USDC 计价的交易对
+fn cost_basis(
+    fills: &[UserFill],
+    pairs: &HashMap<String, (String, String)>,
+    method: CostMethod,
+) -> HashMap<String, (f64, f64)> {
+    // 每个代币的持仓批次: (数量, 单位成本)
+    let mut lots: HashMap<String, VecDeque<(f64, f64)>> = HashMap::new();
+    let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+
+    for fill in fills {
+        let Some((base, quote)) = pairs.get(&fill.coin) else {
+            continue;
+        };
+        if quote != "USDC" {
+            continue;
+        }
+        let (px, sz, fee) = (parse(&fill.px), parse(&fill.sz), parse(&fill.fee));
+        let token_lots = lots.entry(base.clone()).or_default();
+
+        if fill.side == "B" {
+            // 买入手续费以买入代币或 USDC 扣除，均计入成本
+            let (received, cost) = if fill.fee_token == *base {
+                (sz - fee, px * sz)
+            } else {
+                (sz, px * sz + fee)
+            };
+            if received <= 0.0 {
+                continue;
+            }
+            match method {
+                CostMethod::Fifo => token_lots.push_back((received, cost / received)),
+                CostMethod::Average => {
+                    let (qty, avg) = token_lots.pop_front().unwrap_or((0.0, 0.0));
+                    let total = qty + received;
+                    token_lots.push_back((total, (qty * avg + cost) / total));
+                }
+            }
+        } else {
+            let mut remaining = sz;
+            while remaining > 0.0 {
+                let Some(lot) = token_lots.front_mut() else {
+                    break;
+                };
+                if lot.0 > remaining {
+                    lot.0 -= remaining;
+                    remaining = 0.0;
+                } else {
+                    remaining -= lot.0;
+                    token_lots.pop_front();
+                }
+            }
+        }
+    }
+
+    lots.into_iter()
+        .filter_map(|(token, lots)| {
+            let quantity: f64 = lots.iter().map(|(qty, _)| qty).sum();
+            if quantity <= 0.0 {
+                return None;
+            }
+            let cost: f64 = lots.iter().map(|(qty, px)| qty * px).sum();
+            Some((token, (quantity, cost / quantity)))
+        })
+        .collect()
+}
+
+/// 计算现货余额的平均成本，并以当前中间价估值
+pub async fn spot_costs<B: InfoBackend>(
+    backend: &B,
+    address: H160,
+    method: CostMethod,
+) -> Result<HashMap<String, SpotCost>> {
+    let (fills, pairs, mids) = tokio::try_join!(
+        sync_fills(backend, address),
+        backend.spot_pairs(),
+        backend.all_mids(),
+    )?;
+
+    // 基础代币 -> USDC 交易对的中间价
+    let token_mids: HashMap<&str, f64> = pairs
+        .iter()
+        .filter(|(_, (_, quote))| quote == "USDC")
+        .filter_map(|(pair, (base, _))| {
+            let mid = mids.get(pair)?.parse::<f64>().ok()?;
+            Some((base.as_str(), mid))
+        })
+        .collect();
+
+    Ok(cost_basis(&fills, &pairs, method)
+        .into_iter()
+        .map(|(token, (quantity, average_cost))| {
+            let mid = token_mids.get(token.as_str()).copied();
+            (
+                token,
+                SpotCost {
+                    quantity,
+                    average_cost,
+                    mid,
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(coin: &str, side: &str, px: f64, sz: f64, fee: f64, fee_token: &str) -> UserFill {
+        UserFill {
+            coin: coin.to_string(),
+            px: px.to_string(),
+            sz: sz.to_string(),
+            side: side.to_string(),
+            time: 0,
+            fee: fee.to_string(),
+            fee_token: fee_token.to_string(),
+            tid: 0,
+            oid: 0,
+            closed_pnl: String::new(),
+            cloid: None,
+        }
+    }
+
+    fn pairs() -> HashMap<String, (String, String)> {
+        HashMap::from([
+            ("@1".to_string(), ("HYPE".to_string(), "USDC".to_string())),
+            ("@2".to_string(), ("PURR".to_string(), "HYPE".to_string())),
+        ])
+    }
+
+    fn fills() -> Vec<UserFill> {
+        vec![
+            fill("@1", "B", 100.0, 1.0, 0.5, "USDC"),
+            fill("@1", "B", 200.0, 1.0, 0.0, "USDC"),
+            fill("@1", "A", 300.0, 1.0, 0.3, "USDC"),
+            // 非 USDC 计价的交易对不统计
+            fill("@2", "B", 1.0, 10.0, 0.0, "USDC"),
+        ]
+    }
+
+    #[test]
+    fn fifo_sells_the_oldest_lot_first() {
+        let costs = cost_basis(&fills(), &pairs(), CostMethod::Fifo);
+        assert_eq!(costs.len(), 1);
+        assert_eq!(costs["HYPE"], (1.0, 200.0));
+    }
+
+    #[test]
+    fn average_keeps_the_weighted_cost() {
+        let costs = cost_basis(&fills(), &pairs(), CostMethod::Average);
+        assert_eq!(costs.len(), 1);
+        assert_eq!(costs["HYPE"], (1.0, 150.25));
+    }
+
+    #[test]
+    fn fee_in_the_bought_token_reduces_quantity() {
+        let fills = [fill("@1", "B", 100.0, 1.0, 0.2, "HYPE")];
+        let costs = cost_basis(&fills, &pairs(), CostMethod::Fifo);
+        assert_eq!(costs["HYPE"], (0.8, 125.0));
+    }
+
+    #[test]
+    fn fully_sold_tokens_are_dropped() {
+        let fills = [
+            fill("@1", "B", 100.0, 1.0, 0.0, "USDC"),
+            fill("@1", "A", 120.0, 1.5, 0.0, "USDC"),
+        ];
+        assert!(cost_basis(&fills, &pairs(), CostMethod::Average).is_empty());
+    }
+}
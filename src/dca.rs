@@ -0,0 +1,399 @@
+use crate::action;
+use crate::backend::Backend;
+use crate::error::ToolsetError;
+use crate::order::{self, LimitType, OrderKind, OrderSide, OrderSpec};
+use crate::store;
+use crate::toolset::{Toolset, now_ms};
+use crate::types::UserFill;
+use anyhow::Result;
+use chrono::{Local, NaiveTime, TimeZone};
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// 最短执行间隔
+pub const MIN_DCA_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 本地时钟与交易所时钟的最大偏差，按计划时间查找成交时向前多查这段时间
+const CLOCK_SKEW_MS: u64 = 60_000;
+
+/// 定投计划：按固定间隔以市价买入固定金额
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DcaPlan {
+    pub id: u64,
+    /// 永续合约名称，或现货交易对名称(如 UBTC/USDC)
+    pub asset: String,
+    pub spot: bool,
+    /// 每次买入的金额(USDC)
+    pub usd: f64,
+    /// 执行间隔，秒
+    pub every: u64,
+    /// 第一次执行对齐到的当地时间，如 09:00
+    pub at: Option<String>,
+    /// 中间价高于该价格时跳过
+    pub max_price: Option<f64>,
+    /// 买入限价相对中间价的最大滑点，0.01 表示 1%
+    pub slippage: f64,
+    /// 下一次执行时间，毫秒时间戳
+    pub next_run: u64,
+    pub created_at: u64,
+}
+
+impl DcaPlan {
+    pub fn is_due(&self, now: u64) -> bool {
+        self.next_run <= now
+    }
+
+    /// 本次执行的订单使用的 cloid，同一计划的同一次执行(顺延前)的重试使用相同的 cloid
+    pub fn cloid(&self) -> Uuid {
+        Uuid::from_u64_pair(self.created_at, self.next_run)
+    }
+
+    /// 按间隔顺延到 `now` 之后，runner 停止期间错过的执行不会补做
+    pub fn advance(&mut self, now: u64) {
+        let every = (self.every * 1000).max(1);
+        if self.next_run <= now {
+            self.next_run += (now - self.next_run) / every * every + every;
+        }
+    }
+}
+
+/// 第一次执行时间：指定了 `at` 时为之后最近的当地时间 `at`，否则立即执行
+pub fn first_run(at: Option<NaiveTime>) -> u64 {
+    let now = Local::now();
+    let Some(at) = at else {
+        return now.timestamp_millis() as u64;
+    };
+    let mut date = now.date_naive();
+    loop {
+        // 夏令时切换导致当天不存在该时间时顺延一天
+        if let Some(time) = Local.from_local_datetime(&date.and_time(at)).earliest()
+            && time > now
+        {
+            return time.timestamp_millis() as u64;
+        }
+        date = date.succ_opt().unwrap_or(date);
+    }
+}
+
+/// 一次定投的结果
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DcaOutcome {
+    Filled,
+    /// 护栏触发，未下单
+    Skipped,
+    /// 下单失败或未成交
+    Failed,
+}
+
+/// 定投执行记录
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DcaExecution {
+    pub plan_id: u64,
+    pub asset: String,
+    /// 执行时间，毫秒时间戳
+    pub time: u64,
+    pub outcome: DcaOutcome,
+    /// 计划买入的金额
+    pub usd: f64,
+    /// 成交均价
+    pub price: Option<f64>,
+    /// 成交数量
+    pub quantity: Option<f64>,
+    pub oid: Option<u64>,
+    /// 跳过或失败的原因
+    pub reason: Option<String>,
+}
+
+impl DcaExecution {
+    fn new(plan: &DcaPlan, outcome: DcaOutcome, reason: Option<String>) -> Self {
+        Self {
+            plan_id: plan.id,
+            asset: plan.asset.clone(),
+            time: now_ms(),
+            outcome,
+            usd: plan.usd,
+            price: None,
+            quantity: None,
+            oid: None,
+            reason,
+        }
+    }
+
+    fn skipped(plan: &DcaPlan, reason: String) -> Self {
+        Self::new(plan, DcaOutcome::Skipped, Some(reason))
+    }
+
+    fn failed(plan: &DcaPlan, reason: String) -> Self {
+        Self::new(plan, DcaOutcome::Failed, Some(reason))
+    }
+}
+
+fn plans_file(address: H160) -> String {
+    format!("dca-{:?}.json", address)
+}
+
+fn history_file(address: H160) -> String {
+    format!("dca-history-{:?}.json", address)
+}
+
+/// 账户的所有定投计划，按 ID 索引
+pub fn load(address: H160) -> Result<BTreeMap<u64, DcaPlan>> {
+    store::load(&plans_file(address))
+}
+
+pub fn save(address: H160, plans: &BTreeMap<u64, DcaPlan>) -> Result<()> {
+    store::save(&plans_file(address), plans)
+}
+
+/// 所有执行记录，按执行时间排序
+pub fn history(address: H160) -> Result<Vec<DcaExecution>> {
+    store::load(&history_file(address))
+}
+
+/// 追加一条执行记录
+pub fn record(address: H160, execution: &DcaExecution) -> Result<()> {
+    let mut history = history(address)?;
+    history.push(execution.clone());
+    store::save(&history_file(address), &history)
+}
+
+/// 计划对应的中间价及数量精度
+async fn quote<B: Backend>(toolset: &Toolset<B>, plan: &DcaPlan) -> Result<(Option<f64>, u32)> {
+    if plan.spot {
+        let market = toolset.spot_market(&plan.asset).await?;
+        Ok((market.mid_price, market.sz_decimals))
+    } else {
+        let market = toolset.market(&plan.asset).await?;
+        Ok((
+            Some(market.mid_price.unwrap_or(market.mark_price)),
+            market.sz_decimals,
+        ))
+    }
+}
+
+/// 可用于买入的 USDC：永续合约为可提取余额，现货为未冻结的 USDC
+async fn available_usdc<B: Backend>(toolset: &Toolset<B>, spot: bool) -> Result<f64> {
+    let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+    let backend = toolset.backend();
+    if spot {
+        let balances = backend.user_token_balances(toolset.address()).await?;
+        Ok(balances
+            .balances
+            .iter()
+            .find(|b| b.coin == "USDC")
+            .map(|b| parse(&b.total) - parse(&b.hold))
+            .unwrap_or(0.0))
+    } else {
+        let state = backend.user_state(toolset.address()).await?;
+        Ok(parse(&state.withdrawable))
+    }
+}
+
+/// 按当前价格买入计划金额的 Ioc 限价单，限价为中间价加上滑点
+pub fn order_spec(plan: &DcaPlan, mid: f64, sz_decimals: u32) -> OrderSpec {
+    OrderSpec {
+        asset: plan.asset.clone(),
+        side: OrderSide::Buy,
+        kind: OrderKind::Limit {
+            tif: LimitType::Ioc,
+        },
+        reduce_only: false,
+        price: Some(mid * (1.0 + plan.slippage)),
+        quantity: order::round_size(plan.usd / mid, sz_decimals),
+        cloid: Some(plan.cloid()),
+    }
+}
+
+/// 查找本次执行已成交的订单：上次下单时网络出错，但交易所可能已经收到并成交
+async fn previous_fill<B: Backend>(
+    toolset: &Toolset<B>,
+    plan: &DcaPlan,
+) -> Result<Option<DcaExecution>> {
+    let cloid = action::wire_cloid(&plan.cloid());
+    let fills: Vec<UserFill> = toolset
+        .fills_since(plan.next_run.saturating_sub(CLOCK_SKEW_MS))
+        .await?
+        .into_iter()
+        .filter(|f| f.cloid.as_deref() == Some(cloid.as_str()))
+        .collect();
+    if fills.is_empty() {
+        return Ok(None);
+    }
+    let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+    let quantity: f64 = fills.iter().map(|f| parse(&f.sz)).sum();
+    let cost: f64 = fills.iter().map(|f| parse(&f.sz) * parse(&f.px)).sum();
+    let mut execution = DcaExecution::new(plan, DcaOutcome::Filled, None);
+    execution.price = (quantity > 0.0).then(|| cost / quantity);
+    execution.quantity = Some(quantity);
+    execution.oid = Some(fills[0].oid);
+    Ok(Some(execution))
+}
+
+/// 执行一次定投：价格、余额及风控护栏触发时跳过，否则以 Ioc 限价单买入
+///
+/// 只有网络错误返回 Err，此时不应顺延计划，下次检查时重试；重试前先按 cloid 查找成交，避免重复买入
+pub async fn execute<B: Backend>(toolset: &Toolset<B>, plan: &DcaPlan) -> Result<DcaExecution> {
+    if let Some(execution) = previous_fill(toolset, plan).await? {
+        return Ok(execution);
+    }
+    let (mid, sz_decimals) = match quote(toolset, plan).await {
+        Ok(quote) => quote,
+        Err(error) if ToolsetError::is_retryable(&error) => return Err(error),
+        Err(error) => return Ok(DcaExecution::failed(plan, error.to_string())),
+    };
+    let Some(mid) = mid else {
+        return Ok(DcaExecution::skipped(
+            plan,
+            format!("No price for {}", plan.asset),
+        ));
+    };
+    if let Some(max_price) = plan.max_price
+        && mid > max_price
+    {
+        return Ok(DcaExecution::skipped(
+            plan,
+            format!("Price {} is above the max price {}", mid, max_price),
+        ));
+    }
+    let available = available_usdc(toolset, plan.spot).await?;
+    if available < plan.usd {
+        return Ok(DcaExecution::skipped(
+            plan,
+            format!(
+                "Available balance {:.2} USDC is below {:.2}",
+                available, plan.usd
+            ),
+        ));
+    }
+    let spec = order_spec(plan, mid, sz_decimals);
+    if spec.quantity <= 0.0 {
+        return Ok(DcaExecution::skipped(
+            plan,
+            format!(
+                "{} USDC is below the minimum size of {}",
+                plan.usd, plan.asset
+            ),
+        ));
+    }
+
+    let result = match toolset.place_order(&spec).await {
+        Ok(mut results) if !results.is_empty() => results.remove(0),
+        Ok(_) => return Ok(DcaExecution::failed(plan, "Empty order response".into())),
+        Err(error) if ToolsetError::is_retryable(&error) => return Err(error),
+        Err(error) => {
+            return Ok(match error.downcast_ref::<ToolsetError>() {
+                Some(ToolsetError::Risk(message)) => DcaExecution::skipped(plan, message.clone()),
+                _ => DcaExecution::failed(plan, error.to_string()),
+            });
+        }
+    };
+    let mut execution = match (&result.error, result.filled_size) {
+        (Some(error), _) => DcaExecution::failed(plan, error.clone()),
+        (None, Some(filled)) if filled > 0.0 => DcaExecution::new(plan, DcaOutcome::Filled, None),
+        _ => DcaExecution::failed(
+            plan,
+            format!("Not filled within {}% of mid", plan.slippage * 100.0),
+        ),
+    };
+    execution.price = result.average_price;
+    execution.quantity = result.filled_size;
+    execution.oid = result.oid;
+    Ok(execution)
+}
+
+/// 检查一次所有计划，执行到期的计划并记录结果；网络错误的计划不顺延，下次检查时重试
+pub async fn run_due<B: Backend>(toolset: &Toolset<B>) -> Result<Vec<DcaExecution>> {
+    let address = toolset.address();
+    let now = now_ms();
+    let due: Vec<DcaPlan> = load(address)?
+        .into_values()
+        .filter(|p| p.is_due(now))
+        .collect();
+    let mut executions = vec![];
+    for plan in due {
+        let execution = execute(toolset, &plan).await?;
+        record(address, &execution)?;
+        // 重新读取，执行期间可能有计划被删除
+        let mut plans = load(address)?;
+        if let Some(plan) = plans.get_mut(&plan.id) {
+            plan.advance(now_ms());
+            save(address, &plans)?;
+        }
+        executions.push(execution);
+    }
+    Ok(executions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockFailure;
+    use crate::mock::test_support::{position, toolset};
+
+    fn plan(usd: f64, max_price: Option<f64>) -> DcaPlan {
+        let now = now_ms();
+        DcaPlan {
+            id: 1,
+            asset: "BTC".into(),
+            spot: false,
+            usd,
+            every: 3600,
+            at: None,
+            max_price,
+            slippage: 0.01,
+            next_run: now,
+            created_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn guardrails_skip_without_placing_an_order() {
+        let toolset = toolset();
+        for (plan, reason) in [
+            (
+                plan(100.0, Some(45_000.0)),
+                "Price 50000 is above the max price 45000",
+            ),
+            (
+                plan(20_000.0, None),
+                "Available balance 10000.00 USDC is below 20000.00",
+            ),
+            (plan(0.1, None), "0.1 USDC is below the minimum size of BTC"),
+        ] {
+            let execution = execute(&toolset, &plan).await.unwrap();
+            assert_eq!(execution.outcome, DcaOutcome::Skipped);
+            assert_eq!(execution.reason.as_deref(), Some(reason));
+        }
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn retry_after_a_lost_response_reuses_the_fill() {
+        let toolset = toolset();
+        let mut plan = plan(100.0, None);
+
+        // 交易所已成交，但响应丢失
+        toolset.backend().fail_next(MockFailure::LostResponse);
+        assert!(execute(&toolset, &plan).await.is_err());
+        assert_eq!(position(&toolset, "BTC"), 0.002);
+
+        // 重试时按 cloid 找到成交，不会再次买入
+        let execution = execute(&toolset, &plan).await.unwrap();
+        assert_eq!(execution.outcome, DcaOutcome::Filled);
+        assert_eq!(execution.quantity, Some(0.002));
+        assert_eq!(execution.price, Some(50_000.0));
+        assert_eq!(position(&toolset, "BTC"), 0.002);
+
+        // 顺延后的下一次执行使用新的 cloid
+        plan.advance(plan.next_run);
+        let execution = execute(&toolset, &plan).await.unwrap();
+        assert_eq!(execution.outcome, DcaOutcome::Filled);
+        assert_eq!(position(&toolset, "BTC"), 0.004);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::types::OrderResult;
+use serde::Serialize;
+use thiserror::Error;
+
+/// 命令执行失败的类型，每种类型对应一个进程退出码
+///
+/// | 退出码 | 类型 |
+/// |---|---|
+/// | 1 | 其它错误 |
+/// | 2 | 命令行参数错误(由 clap 处理) |
+/// | 3 | 配置错误 |
+/// | 4 | 输入校验失败 |
+/// | 5 | 网络错误、交易所 5xx 或限流 |
+/// | 6 | 交易所拒绝(含 4xx 请求错误) |
+/// | 7 | 批量操作部分失败 |
+/// | 8 | 超出风控限制 |
+/// | 9 | 用户取消 |
+#[derive(Debug, Clone, Error)]
+pub enum ToolsetError {
+    #[error("Configuration error: {0}")]
+    Config(String),
+    #[error("Invalid input: {0}")]
+    Validation(String),
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Rejected by exchange: {0}")]
+    Rejected(String),
+    #[error("Partially failed: {succeeded} succeeded, {failed} failed")]
+    PartialFailure { succeeded: usize, failed: usize },
+    #[error("Risk limit exceeded: {0}")]
+    Risk(String),
+    #[error("Cancelled by user")]
+    Cancelled,
+}
+
+impl ToolsetError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ToolsetError::Config(_) => 3,
+            ToolsetError::Validation(_) => 4,
+            ToolsetError::Network(_) => 5,
+            ToolsetError::Rejected(_) => 6,
+            ToolsetError::PartialFailure { .. } => 7,
+            ToolsetError::Risk(_) => 8,
+            ToolsetError::Cancelled => 9,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ToolsetError::Config(_) => "config",
+            ToolsetError::Validation(_) => "validation",
+            ToolsetError::Network(_) => "network",
+            ToolsetError::Rejected(_) => "rejected",
+            ToolsetError::PartialFailure { .. } => "partial_failure",
+            ToolsetError::Risk(_) => "risk",
+            ToolsetError::Cancelled => "cancelled",
+        }
+    }
+
+    /// 将任意错误归类，无法识别的错误(如本地文件读写失败)为 None
+    pub fn classify(error: &anyhow::Error) -> Option<ToolsetError> {
+        if let Some(e) = error.downcast_ref::<ToolsetError>() {
+            Some(e.clone())
+        } else {
+            error
+                .downcast_ref::<hyperliquid_rust_sdk::Error>()
+                .map(|e| ToolsetError::from(e.clone()))
+        }
+    }
+
+    /// 是否为网络错误(含交易所 5xx 及限流)，守护进程遇到这类错误时等待后重试
+    pub fn is_retryable(error: &anyhow::Error) -> bool {
+        matches!(Self::classify(error), Some(ToolsetError::Network(_)))
+    }
+
+    /// 根据每个订单的执行结果判断是否全部或部分被拒绝
+    pub fn from_results(results: &[OrderResult]) -> Option<ToolsetError> {
+        let failed: Vec<&str> = results.iter().filter_map(|r| r.error.as_deref()).collect();
+        if failed.is_empty() {
+            None
+        } else if failed.len() == results.len() {
+            Some(ToolsetError::Rejected(failed.join("; ")))
+        } else {
+            Some(ToolsetError::PartialFailure {
+                succeeded: results.len() - failed.len(),
+                failed: failed.len(),
+            })
+        }
+    }
+}
+
+impl From<hyperliquid_rust_sdk::Error> for ToolsetError {
+    /// 只有传输错误、5xx 及限流(429)归为网络错误，守护进程会重试这类错误；
+    /// 其它 4xx、签名及解析错误重试也不会成功
+    fn from(error: hyperliquid_rust_sdk::Error) -> Self {
+        use hyperliquid_rust_sdk::Error;
+        match error {
+            Error::ClientRequest {
+                status_code: 429, ..
+            }
+            | Error::ServerRequest { .. }
+            | Error::GenericRequest(_)
+            | Error::Websocket(_)
+            | Error::WsSend(_)
+            | Error::ReaderDataNotFound
+            | Error::GenericReader(_)
+            | Error::ReaderTextConversion(_) => ToolsetError::Network(error.to_string()),
+            Error::ClientRequest { .. }
+            | Error::JsonParse(_)
+            | Error::GenericParse(_)
+            | Error::RmpParse(_) => ToolsetError::Rejected(error.to_string()),
+            Error::AssetNotFound
+            | Error::FloatStringParse
+            | Error::OrderTypeNotFound
+            | Error::NoCloid
+            | Error::UserEvents
+            | Error::SubscriptionNotFound
+            | Error::WsManagerNotFound => ToolsetError::Validation(error.to_string()),
+            Error::Wallet(_)
+            | Error::PrivateKeyParse(_)
+            | Error::SignatureFailure(_)
+            | Error::Eip712(_)
+            | Error::RandGen(_)
+            | Error::ChainNotAllowed
+            | Error::VaultAddressNotFound => ToolsetError::Config(error.to_string()),
+        }
+    }
+}
+
+/// 结构化输出时写入 stderr 的错误信息
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReport {
+    pub kind: &'static str,
+    pub message: String,
+    pub exit_code: u8,
+}
+
+impl ErrorReport {
+    /// 将任意错误归类，无法识别的错误退出码为 1
+    pub fn new(error: &anyhow::Error) -> Self {
+        let (kind, exit_code) = ToolsetError::classify(error)
+            .map(|e| (e.kind(), e.exit_code()))
+            .unwrap_or(("other", 1));
+        Self {
+            kind,
+            message: error.to_string(),
+            exit_code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_request(status_code: u16) -> anyhow::Error {
+        hyperliquid_rust_sdk::Error::ClientRequest {
+            status_code,
+            error_code: None,
+            error_message: "error".to_string(),
+            error_data: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn only_network_errors_are_retryable() {
+        assert!(ToolsetError::is_retryable(
+            &ToolsetError::Network("timeout".into()).into()
+        ));
+        assert!(ToolsetError::is_retryable(&client_request(429)));
+        assert!(!ToolsetError::is_retryable(&client_request(400)));
+        assert!(!ToolsetError::is_retryable(
+            &ToolsetError::Rejected("Insufficient margin".into()).into()
+        ));
+        assert!(!ToolsetError::is_retryable(&anyhow::anyhow!("disk full")));
+        assert_eq!(ErrorReport::new(&client_request(400)).exit_code, 6);
+    }
+}
@@ -0,0 +1,389 @@
+use crate::backend::Backend;
+use crate::error::ToolsetError;
+use crate::toolset::Toolset;
+use anyhow::Result;
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// 最短抓取间隔
+pub const MIN_SCRAPE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Prometheus 文本格式的 Content-Type
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 解析监听地址，`:9100` 表示监听所有网卡的 9100 端口
+pub fn parse_listen(value: &str) -> Result<SocketAddr, String> {
+    let value = match value.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => value.to_string(),
+    };
+    value
+        .parse()
+        .map_err(|e| format!("Invalid listen address {}: {}", value, e))
+}
+
+/// 一个指标及其所有样本
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    kind: &'static str,
+    samples: Vec<(Vec<(&'static str, String)>, f64)>,
+}
+
+/// 按 Prometheus 文本格式输出的一组指标
+#[derive(Default)]
+pub struct Metrics {
+    families: Vec<Family>,
+}
+
+impl Metrics {
+    fn family(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        kind: &'static str,
+    ) -> &mut Family {
+        let index = match self.families.iter().position(|f| f.name == name) {
+            Some(index) => index,
+            None => {
+                self.families.push(Family {
+                    name,
+                    help,
+                    kind,
+                    samples: vec![],
+                });
+                self.families.len() - 1
+            }
+        };
+        &mut self.families[index]
+    }
+
+    fn gauge(&mut self, name: &'static str, help: &'static str, value: f64) {
+        self.labeled(name, help, vec![], value);
+    }
+
+    fn labeled(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        labels: Vec<(&'static str, String)>,
+        value: f64,
+    ) {
+        self.family(name, help, "gauge")
+            .samples
+            .push((labels, value));
+    }
+
+    fn counter(&mut self, name: &'static str, help: &'static str, value: f64) {
+        self.family(name, help, "counter")
+            .samples
+            .push((vec![], value));
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for family in &self.families {
+            let _ = writeln!(text, "# HELP {} {}", family.name, family.help);
+            let _ = writeln!(text, "# TYPE {} {}", family.name, family.kind);
+            for (labels, value) in &family.samples {
+                text.push_str(family.name);
+                if !labels.is_empty() {
+                    let labels: Vec<String> = labels
+                        .iter()
+                        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+                        .collect();
+                    let _ = write!(text, "{{{}}}", labels.join(","));
+                }
+                let _ = writeln!(text, " {}", value);
+            }
+        }
+        text
+    }
+}
+
+/// 转义标签值中的反斜杠、双引号及换行
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn parse(value: &str) -> f64 {
+    value.parse::<f64>().unwrap_or(0.0)
+}
+
+/// 执行请求并记录耗时
+async fn timed<T>(future: impl Future<Output = Result<T>>) -> (Duration, Result<T>) {
+    let start = Instant::now();
+    let result = future.await;
+    (start.elapsed(), result)
+}
+
+/// 抓取一次账户及行情，返回指标及失败的请求；部分请求失败时仍输出成功部分的指标
+pub async fn scrape<B: Backend>(toolset: &Toolset<B>) -> (Metrics, Vec<String>) {
+    let address = toolset.address();
+    let (state, orders, mids, markets) = tokio::join!(
+        timed(toolset.backend().user_state(address)),
+        timed(toolset.open_orders()),
+        timed(toolset.mids()),
+        timed(toolset.markets()),
+    );
+    let mut metrics = Metrics::default();
+    let mut errors = vec![];
+    for (endpoint, latency, error) in [
+        ("user_state", state.0, state.1.as_ref().err()),
+        ("open_orders", orders.0, orders.1.as_ref().err()),
+        ("all_mids", mids.0, mids.1.as_ref().err()),
+        ("meta_and_asset_ctxs", markets.0, markets.1.as_ref().err()),
+    ] {
+        metrics.labeled(
+            "hlt_api_latency_seconds",
+            "Duration of the last API request",
+            vec![("endpoint", endpoint.to_string())],
+            latency.as_secs_f64(),
+        );
+        metrics.labeled(
+            "hlt_api_up",
+            "Whether the last API request succeeded",
+            vec![("endpoint", endpoint.to_string())],
+            if error.is_none() { 1.0 } else { 0.0 },
+        );
+        if let Some(error) = error {
+            errors.push(format!("{}: {}", endpoint, error));
+        }
+    }
+
+    let mids: BTreeMap<String, f64> = mids
+        .1
+        .map(|mids| mids.into_iter().map(|m| (m.asset, m.price)).collect())
+        .unwrap_or_default();
+    if let Ok(state) = &state.1 {
+        metrics.gauge(
+            "hlt_account_value_usd",
+            "Account value of the perpetuals account",
+            parse(&state.margin_summary.account_value),
+        );
+        metrics.gauge(
+            "hlt_margin_used_usd",
+            "Total margin used",
+            parse(&state.margin_summary.total_margin_used),
+        );
+        metrics.gauge(
+            "hlt_withdrawable_usd",
+            "Withdrawable balance",
+            parse(&state.withdrawable),
+        );
+        for position in state
+            .asset_positions
+            .iter()
+            .map(crate::types::Position::from)
+        {
+            let labels = || vec![("asset", position.asset.clone())];
+            metrics.labeled(
+                "hlt_position_size",
+                "Signed position size, negative for shorts",
+                labels(),
+                position.quantity,
+            );
+            metrics.labeled(
+                "hlt_position_notional_usd",
+                "Position value",
+                labels(),
+                position.position_value,
+            );
+            metrics.labeled(
+                "hlt_position_unrealized_pnl_usd",
+                "Unrealized PnL of the position",
+                labels(),
+                position.unrealized_pnl,
+            );
+            if let (Some(liquidation), Some(mid)) =
+                (position.liquidation_price, mids.get(&position.asset))
+                && *mid > 0.0
+            {
+                metrics.labeled(
+                    "hlt_position_liquidation_distance_ratio",
+                    "Distance between the mid price and the liquidation price, relative to the mid price",
+                    labels(),
+                    (mid - liquidation).abs() / mid,
+                );
+            }
+        }
+    }
+    if let Ok(orders) = &orders.1 {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for order in orders {
+            *counts.entry(&order.asset).or_default() += 1;
+        }
+        for (asset, count) in counts {
+            metrics.labeled(
+                "hlt_open_orders",
+                "Number of open orders per asset",
+                vec![("asset", asset.to_string())],
+                count as f64,
+            );
+        }
+    }
+    for (asset, mid) in &mids {
+        metrics.labeled(
+            "hlt_mid_price",
+            "Mid price of the perpetual",
+            vec![("asset", asset.clone())],
+            *mid,
+        );
+    }
+    if let Ok(markets) = &markets.1 {
+        for market in markets {
+            metrics.labeled(
+                "hlt_funding_rate",
+                "Current hourly funding rate",
+                vec![("asset", market.asset.clone())],
+                market.funding,
+            );
+        }
+    }
+    (metrics, errors)
+}
+
+/// 定期抓取并在 `listen` 的 `/metrics` 上提供最近一次的指标，直到 Ctrl-C
+///
+/// 抓取失败时输出成功部分的指标，`hlt_scrape_success` 为 0
+pub async fn run<B: Backend>(
+    toolset: &Toolset<B>,
+    listen: SocketAddr,
+    interval: Duration,
+) -> Result<()> {
+    let latest = Arc::new(RwLock::new(String::new()));
+    let app = Router::new()
+        .route("/metrics", get(metrics))
+        .fallback(|| async { (StatusCode::NOT_FOUND, "Metrics are served at /metrics\n") })
+        .with_state(latest.clone());
+    let server = axum::Server::try_bind(&listen)
+        .map_err(|e| ToolsetError::Config(format!("Cannot listen on {}: {}", listen, e)))?
+        .serve(app.into_make_service());
+    let server = tokio::spawn(server);
+
+    let mut failures = 0u64;
+    loop {
+        let start = Instant::now();
+        let (mut metrics, errors) = scrape(toolset).await;
+        if !errors.is_empty() {
+            failures += 1;
+            eprintln!("  🔴 Scrape failed: {}", errors.join("; "));
+        }
+        metrics.gauge(
+            "hlt_scrape_success",
+            "Whether all requests of the last scrape succeeded",
+            if errors.is_empty() { 1.0 } else { 0.0 },
+        );
+        metrics.gauge(
+            "hlt_scrape_duration_seconds",
+            "Duration of the last scrape",
+            start.elapsed().as_secs_f64(),
+        );
+        metrics.gauge(
+            "hlt_scrape_timestamp_seconds",
+            "Unix time of the last scrape",
+            chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
+        );
+        metrics.counter(
+            "hlt_scrape_failures_total",
+            "Number of scrapes with at least one failed request",
+            failures as f64,
+        );
+        if let Ok(mut latest) = latest.write() {
+            *latest = metrics.render();
+        }
+
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+        if server.is_finished() {
+            break;
+        }
+    }
+    if server.is_finished() {
+        server.await??;
+    } else {
+        server.abort();
+    }
+    Ok(())
+}
+
+async fn metrics(State(latest): State<Arc<RwLock<String>>>) -> impl IntoResponse {
+    let body = latest.read().map(|m| m.clone()).unwrap_or_default();
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::test_support::{limit, toolset};
+    use crate::order::{LimitType, OrderSide};
+
+    #[test]
+    fn metrics_render_in_the_prometheus_text_format() {
+        let mut metrics = Metrics::default();
+        metrics.gauge("hlt_value", "A value", 1.5);
+        metrics.labeled("hlt_size", "A size", vec![("asset", "BTC".into())], 2.0);
+        metrics.labeled(
+            "hlt_size",
+            "A size",
+            vec![("asset", "a\"b\\c\nd".into())],
+            -3.0,
+        );
+        metrics.counter("hlt_total", "A total", 4.0);
+        assert_eq!(
+            metrics.render(),
+            r#"# HELP hlt_value A value
+# TYPE hlt_value gauge
+hlt_value 1.5
+# HELP hlt_size A size
+# TYPE hlt_size gauge
+hlt_size{asset="BTC"} 2
+hlt_size{asset="a\"b\\c\nd"} -3
+# HELP hlt_total A total
+# TYPE hlt_total counter
+hlt_total 4
+"#
+        );
+    }
+
+    #[tokio::test]
+    async fn scrape_reports_account_orders_and_prices() {
+        let toolset = toolset();
+        let buy = limit("BTC", OrderSide::Buy, LimitType::Ioc, 51_000.0, 0.1);
+        let rest = limit("ETH", OrderSide::Buy, LimitType::Gtc, 2_900.0, 1.0);
+        toolset.place_orders(&[buy, rest]).await.unwrap();
+
+        let (metrics, errors) = scrape(&toolset).await;
+        assert!(errors.is_empty());
+        let text = metrics.render();
+        for line in [
+            r#"hlt_api_up{endpoint="user_state"} 1"#,
+            r#"hlt_api_up{endpoint="meta_and_asset_ctxs"} 1"#,
+            r#"hlt_position_size{asset="BTC"} 0.1"#,
+            r#"hlt_position_notional_usd{asset="BTC"} 5000"#,
+            r#"hlt_open_orders{asset="ETH"} 1"#,
+            r#"hlt_mid_price{asset="BTC"} 50000"#,
+            r#"hlt_mid_price{asset="ETH"} 3000"#,
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+        assert!(text.contains("\nhlt_account_value_usd "));
+        assert!(text.contains("\nhlt_funding_rate{asset=\"BTC\"} "));
+        assert!(!text.contains("hlt_open_orders{asset=\"BTC\"}"));
+    }
+}
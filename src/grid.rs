@@ -0,0 +1,534 @@
+use crate::backend::Backend;
+use crate::error::ToolsetError;
+use crate::order::{self, LimitType, OrderKind, OrderSide, OrderSpec};
+use crate::store;
+use crate::toolset::{Toolset, now_ms};
+use crate::types::MarketInfo;
+use anyhow::Result;
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 网格价位的间隔方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GridSpacing {
+    /// 等差：相邻价位的价差相同
+    Arithmetic,
+    /// 等比：相邻价位的涨跌幅相同
+    Geometric,
+}
+
+/// 网格运行状态
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GridStatus {
+    Running,
+    /// 已停止并撤销所有网格挂单
+    Stopped,
+}
+
+/// 本地时钟与交易所时钟的最大偏差，按下单时间查询成交时向前多查这段时间
+const CLOCK_SKEW_MS: u64 = 60_000;
+
+/// 网格中的一个挂单
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GridOrder {
+    /// 所在价位在 [`Grid::prices`] 中的下标
+    pub level: usize,
+    pub is_buy: bool,
+    /// 交易所中的订单ID，下单失败时为 None，下次检查时重新下单
+    pub oid: Option<u64>,
+    /// 开仓单成交后在相邻价位补的反向单，成交后完成一次低买高卖(或高卖低买)
+    pub closing: bool,
+    /// 下单时间(毫秒时间戳)，订单不在挂单中时只查询这之后的成交
+    #[serde(default)]
+    pub placed_at: u64,
+    /// 该价位之前的订单未全部成交就被撤销时已成交的数量，重新挂单时只挂剩余数量
+    #[serde(default)]
+    pub filled: f64,
+}
+
+/// 网格交易：在价格区间内每个价位挂买卖单，成交后在相邻价位挂反向单赚取差价
+///
+/// 当前价之下挂买单、之上挂卖单，最接近当前价的价位留空；永续合约中高于当前价的卖单成交后持有空仓
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Grid {
+    pub asset: String,
+    pub lower: f64,
+    pub upper: f64,
+    pub spacing: GridSpacing,
+    /// 按交易所价格规则取整后的价位，从低到高
+    pub prices: Vec<f64>,
+    /// 每个价位的下单数量
+    pub quantity: f64,
+    /// 投入的 USDC
+    pub investment: f64,
+    pub orders: Vec<GridOrder>,
+    /// 完成的低买高卖次数
+    pub round_trips: u64,
+    /// 按价位差计算的已实现收益，未扣除手续费
+    pub realized_profit: f64,
+    /// 网格订单成交支付的手续费
+    pub fees: f64,
+    pub status: GridStatus,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl Grid {
+    /// 按区间及当前价格生成网格，每个价位的数量为投入金额平均分配到挂单价位后按当前价换算
+    pub fn new(
+        market: &MarketInfo,
+        lower: f64,
+        upper: f64,
+        levels: usize,
+        investment: f64,
+        spacing: GridSpacing,
+    ) -> Result<Self, ToolsetError> {
+        let asset = &market.asset;
+        let mid = market.mid_price.unwrap_or(market.mark_price);
+        let sz_decimals = market.sz_decimals;
+        if !(lower > 0.0 && lower < upper) {
+            return Err(ToolsetError::Validation(format!(
+                "--lower must be positive and below --upper: {} - {}",
+                lower, upper
+            )));
+        }
+        if levels < 2 {
+            return Err(ToolsetError::Validation(
+                "--levels must be at least 2".into(),
+            ));
+        }
+        if investment <= 0.0 {
+            return Err(ToolsetError::Validation(format!(
+                "--investment must be positive: {}",
+                investment
+            )));
+        }
+        if !(lower < mid && mid < upper) {
+            return Err(ToolsetError::Validation(format!(
+                "The mid price {} of {} is outside the grid range {} - {}",
+                mid, asset, lower, upper
+            )));
+        }
+
+        let steps = (levels - 1) as f64;
+        let prices: Vec<f64> = (0..levels)
+            .map(|i| {
+                let i = i as f64;
+                let price = match spacing {
+                    GridSpacing::Arithmetic => lower + (upper - lower) * i / steps,
+                    GridSpacing::Geometric => lower * (upper / lower).powf(i / steps),
+                };
+                order::round_price(price, sz_decimals)
+            })
+            .collect();
+        if prices.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(ToolsetError::Validation(format!(
+                "{} levels are too dense for the range {} - {}",
+                levels, lower, upper
+            )));
+        }
+        let quantity = order::round_size(investment / steps / mid, sz_decimals);
+        if quantity <= 0.0 {
+            return Err(ToolsetError::Validation(format!(
+                "--investment {} is too small for {} levels of {}",
+                investment, levels, asset
+            )));
+        }
+
+        let now = now_ms();
+        let mut grid = Self {
+            asset: asset.clone(),
+            lower,
+            upper,
+            spacing,
+            prices,
+            quantity,
+            investment,
+            orders: vec![],
+            round_trips: 0,
+            realized_profit: 0.0,
+            fees: 0.0,
+            status: GridStatus::Running,
+            created_at: now,
+            updated_at: now,
+        };
+        let gap = grid.nearest_level(mid);
+        grid.orders = (0..levels)
+            .filter(|&level| level != gap)
+            .map(|level| GridOrder {
+                level,
+                is_buy: level < gap,
+                oid: None,
+                closing: false,
+                placed_at: 0,
+                filled: 0.0,
+            })
+            .collect();
+        Ok(grid)
+    }
+
+    /// 最接近 `price` 的价位
+    fn nearest_level(&self, price: f64) -> usize {
+        self.prices
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - price).abs().total_cmp(&(*b - price).abs()))
+            .map(|(level, _)| level)
+            .unwrap_or_default()
+    }
+
+    /// 网格订单：在价位上挂 Gtc 限价单，数量为该价位未成交的数量
+    pub fn order_spec(&self, order: &GridOrder) -> OrderSpec {
+        OrderSpec {
+            asset: self.asset.clone(),
+            side: if order.is_buy {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            },
+            kind: OrderKind::Limit {
+                tif: LimitType::Gtc,
+            },
+            reduce_only: false,
+            price: Some(self.prices[order.level]),
+            quantity: self.quantity - order.filled,
+            cloid: None,
+        }
+    }
+
+    /// 尚未在交易所中挂出的订单
+    pub fn pending_specs(&self) -> Vec<OrderSpec> {
+        self.orders
+            .iter()
+            .filter(|o| o.oid.is_none())
+            .map(|o| self.order_spec(o))
+            .collect()
+    }
+
+    /// 扣除手续费后的收益
+    pub fn net_profit(&self) -> f64 {
+        self.realized_profit - self.fees
+    }
+}
+
+/// 网格订单的一次成交
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GridFill {
+    pub is_buy: bool,
+    pub price: f64,
+    pub quantity: f64,
+    pub fee: f64,
+    /// 完成一次低买高卖时按价位差计算的收益
+    pub profit: Option<f64>,
+    /// 补挂反向单的价位
+    pub next_price: f64,
+}
+
+/// 一次检查后的状态
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GridUpdate {
+    pub fills: Vec<GridFill>,
+    /// 未成交却已不在挂单中(如被手动撤销)、已重新挂出的价位
+    pub replaced: Vec<f64>,
+    /// 本次下单失败的订单，下次检查时重试
+    pub errors: Vec<String>,
+    pub grid: Grid,
+}
+
+fn grids_file(address: H160) -> String {
+    format!("grids-{:?}.json", address)
+}
+
+/// 账户的所有网格，按标的索引
+pub fn load(address: H160) -> Result<BTreeMap<String, Grid>> {
+    store::load(&grids_file(address))
+}
+
+/// 在文件锁内修改账户的网格，运行中的网格与 `hlt grid stop` 同时保存时不会互相覆盖
+pub fn update<R>(address: H160, f: impl FnOnce(&mut BTreeMap<String, Grid>) -> R) -> Result<R> {
+    store::update(&grids_file(address), f)
+}
+
+/// 挂出所有尚未挂出的网格订单，返回下单失败的原因
+async fn place_pending<B: Backend>(toolset: &Toolset<B>, grid: &mut Grid) -> Result<Vec<String>> {
+    let specs = grid.pending_specs();
+    if specs.is_empty() {
+        return Ok(vec![]);
+    }
+    let placed_at = now_ms();
+    let results = toolset.place_orders(&specs).await?;
+    let mut errors = vec![];
+    let pending = grid.orders.iter_mut().filter(|o| o.oid.is_none());
+    for ((order, result), spec) in pending.zip(results).zip(&specs) {
+        match (result.error, result.oid) {
+            (None, Some(oid)) => {
+                order.oid = Some(oid);
+                order.placed_at = placed_at;
+            }
+            (error, _) => errors.push(format!(
+                "{} @ {}: {}",
+                if order.is_buy { "buy" } else { "sell" },
+                spec.price.unwrap_or_default(),
+                error.unwrap_or("Missing order ID in response".into())
+            )),
+        }
+    }
+    Ok(errors)
+}
+
+/// 挂出初始网格，有订单被拒绝时撤销已挂出的订单并返回错误
+pub async fn start<B: Backend>(toolset: &Toolset<B>, grid: &mut Grid) -> Result<()> {
+    let errors = place_pending(toolset, grid).await?;
+    if !errors.is_empty() {
+        cancel_orders(toolset, grid).await?;
+        return Err(ToolsetError::Rejected(errors.join("; ")).into());
+    }
+    Ok(())
+}
+
+/// 检查一次网格订单：已成交的价位在相邻价位补反向单，被撤销的订单重新挂出
+pub async fn tick<B: Backend>(toolset: &Toolset<B>, grid: &mut Grid) -> Result<GridUpdate> {
+    // 先查询挂单再查询成交：订单在两次查询之间成交时，成交一定包含在之后查询的结果中
+    let open = toolset.open_orders().await?;
+    let since = grid
+        .orders
+        .iter()
+        .filter(|o| o.oid.is_some_and(|oid| !open.iter().any(|x| x.oid == oid)))
+        .map(|o| o.placed_at.max(grid.created_at))
+        .min();
+    let fills = match since {
+        Some(since) => {
+            toolset
+                .fills_since(since.saturating_sub(CLOCK_SKEW_MS))
+                .await?
+        }
+        None => vec![],
+    };
+    let sz_decimals = toolset
+        .backend()
+        .sz_decimals(&grid.asset)
+        .ok_or_else(|| ToolsetError::Validation(format!("Unknown asset: {}", grid.asset)))?;
+
+    let mut update_fills = vec![];
+    let mut replaced = vec![];
+    let mut orders = vec![];
+    for order in std::mem::take(&mut grid.orders) {
+        let Some(oid) = order.oid else {
+            orders.push(order);
+            continue;
+        };
+        if open.iter().any(|o| o.oid == oid) {
+            orders.push(order);
+            continue;
+        }
+        let (filled, fee) = fills
+            .iter()
+            .filter(|f| f.coin == grid.asset && f.oid == oid)
+            .fold((0.0, 0.0), |(size, fee), f| {
+                (
+                    size + f.sz.parse::<f64>().unwrap_or(0.0),
+                    fee + f.fee.parse::<f64>().unwrap_or(0.0),
+                )
+            });
+        grid.fees += fee;
+        let filled = order::round_size(order.filled + filled, sz_decimals);
+        if filled < grid.quantity {
+            // 未全部成交却已不在挂单中，在原价位重新挂出未成交的数量
+            replaced.push(grid.prices[order.level]);
+            orders.push(GridOrder {
+                oid: None,
+                filled,
+                ..order
+            });
+            continue;
+        }
+
+        let next = if order.is_buy {
+            order.level + 1
+        } else {
+            order.level - 1
+        };
+        let price = grid.prices[order.level];
+        let profit = order
+            .closing
+            .then(|| grid.quantity * (price - grid.prices[next]).abs());
+        if let Some(profit) = profit {
+            grid.round_trips += 1;
+            grid.realized_profit += profit;
+        }
+        update_fills.push(GridFill {
+            is_buy: order.is_buy,
+            price,
+            quantity: filled,
+            fee,
+            profit,
+            next_price: grid.prices[next],
+        });
+        orders.push(GridOrder {
+            level: next,
+            is_buy: !order.is_buy,
+            oid: None,
+            closing: !order.closing,
+            placed_at: 0,
+            filled: 0.0,
+        });
+    }
+    grid.orders = orders;
+    grid.orders.sort_by_key(|o| o.level);
+
+    let errors = place_pending(toolset, grid).await?;
+    if !update_fills.is_empty() || !replaced.is_empty() {
+        grid.updated_at = now_ms();
+    }
+    Ok(GridUpdate {
+        fills: update_fills,
+        replaced,
+        errors,
+        grid: grid.clone(),
+    })
+}
+
+/// 撤销网格的所有挂单，已成交或已撤销的订单忽略
+pub async fn cancel_orders<B: Backend>(toolset: &Toolset<B>, grid: &mut Grid) -> Result<()> {
+    let open = toolset.open_orders().await?;
+    let orders: Vec<(String, u64)> = grid
+        .orders
+        .iter()
+        .filter_map(|o| o.oid)
+        .filter(|oid| open.iter().any(|o| o.oid == *oid))
+        .map(|oid| (grid.asset.clone(), oid))
+        .collect();
+    if !orders.is_empty() {
+        let results = toolset.cancel_orders(&orders).await?;
+        if let Some(error) = ToolsetError::from_results(&results) {
+            return Err(error.into());
+        }
+    }
+    for order in &mut grid.orders {
+        order.oid = None;
+    }
+    Ok(())
+}
+
+/// 停止网格：撤销所有挂单，保留已实现收益
+pub async fn stop<B: Backend>(toolset: &Toolset<B>, grid: &mut Grid) -> Result<()> {
+    cancel_orders(toolset, grid).await?;
+    grid.orders.clear();
+    grid.status = GridStatus::Stopped;
+    grid.updated_at = now_ms();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::test_support::{position, toolset};
+
+    #[tokio::test]
+    async fn tick_closes_a_round_trip_and_replaces_cancelled_orders() {
+        let toolset = toolset();
+        let market = toolset.market("BTC").await.unwrap();
+        let mut grid = Grid::new(
+            &market,
+            49_000.0,
+            51_000.0,
+            5,
+            1_000.0,
+            GridSpacing::Arithmetic,
+        )
+        .unwrap();
+        assert_eq!(
+            grid.prices,
+            [49_000.0, 49_500.0, 50_000.0, 50_500.0, 51_000.0]
+        );
+        assert_eq!(grid.quantity, 0.005);
+        start(&toolset, &mut grid).await.unwrap();
+        assert_eq!(toolset.open_orders().await.unwrap().len(), 4);
+
+        // 价格下跌，49500 的买单成交后在 50000 挂平仓卖单
+        toolset.backend().set_mid("BTC", 49_400.0);
+        let update = tick(&toolset, &mut grid).await.unwrap();
+        assert_eq!(update.fills.len(), 1);
+        let fill = &update.fills[0];
+        assert!(fill.is_buy);
+        assert_eq!(
+            (fill.price, fill.next_price, fill.profit),
+            (49_500.0, 50_000.0, None)
+        );
+        assert!(update.errors.is_empty());
+        assert_eq!(position(&toolset, "BTC"), 0.005);
+        let closing = grid.orders.iter().find(|o| o.level == 2).unwrap();
+        assert!(closing.closing && !closing.is_buy && closing.oid.is_some());
+
+        // 价格回升，平仓卖单成交完成一次低买高卖
+        toolset.backend().set_mid("BTC", 50_100.0);
+        let update = tick(&toolset, &mut grid).await.unwrap();
+        assert_eq!(update.fills.len(), 1);
+        assert_eq!(update.fills[0].profit, Some(2.5));
+        assert_eq!((grid.round_trips, grid.realized_profit), (1, 2.5));
+        assert!(grid.fees > 0.0);
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+
+        // 手动撤销的订单在原价位重新挂出
+        let order = grid.orders.iter().find(|o| o.level == 4).unwrap();
+        toolset.cancel("BTC", order.oid.unwrap()).await.unwrap();
+        let update = tick(&toolset, &mut grid).await.unwrap();
+        assert!(update.fills.is_empty());
+        assert_eq!(update.replaced, [51_000.0]);
+        assert_eq!(toolset.open_orders().await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn partially_filled_orders_are_replaced_with_the_remainder() {
+        let toolset = toolset();
+        let market = toolset.market("BTC").await.unwrap();
+        let mut grid = Grid::new(
+            &market,
+            49_000.0,
+            51_000.0,
+            5,
+            1_000.0,
+            GridSpacing::Arithmetic,
+        )
+        .unwrap();
+        start(&toolset, &mut grid).await.unwrap();
+
+        // 49500 的买单成交 0.002 后被撤销，只重新挂出剩余的 0.003
+        let oid = grid
+            .orders
+            .iter()
+            .find(|o| o.level == 1)
+            .unwrap()
+            .oid
+            .unwrap();
+        toolset.backend().with(|sim| sim.fill_partially(oid, 0.002));
+        toolset.cancel("BTC", oid).await.unwrap();
+        let update = tick(&toolset, &mut grid).await.unwrap();
+        assert!(update.fills.is_empty());
+        assert_eq!(update.replaced, [49_500.0]);
+        let order = grid.orders.iter().find(|o| o.level == 1).unwrap();
+        assert_eq!(order.filled, 0.002);
+        let open = toolset.open_orders().await.unwrap();
+        let replaced = open.iter().find(|o| Some(o.oid) == order.oid).unwrap();
+        assert_eq!(replaced.quantity, 0.003);
+
+        // 剩余数量成交后按整个价位的数量挂平仓卖单
+        toolset.backend().set_mid("BTC", 49_400.0);
+        let update = tick(&toolset, &mut grid).await.unwrap();
+        assert_eq!(update.fills.len(), 1);
+        assert_eq!(update.fills[0].quantity, 0.005);
+        assert_eq!(position(&toolset, "BTC"), 0.005);
+        let closing = grid.orders.iter().find(|o| o.level == 2).unwrap();
+        assert!(closing.closing && closing.filled == 0.0);
+        let open = toolset.open_orders().await.unwrap();
+        let closing = open.iter().find(|o| Some(o.oid) == closing.oid).unwrap();
+        assert_eq!(closing.quantity, 0.005);
+    }
+}
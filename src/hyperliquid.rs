@@ -1,5 +1,13 @@
+use crate::action;
+use crate::error::ToolsetError;
+use crate::toolset::now_ms;
+use crate::types::{Candle, FeeSummary, MarketInfo, OrderResult, SpotMarket, UserFill};
 use anyhow::Result;
 use ethers::{signers::LocalWallet, types::H160};
+use hyperliquid_rust_sdk::{ExchangeClient, ExchangeResponseStatus, InfoClient};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::env;
 
 pub struct HyperLiquidConfig {
@@ -8,26 +16,367 @@ pub struct HyperLiquidConfig {
 }
 
 impl HyperLiquidConfig {
-    pub fn new() -> Self {
-        Self {
-            account_address: Self::get_account_address(),
-            secret_key: Self::get_secret_key(),
-        }
+    pub fn new() -> Result<Self, ToolsetError> {
+        Ok(Self {
+            account_address: Self::get_account_address()?,
+            secret_key: Self::get_secret_key()?,
+        })
     }
 
-    fn get_account_address() -> H160 {
-        let address =
-            env::var("HYPERLIQUID_ACCOUNT_ADDRESS").expect("HYPERLIQUID_ACCOUNT_ADDRESS not set");
-        address.parse::<H160>().expect("Invalid account address")
+    fn get_account_address() -> Result<H160, ToolsetError> {
+        let address = env::var("HYPERLIQUID_ACCOUNT_ADDRESS")
+            .map_err(|_| ToolsetError::Config("HYPERLIQUID_ACCOUNT_ADDRESS not set".into()))?;
+        address
+            .parse::<H160>()
+            .map_err(|e| ToolsetError::Config(format!("Invalid account address: {}", e)))
     }
 
-    fn get_secret_key() -> String {
-        env::var("HYPERLIQUID_SECRET_KEY").expect("HYPERLIQUID_SECRET_KEY not set")
+    fn get_secret_key() -> Result<String, ToolsetError> {
+        env::var("HYPERLIQUID_SECRET_KEY")
+            .map_err(|_| ToolsetError::Config("HYPERLIQUID_SECRET_KEY not set".into()))
     }
 
-    pub fn wallet(&self) -> Result<LocalWallet> {
+    pub fn wallet(&self) -> Result<LocalWallet, ToolsetError> {
         self.secret_key
             .parse()
-            .map_err(|e| anyhow::anyhow!("Error parsing wallet secret key: {}", e))
+            .map_err(|e| ToolsetError::Config(format!("Error parsing wallet secret key: {}", e)))
     }
 }
+
+/// 将交易所响应转换为每个订单的执行结果
+pub fn statuses(response: ExchangeResponseStatus) -> Result<Vec<OrderResult>> {
+    match response {
+        ExchangeResponseStatus::Ok(status) => Ok(status
+            .data
+            .map(|data| data.statuses.iter().map(OrderResult::from).collect())
+            .unwrap_or_default()),
+        ExchangeResponseStatus::Err(error) => Err(ToolsetError::Rejected(error).into()),
+    }
+}
+
+/// 发送 SDK 尚未封装的 info 请求
+pub async fn info_request<T: DeserializeOwned>(
+    info_client: &InfoClient,
+    request: serde_json::Value,
+) -> Result<T> {
+    let response = info_client
+        .http_client
+        .post("/info", request.to_string())
+        .await?;
+    serde_json::from_str(&response).map_err(|e| {
+        ToolsetError::Rejected(format!(
+            "Unexpected response for {}: {}",
+            request["type"], e
+        ))
+        .into()
+    })
+}
+
+/// 签名并发送 SDK 尚未封装的 L1 action
+pub async fn exchange_request(
+    exchange_client: &ExchangeClient,
+    action: serde_json::Value,
+) -> Result<ExchangeResponseStatus> {
+    let nonce = now_ms();
+    let signature = action::sign_l1_action(
+        &exchange_client.wallet,
+        &action,
+        nonce,
+        exchange_client.vault_address,
+        exchange_client.http_client.is_mainnet(),
+    )?;
+    let payload = action::signed_payload(action, signature, nonce, exchange_client.vault_address);
+    let response = exchange_client
+        .http_client
+        .post("/exchange", payload.to_string())
+        .await?;
+    serde_json::from_str(&response).map_err(|e| {
+        ToolsetError::Rejected(format!(
+            "Unexpected response for {}: {}",
+            payload["action"]["type"], e
+        ))
+        .into()
+    })
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PerpMeta {
+    universe: Vec<PerpAssetMeta>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PerpAssetMeta {
+    name: String,
+    sz_decimals: u32,
+    max_leverage: u32,
+    #[serde(default)]
+    is_delisted: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PerpAssetCtx {
+    funding: String,
+    open_interest: String,
+    prev_day_px: String,
+    day_ntl_vlm: String,
+    premium: Option<String>,
+    oracle_px: String,
+    mark_px: String,
+    mid_px: Option<String>,
+}
+
+/// 获取所有永续合约的元数据及市场状态，已下架的标的会被忽略
+pub async fn markets(info_client: &InfoClient) -> Result<Vec<MarketInfo>> {
+    let (meta, ctxs): (PerpMeta, Vec<PerpAssetCtx>) = info_request(
+        info_client,
+        serde_json::json!({ "type": "metaAndAssetCtxs" }),
+    )
+    .await?;
+
+    let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+    Ok(meta
+        .universe
+        .into_iter()
+        .zip(ctxs)
+        .filter(|(meta, _)| !meta.is_delisted)
+        .map(|(meta, ctx)| MarketInfo {
+            asset: meta.name,
+            max_leverage: meta.max_leverage,
+            sz_decimals: meta.sz_decimals,
+            mark_price: parse(&ctx.mark_px),
+            oracle_price: parse(&ctx.oracle_px),
+            mid_price: ctx.mid_px.as_deref().map(parse),
+            prev_day_price: parse(&ctx.prev_day_px),
+            day_volume: parse(&ctx.day_ntl_vlm),
+            open_interest: parse(&ctx.open_interest),
+            funding: parse(&ctx.funding),
+            premium: ctx.premium.as_deref().map(parse),
+        })
+        .collect())
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DailyVolume {
+    user_cross: String,
+    user_add: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct StakingDiscount {
+    discount: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UserFees {
+    daily_user_vlm: Vec<DailyVolume>,
+    user_cross_rate: String,
+    user_add_rate: String,
+    user_spot_cross_rate: Option<String>,
+    user_spot_add_rate: Option<String>,
+    active_referral_discount: String,
+    active_staking_discount: Option<StakingDiscount>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UserRateLimit {
+    cum_vlm: String,
+    n_requests_used: u64,
+    n_requests_cap: u64,
+}
+
+/// 获取账户手续费率、14日成交额、折扣以及 API 请求额度使用情况
+pub async fn fees(info_client: &InfoClient, address: H160) -> Result<FeeSummary> {
+    let (fees, limit) = tokio::try_join!(
+        info_request::<UserFees>(
+            info_client,
+            serde_json::json!({ "type": "userFees", "user": address }),
+        ),
+        info_request::<UserRateLimit>(
+            info_client,
+            serde_json::json!({ "type": "userRateLimit", "user": address }),
+        ),
+    )?;
+
+    let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+    let (taker_volume, maker_volume) = fees
+        .daily_user_vlm
+        .iter()
+        .rev()
+        .take(14)
+        .fold((0.0, 0.0), |(cross, add), v| {
+            (cross + parse(&v.user_cross), add + parse(&v.user_add))
+        });
+    Ok(FeeSummary {
+        maker_rate: parse(&fees.user_add_rate),
+        taker_rate: parse(&fees.user_cross_rate),
+        spot_maker_rate: fees.user_spot_add_rate.as_deref().map(parse),
+        spot_taker_rate: fees.user_spot_cross_rate.as_deref().map(parse),
+        taker_volume_14d: taker_volume,
+        maker_volume_14d: maker_volume,
+        referral_discount: parse(&fees.active_referral_discount),
+        staking_discount: fees
+            .active_staking_discount
+            .map(|d| parse(&d.discount))
+            .unwrap_or(0.0),
+        cum_volume: parse(&limit.cum_vlm),
+        requests_used: limit.n_requests_used,
+        requests_cap: limit.n_requests_cap,
+    })
+}
+
+/// 获取指定时间之后的成交记录，单次最多返回 2000 条
+pub async fn user_fills_by_time(
+    info_client: &InfoClient,
+    address: H160,
+    start_time: u64,
+) -> Result<Vec<UserFill>> {
+    info_request(
+        info_client,
+        serde_json::json!({
+            "type": "userFillsByTime",
+            "user": address,
+            "startTime": start_time,
+            "aggregateByTime": false,
+        }),
+    )
+    .await
+}
+
+#[derive(Deserialize, Debug)]
+struct FrontendOrder {
+    oid: u64,
+    cloid: Option<String>,
+}
+
+/// 下单时设置了 cloid 的挂单，订单ID -> cloid
+pub async fn open_order_cloids(
+    info_client: &InfoClient,
+    address: H160,
+) -> Result<HashMap<u64, String>> {
+    let orders: Vec<FrontendOrder> = info_request(
+        info_client,
+        serde_json::json!({
+            "type": "frontendOpenOrders",
+            "user": address,
+        }),
+    )
+    .await?;
+    Ok(orders
+        .into_iter()
+        .filter_map(|o| Some((o.oid, o.cloid?)))
+        .collect())
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpotToken {
+    name: String,
+    index: usize,
+    sz_decimals: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct SpotPair {
+    name: String,
+    tokens: [usize; 2],
+}
+
+#[derive(Deserialize, Debug)]
+struct SpotMeta {
+    tokens: Vec<SpotToken>,
+    universe: Vec<SpotPair>,
+}
+
+/// 获取所有现货交易对，返回 交易对名称 -> (基础代币, 计价代币)
+pub async fn spot_pairs(info_client: &InfoClient) -> Result<HashMap<String, (String, String)>> {
+    let meta: SpotMeta =
+        info_request(info_client, serde_json::json!({ "type": "spotMeta" })).await?;
+    let token_names: HashMap<usize, &str> = meta
+        .tokens
+        .iter()
+        .map(|t| (t.index, t.name.as_str()))
+        .collect();
+    Ok(meta
+        .universe
+        .iter()
+        .filter_map(|p| {
+            let base = token_names.get(&p.tokens[0])?;
+            let quote = token_names.get(&p.tokens[1])?;
+            Some((p.name.clone(), (base.to_string(), quote.to_string())))
+        })
+        .collect())
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpotContext {
+    coin: String,
+    mid_px: Option<String>,
+}
+
+/// 获取所有现货交易对的精度及中间价，交易对名称为 基础代币/计价代币
+pub async fn spot_markets(info_client: &InfoClient) -> Result<Vec<SpotMarket>> {
+    let (meta, contexts): (SpotMeta, Vec<SpotContext>) = info_request(
+        info_client,
+        serde_json::json!({ "type": "spotMetaAndAssetCtxs" }),
+    )
+    .await?;
+    let tokens: HashMap<usize, &SpotToken> = meta.tokens.iter().map(|t| (t.index, t)).collect();
+    let mids: HashMap<&str, f64> = contexts
+        .iter()
+        .filter_map(|c| Some((c.coin.as_str(), c.mid_px.as_deref()?.parse().ok()?)))
+        .collect();
+    Ok(meta
+        .universe
+        .iter()
+        .filter_map(|p| {
+            let base = tokens.get(&p.tokens[0])?;
+            let quote = tokens.get(&p.tokens[1])?;
+            Some(SpotMarket {
+                pair: format!("{}/{}", base.name, quote.name),
+                base: base.name.clone(),
+                quote: quote.name.clone(),
+                sz_decimals: base.sz_decimals,
+                mid_price: mids.get(p.name.as_str()).copied(),
+            })
+        })
+        .collect())
+}
+
+/// 获取 `start_time` 之后的K线，`interval` 如 1m、15m、1h、1d
+pub async fn candles(
+    info_client: &InfoClient,
+    asset: &str,
+    interval: &str,
+    start_time: u64,
+) -> Result<Vec<Candle>> {
+    let end_time = now_ms();
+    let candles = info_client
+        .candles_snapshot(
+            asset.to_string(),
+            interval.to_string(),
+            start_time,
+            end_time,
+        )
+        .await?;
+    let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+    Ok(candles
+        .iter()
+        .map(|c| Candle {
+            open_time: c.time_open,
+            close_time: c.time_close,
+            open: parse(&c.open),
+            high: parse(&c.high),
+            low: parse(&c.low),
+            close: parse(&c.close),
+            volume: parse(&c.vlm),
+        })
+        .collect())
+}
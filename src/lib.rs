@@ -1,6 +1,29 @@
+pub mod action;
+pub mod alert;
+pub mod backend;
+pub mod batch;
+pub mod config;
+pub mod cost;
+pub mod dca;
+pub mod error;
+pub mod exporter;
+pub mod grid;
 pub mod hyperliquid;
+pub mod mock;
+pub mod oco;
+pub mod order;
+pub mod output;
+pub mod paper;
+pub mod risk;
+pub mod rules;
+pub mod serve;
+pub mod store;
+pub mod stream;
+pub mod toolset;
+pub mod trail;
 pub mod tui;
 pub mod types;
 pub mod ui;
 
 pub use crate::hyperliquid::HyperLiquidConfig;
+pub use crate::toolset::Toolset;
@@ -0,0 +1,1001 @@
+use crate::action;
+use crate::backend::{ExchangeBackend, InfoBackend};
+use crate::error::ToolsetError;
+use crate::toolset::{DEFAULT_LEVERAGE, now_ms};
+use crate::types::{Candle, FeeSummary, MarketInfo, SpotMarket, UserFill};
+use anyhow::Result;
+use ethers::types::H160;
+use hyperliquid_rust_sdk::{
+    AssetPosition, ClientCancelRequest, ClientModifyRequest, ClientOrder, ClientOrderRequest,
+    CumulativeFunding, ExchangeDataStatus, ExchangeDataStatuses, ExchangeResponse,
+    ExchangeResponseStatus, FilledOrder, L2SnapshotResponse, Level, Leverage, MarginSummary,
+    OpenOrdersResponse, PositionData, RecentTradesResponse, RestingOrder, UserStateResponse,
+    UserTokenBalance, UserTokenBalanceResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// 模拟撮合的吃单费率
+pub const TAKER_FEE: f64 = 0.00045;
+/// 模拟撮合的挂单费率
+pub const MAKER_FEE: f64 = 0.00015;
+/// 定时撤单时间距当前时间的最小间隔
+const MIN_SCHEDULE_CANCEL_MS: u64 = 5_000;
+
+/// 模拟市场
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimMarket {
+    pub asset: String,
+    pub sz_decimals: u32,
+    pub max_leverage: u32,
+    pub mid: f64,
+    /// 每小时资金费率
+    #[serde(default)]
+    pub funding: f64,
+    /// 买盘 (价格, 数量)，由高到低排列；为空时按中间价撮合
+    #[serde(skip)]
+    pub bids: Vec<(f64, f64)>,
+    /// 卖盘 (价格, 数量)，由低到高排列
+    #[serde(skip)]
+    pub asks: Vec<(f64, f64)>,
+}
+
+/// 模拟仓位，`szi` 为带方向的数量，空头为负
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SimPosition {
+    pub szi: f64,
+    pub entry_px: f64,
+    pub leverage: u32,
+    /// 开仓以来累计支付的资金费(正数为支出)
+    #[serde(default)]
+    pub cum_funding: f64,
+}
+
+/// 模拟触发条件
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimTrigger {
+    pub trigger_px: f64,
+    pub is_market: bool,
+    /// tp / sl
+    pub tpsl: String,
+}
+
+/// 模拟挂单
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimOrder {
+    pub oid: u64,
+    pub asset: String,
+    pub is_buy: bool,
+    pub limit_px: f64,
+    pub sz: f64,
+    pub orig_sz: f64,
+    pub reduce_only: bool,
+    pub tif: String,
+    pub trigger: Option<SimTrigger>,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub cloid: Option<String>,
+}
+
+/// 模拟账户，可序列化以便持久化
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SimAccount {
+    /// 已实现的 USDC 余额(不含未实现盈亏)
+    pub usdc: f64,
+    pub positions: BTreeMap<String, SimPosition>,
+    pub orders: Vec<SimOrder>,
+    pub fills: Vec<UserFill>,
+    pub next_oid: u64,
+    pub next_tid: u64,
+    /// 定时撤销所有挂单的时间(毫秒时间戳)
+    #[serde(default)]
+    pub scheduled_cancel: Option<u64>,
+}
+
+/// 基于中间价撮合的模拟交易所
+///
+/// - 穿价的限价单以中间价立即成交(吃单)，设置了盘口时逐档吃单，其余限价单挂单，中间价穿过挂单价时以挂单价成交(挂单)
+/// - Alo 订单穿价时被拒绝，Ioc 订单未能立即成交时被拒绝
+/// - 触发单在中间价满足触发条件后按市场价成交，或转为限价单
+/// - 到达定时撤单时间后撤销所有挂单
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SimExchange {
+    pub markets: BTreeMap<String, SimMarket>,
+    pub account: SimAccount,
+}
+
+impl SimExchange {
+    pub fn new(usdc: f64) -> Self {
+        Self {
+            markets: BTreeMap::new(),
+            account: SimAccount {
+                usdc,
+                next_oid: 1,
+                next_tid: 1,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn with_account(account: SimAccount) -> Self {
+        Self {
+            markets: BTreeMap::new(),
+            account,
+        }
+    }
+
+    pub fn add_market(&mut self, asset: &str, mid: f64, sz_decimals: u32, max_leverage: u32) {
+        self.markets.insert(
+            asset.to_string(),
+            SimMarket {
+                asset: asset.to_string(),
+                sz_decimals,
+                max_leverage,
+                mid,
+                funding: 0.0,
+                bids: vec![],
+                asks: vec![],
+            },
+        );
+    }
+
+    /// 设置盘口，下单时按盘口逐档成交
+    pub fn set_book(&mut self, asset: &str, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) {
+        if let Some(market) = self.markets.get_mut(asset) {
+            market.bids = bids;
+            market.asks = asks;
+        }
+    }
+
+    /// 更新中间价，并撮合因此满足条件的挂单及触发单
+    pub fn set_mid(&mut self, asset: &str, mid: f64) {
+        if let Some(market) = self.markets.get_mut(asset) {
+            market.mid = mid;
+            self.match_resting(asset);
+        }
+    }
+
+    pub fn mid(&self, asset: &str) -> Option<f64> {
+        self.markets.get(asset).map(|m| m.mid)
+    }
+
+    fn next_oid(&mut self) -> u64 {
+        let oid = self.account.next_oid.max(1);
+        self.account.next_oid = oid + 1;
+        oid
+    }
+
+    fn position_szi(&self, asset: &str) -> f64 {
+        self.account
+            .positions
+            .get(asset)
+            .map(|p| p.szi)
+            .unwrap_or(0.0)
+    }
+
+    /// 浮点数求和从 -0.0 开始，空账户会显示为 -0.0，因此以 0.0 为初值累加
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.account
+            .positions
+            .iter()
+            .map(|(asset, p)| p.szi * (self.mid(asset).unwrap_or(p.entry_px) - p.entry_px))
+            .fold(0.0, |sum, v| sum + v)
+    }
+
+    pub fn account_value(&self) -> f64 {
+        self.account.usdc + self.unrealized_pnl()
+    }
+
+    pub fn margin_used(&self) -> f64 {
+        self.account
+            .positions
+            .iter()
+            .map(|(asset, p)| {
+                p.szi.abs() * self.mid(asset).unwrap_or(p.entry_px) / p.leverage as f64
+            })
+            .fold(0.0, |sum, v| sum + v)
+    }
+
+    /// 下单，返回交易所对该订单的处理结果
+    pub fn place(&mut self, order: ClientOrderRequest) -> ExchangeDataStatus {
+        let Some(market) = self.markets.get(&order.asset).cloned() else {
+            return ExchangeDataStatus::Error(format!("Unknown asset: {}", order.asset));
+        };
+        let mut sz = order.sz;
+        if sz <= 0.0 {
+            return ExchangeDataStatus::Error("Order has zero size.".to_string());
+        }
+
+        let szi = self.position_szi(&order.asset);
+        if order.reduce_only {
+            let reduces = (order.is_buy && szi < 0.0) || (!order.is_buy && szi > 0.0);
+            if !reduces {
+                return ExchangeDataStatus::Error(
+                    "Reduce only order would increase position.".to_string(),
+                );
+            }
+            sz = sz.min(szi.abs());
+        } else {
+            let increase = if (order.is_buy && szi >= 0.0) || (!order.is_buy && szi <= 0.0) {
+                sz
+            } else {
+                (sz - szi.abs()).max(0.0)
+            };
+            let leverage = self.leverage(&order.asset, market.max_leverage);
+            let required = increase * order.limit_px / leverage as f64;
+            if required > self.account_value() - self.margin_used() {
+                return ExchangeDataStatus::Error(
+                    "Insufficient margin to place order.".to_string(),
+                );
+            }
+        }
+
+        let oid = self.next_oid();
+        let (tif, trigger) = match order.order_type {
+            ClientOrder::Limit(limit) => (limit.tif, None),
+            ClientOrder::Trigger(trigger) => (
+                "Gtc".to_string(),
+                Some(SimTrigger {
+                    trigger_px: trigger.trigger_px,
+                    is_market: trigger.is_market,
+                    tpsl: trigger.tpsl,
+                }),
+            ),
+        };
+        let sim_order = SimOrder {
+            oid,
+            asset: order.asset.clone(),
+            is_buy: order.is_buy,
+            limit_px: order.limit_px,
+            sz,
+            orig_sz: sz,
+            reduce_only: order.reduce_only,
+            tif,
+            trigger,
+            timestamp: now_ms(),
+            cloid: order.cloid.as_ref().map(action::wire_cloid),
+        };
+
+        if sim_order.trigger.is_some() {
+            self.account.orders.push(sim_order);
+            self.match_resting(&order.asset);
+            return ExchangeDataStatus::Resting(RestingOrder { oid });
+        }
+
+        let best = if order.is_buy {
+            market.asks.first()
+        } else {
+            market.bids.first()
+        }
+        .map(|(px, _)| *px)
+        .unwrap_or(market.mid);
+        let crosses = if order.is_buy {
+            order.limit_px >= best
+        } else {
+            order.limit_px <= best
+        };
+        match (sim_order.tif.as_str(), crosses) {
+            ("Alo", true) => ExchangeDataStatus::Error(format!(
+                "Post only order would have immediately matched, bbo was {}",
+                best
+            )),
+            (tif, true) => {
+                let (filled, avg_px) =
+                    self.take_liquidity(&order.asset, order.is_buy, order.limit_px, sz);
+                self.fill(&sim_order, avg_px, filled, false);
+                // 盘口深度不足时，剩余部分挂单(Ioc 订单则取消)
+                let remaining = sz - filled;
+                if remaining > 1e-12 && tif != "Ioc" {
+                    self.account.orders.push(SimOrder {
+                        sz: remaining,
+                        ..sim_order
+                    });
+                }
+                ExchangeDataStatus::Filled(FilledOrder {
+                    total_sz: filled.to_string(),
+                    avg_px: avg_px.to_string(),
+                    oid,
+                })
+            }
+            ("Ioc", false) => ExchangeDataStatus::Error(
+                "Order could not immediately match against any resting orders.".to_string(),
+            ),
+            _ => {
+                self.account.orders.push(sim_order);
+                ExchangeDataStatus::Resting(RestingOrder { oid })
+            }
+        }
+    }
+
+    /// 撤单
+    pub fn cancel(&mut self, asset: &str, oid: u64) -> ExchangeDataStatus {
+        let before = self.account.orders.len();
+        self.account
+            .orders
+            .retain(|o| !(o.oid == oid && o.asset == asset));
+        if self.account.orders.len() < before {
+            ExchangeDataStatus::Success
+        } else {
+            ExchangeDataStatus::Error(
+                "Order was never placed, already canceled, or filled.".to_string(),
+            )
+        }
+    }
+
+    /// 修改挂单：撤销原订单后按新的参数重新下单，修改后的订单使用新的订单ID
+    pub fn modify(&mut self, oid: u64, order: ClientOrderRequest) -> ExchangeDataStatus {
+        if !self.account.orders.iter().any(|o| o.oid == oid) {
+            return ExchangeDataStatus::Error("Cannot modify canceled or filled order".to_string());
+        }
+        self.remove_order(oid);
+        self.place(order)
+    }
+
+    /// 设置定时撤单，与交易所一样要求时间至少在 5 秒之后，为 None 时取消定时撤单
+    pub fn schedule_cancel(&mut self, time: Option<u64>, now: u64) -> ExchangeResponseStatus {
+        if let Some(time) = time
+            && time < now + MIN_SCHEDULE_CANCEL_MS
+        {
+            return ExchangeResponseStatus::Err(
+                "Scheduled cancel time too early, must be at least 5 seconds after current time"
+                    .to_string(),
+            );
+        }
+        self.account.scheduled_cancel = time;
+        ExchangeResponseStatus::Ok(ExchangeResponse {
+            response_type: "default".to_string(),
+            data: None,
+        })
+    }
+
+    /// 到达定时撤单时间后撤销所有挂单
+    pub fn run_scheduled_cancel(&mut self, now: u64) {
+        if self
+            .account
+            .scheduled_cancel
+            .is_some_and(|time| time <= now)
+        {
+            self.account.orders.clear();
+            self.account.scheduled_cancel = None;
+        }
+    }
+
+    /// 吃单，返回成交数量及成交均价；未设置盘口时全部以中间价成交
+    fn take_liquidity(&mut self, asset: &str, is_buy: bool, limit_px: f64, sz: f64) -> (f64, f64) {
+        let Some(market) = self.markets.get_mut(asset) else {
+            return (0.0, 0.0);
+        };
+        let levels = if is_buy {
+            &mut market.asks
+        } else {
+            &mut market.bids
+        };
+        if levels.is_empty() {
+            return (sz, market.mid);
+        }
+
+        let (mut filled, mut notional) = (0.0, 0.0);
+        for level in levels.iter_mut() {
+            let within = if is_buy {
+                level.0 <= limit_px
+            } else {
+                level.0 >= limit_px
+            };
+            if !within || filled >= sz {
+                break;
+            }
+            let take = level.1.min(sz - filled);
+            filled += take;
+            notional += take * level.0;
+            level.1 -= take;
+        }
+        levels.retain(|(_, level_sz)| *level_sz > 0.0);
+        if filled > 0.0 {
+            (filled, notional / filled)
+        } else {
+            (0.0, limit_px)
+        }
+    }
+
+    fn leverage(&self, asset: &str, max_leverage: u32) -> u32 {
+        self.account
+            .positions
+            .get(asset)
+            .map(|p| p.leverage)
+            .filter(|l| *l > 0)
+            .unwrap_or(DEFAULT_LEVERAGE.min(max_leverage.max(1)))
+    }
+
+    /// 撮合指定标的的挂单及触发单
+    fn match_resting(&mut self, asset: &str) {
+        let Some(mid) = self.mid(asset) else {
+            return;
+        };
+        let orders: Vec<SimOrder> = self
+            .account
+            .orders
+            .iter()
+            .filter(|o| o.asset == asset)
+            .cloned()
+            .collect();
+        for mut order in orders {
+            if let Some(trigger) = &order.trigger {
+                // 卖出: 止盈在价格上涨至触发价时触发，止损在价格下跌至触发价时触发；买入相反
+                let rising = (trigger.tpsl == "tp") != order.is_buy;
+                let triggered = if rising {
+                    mid >= trigger.trigger_px
+                } else {
+                    mid <= trigger.trigger_px
+                };
+                if !triggered {
+                    continue;
+                }
+                if trigger.is_market {
+                    self.remove_order(order.oid);
+                    self.fill_reduce_aware(&order, mid, false);
+                    continue;
+                }
+                // 转为普通限价单
+                order.trigger = None;
+                if let Some(o) = self.account.orders.iter_mut().find(|o| o.oid == order.oid) {
+                    o.trigger = None;
+                }
+            }
+            let crosses = if order.is_buy {
+                mid <= order.limit_px
+            } else {
+                mid >= order.limit_px
+            };
+            if crosses {
+                self.remove_order(order.oid);
+                self.fill_reduce_aware(&order, order.limit_px, true);
+            }
+        }
+    }
+
+    /// 按挂单价成交挂单的一部分，剩余数量继续挂单
+    pub fn fill_partially(&mut self, oid: u64, sz: f64) {
+        let Some(order) = self.account.orders.iter_mut().find(|o| o.oid == oid) else {
+            return;
+        };
+        order.sz -= sz;
+        let order = order.clone();
+        self.fill(&order, order.limit_px, sz, true);
+    }
+
+    fn remove_order(&mut self, oid: u64) {
+        self.account.orders.retain(|o| o.oid != oid);
+    }
+
+    /// 只减仓订单在成交时按当前仓位截断
+    fn fill_reduce_aware(&mut self, order: &SimOrder, px: f64, maker: bool) {
+        let mut sz = order.sz;
+        if order.reduce_only {
+            let szi = self.position_szi(&order.asset);
+            let reduces = (order.is_buy && szi < 0.0) || (!order.is_buy && szi > 0.0);
+            if !reduces {
+                return;
+            }
+            sz = sz.min(szi.abs());
+        }
+        self.fill(order, px, sz, maker);
+    }
+
+    /// 记录成交并更新仓位及余额
+    fn fill(&mut self, order: &SimOrder, px: f64, sz: f64, maker: bool) {
+        if sz <= 0.0 {
+            return;
+        }
+        let max_leverage = self
+            .markets
+            .get(&order.asset)
+            .map(|m| m.max_leverage)
+            .unwrap_or(DEFAULT_LEVERAGE);
+        let leverage = self.leverage(&order.asset, max_leverage);
+        let position = self
+            .account
+            .positions
+            .entry(order.asset.clone())
+            .or_insert_with(|| SimPosition {
+                leverage,
+                ..Default::default()
+            });
+        let signed = if order.is_buy { sz } else { -sz };
+        let mut closed_pnl = 0.0;
+
+        if position.szi == 0.0 || position.szi.signum() == signed.signum() {
+            let total = position.szi.abs() + sz;
+            position.entry_px = (position.szi.abs() * position.entry_px + sz * px) / total;
+            position.szi += signed;
+        } else {
+            let closed = sz.min(position.szi.abs());
+            closed_pnl = closed * (px - position.entry_px) * position.szi.signum();
+            position.szi += signed;
+            if position.szi.abs() < 1e-12 {
+                position.szi = 0.0;
+            } else if position.szi.signum() == signed.signum() {
+                // 反手，剩余部分以成交价开仓
+                position.entry_px = px;
+                position.cum_funding = 0.0;
+            }
+        }
+        if position.szi == 0.0 {
+            self.account.positions.remove(&order.asset);
+        }
+
+        let fee = px * sz * if maker { MAKER_FEE } else { TAKER_FEE };
+        self.account.usdc += closed_pnl - fee;
+
+        let tid = self.account.next_tid.max(1);
+        self.account.next_tid = tid + 1;
+        self.account.fills.push(UserFill {
+            coin: order.asset.clone(),
+            px: px.to_string(),
+            sz: sz.to_string(),
+            side: if order.is_buy { "B" } else { "A" }.to_string(),
+            time: now_ms(),
+            fee: fee.to_string(),
+            fee_token: "USDC".to_string(),
+            tid,
+            oid: order.oid,
+            closed_pnl: closed_pnl.to_string(),
+            cloid: order.cloid.clone(),
+        });
+    }
+
+    /// 按当前资金费率为所有仓位结算 `hours` 小时的资金费，多头在费率为正时支付
+    pub fn accrue_funding(&mut self, hours: f64) {
+        let mut total = 0.0;
+        for (asset, position) in self.account.positions.iter_mut() {
+            let Some(market) = self.markets.get(asset) else {
+                continue;
+            };
+            let payment = position.szi * market.mid * market.funding * hours;
+            position.cum_funding += payment;
+            total += payment;
+        }
+        self.account.usdc -= total;
+    }
+
+    /// 以模拟账户构造 clearinghouseState 响应
+    pub fn user_state(&self) -> UserStateResponse {
+        let account_value = self.account_value();
+        let asset_positions = self
+            .account
+            .positions
+            .iter()
+            .map(|(asset, p)| {
+                let market = self.markets.get(asset);
+                let mid = market.map(|m| m.mid).unwrap_or(p.entry_px);
+                let max_leverage = market.map(|m| m.max_leverage).unwrap_or(DEFAULT_LEVERAGE);
+                let position_value = p.szi.abs() * mid;
+                let unrealized = p.szi * (mid - p.entry_px);
+                let margin_used = position_value / p.leverage as f64;
+                // 维持保证金为最大杠杆下初始保证金的一半
+                let maintenance = position_value / (2.0 * max_leverage as f64);
+                let liquidation = mid - (account_value - maintenance) / p.szi;
+                AssetPosition {
+                    type_string: "oneWay".to_string(),
+                    position: PositionData {
+                        coin: asset.clone(),
+                        entry_px: Some(p.entry_px.to_string()),
+                        leverage: Leverage {
+                            type_string: "cross".to_string(),
+                            value: p.leverage,
+                            raw_usd: None,
+                        },
+                        liquidation_px: (liquidation > 0.0).then(|| liquidation.to_string()),
+                        margin_used: margin_used.to_string(),
+                        position_value: position_value.to_string(),
+                        return_on_equity: (unrealized / (p.szi.abs() * p.entry_px)
+                            * p.leverage as f64)
+                            .to_string(),
+                        szi: p.szi.to_string(),
+                        unrealized_pnl: unrealized.to_string(),
+                        max_leverage,
+                        cum_funding: CumulativeFunding {
+                            all_time: p.cum_funding.to_string(),
+                            since_open: p.cum_funding.to_string(),
+                            since_change: p.cum_funding.to_string(),
+                        },
+                    },
+                }
+            })
+            .collect();
+        let total_ntl_pos: f64 = self
+            .account
+            .positions
+            .iter()
+            .map(|(asset, p)| p.szi.abs() * self.mid(asset).unwrap_or(p.entry_px))
+            .fold(0.0, |sum, v| sum + v);
+        let signed_ntl: f64 = self
+            .account
+            .positions
+            .iter()
+            .map(|(asset, p)| p.szi * self.mid(asset).unwrap_or(p.entry_px))
+            .fold(0.0, |sum, v| sum + v);
+        let margin_used = self.margin_used();
+        let summary = || MarginSummary {
+            account_value: account_value.to_string(),
+            total_margin_used: margin_used.to_string(),
+            total_ntl_pos: total_ntl_pos.to_string(),
+            total_raw_usd: (account_value - signed_ntl).to_string(),
+        };
+        UserStateResponse {
+            asset_positions,
+            cross_margin_summary: summary(),
+            margin_summary: summary(),
+            withdrawable: (account_value - margin_used).max(0.0).to_string(),
+        }
+    }
+
+    /// USDC 余额，仓位占用的保证金计为冻结
+    pub fn token_balances(&self) -> UserTokenBalanceResponse {
+        UserTokenBalanceResponse {
+            balances: vec![UserTokenBalance {
+                coin: "USDC".to_string(),
+                hold: self.margin_used().to_string(),
+                total: self.account.usdc.to_string(),
+                entry_ntl: "0.0".to_string(),
+            }],
+        }
+    }
+
+    pub fn open_orders(&self) -> Vec<OpenOrdersResponse> {
+        self.account
+            .orders
+            .iter()
+            .map(|o| OpenOrdersResponse {
+                coin: o.asset.clone(),
+                limit_px: o.limit_px.to_string(),
+                oid: o.oid,
+                side: if o.is_buy { "B" } else { "A" }.to_string(),
+                sz: o.sz.to_string(),
+                timestamp: o.timestamp,
+            })
+            .collect()
+    }
+
+    pub fn open_order_cloids(&self) -> HashMap<u64, String> {
+        self.account
+            .orders
+            .iter()
+            .filter_map(|o| Some((o.oid, o.cloid.clone()?)))
+            .collect()
+    }
+
+    pub fn recent_trades(&self, asset: &str) -> Vec<RecentTradesResponse> {
+        self.account
+            .fills
+            .iter()
+            .rev()
+            .filter(|f| f.coin == asset)
+            .take(100)
+            .map(|f| RecentTradesResponse {
+                coin: f.coin.clone(),
+                side: f.side.clone(),
+                px: f.px.clone(),
+                sz: f.sz.clone(),
+                time: f.time,
+                hash: format!("{:#x}", f.tid),
+            })
+            .collect()
+    }
+
+    /// 按模拟盘口生成的盘口快照，每档订单数记为 1
+    pub fn l2_book(&self, asset: &str) -> L2SnapshotResponse {
+        let levels = |side: &[(f64, f64)]| {
+            side.iter()
+                .map(|(px, sz)| Level {
+                    n: 1,
+                    px: px.to_string(),
+                    sz: sz.to_string(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let market = self.markets.get(asset);
+        L2SnapshotResponse {
+            coin: asset.to_string(),
+            levels: vec![
+                market.map(|m| levels(&m.bids)).unwrap_or_default(),
+                market.map(|m| levels(&m.asks)).unwrap_or_default(),
+            ],
+            time: now_ms(),
+        }
+    }
+
+    pub fn market_infos(&self) -> Vec<MarketInfo> {
+        self.markets
+            .values()
+            .map(|m| MarketInfo {
+                asset: m.asset.clone(),
+                max_leverage: m.max_leverage,
+                sz_decimals: m.sz_decimals,
+                mark_price: m.mid,
+                oracle_price: m.mid,
+                mid_price: Some(m.mid),
+                prev_day_price: m.mid,
+                day_volume: 0.0,
+                open_interest: 0.0,
+                funding: m.funding,
+                premium: None,
+            })
+            .collect()
+    }
+
+    pub fn fee_summary(&self) -> FeeSummary {
+        FeeSummary {
+            maker_rate: MAKER_FEE,
+            taker_rate: TAKER_FEE,
+            spot_maker_rate: None,
+            spot_taker_rate: None,
+            taker_volume_14d: 0.0,
+            maker_volume_14d: 0.0,
+            referral_discount: 0.0,
+            staking_discount: 0.0,
+            cum_volume: 0.0,
+            requests_used: 0,
+            requests_cap: 10000,
+        }
+    }
+
+    pub fn asset_index(&self, asset: &str) -> Option<u32> {
+        self.markets
+            .keys()
+            .position(|a| a == asset)
+            .map(|i| i as u32)
+    }
+}
+
+/// 将处理结果包装为交易所响应
+pub fn exchange_response(
+    response_type: &str,
+    statuses: Vec<ExchangeDataStatus>,
+) -> ExchangeResponseStatus {
+    ExchangeResponseStatus::Ok(ExchangeResponse {
+        response_type: response_type.to_string(),
+        data: Some(ExchangeDataStatuses { statuses }),
+    })
+}
+
+/// 内存中的模拟交易所，用于离线测试命令、界面及渲染
+///
+/// 只模拟单个账户，查询接口中的地址参数会被忽略
+#[derive(Debug, Default)]
+pub struct MockExchange {
+    sim: Mutex<SimExchange>,
+    /// 标的 -> K线，由调用方预先设置
+    candles: Mutex<HashMap<String, Vec<Candle>>>,
+    /// 下一次交易所请求的模拟网络故障
+    failure: Mutex<Option<MockFailure>>,
+}
+
+/// 模拟的网络故障，请求返回网络错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFailure {
+    /// 请求未到达交易所
+    Dropped,
+    /// 交易所已处理请求，但响应丢失
+    LostResponse,
+}
+
+impl MockExchange {
+    pub fn new(sim: SimExchange) -> Self {
+        Self {
+            sim: Mutex::new(sim),
+            candles: Mutex::new(HashMap::new()),
+            failure: Mutex::new(None),
+        }
+    }
+
+    /// 下一次下单、撤单、改单或定时撤单请求返回网络错误
+    pub fn fail_next(&self, failure: MockFailure) {
+        *self.failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(failure);
+    }
+
+    /// 执行交易所请求，按设置的故障丢弃请求或响应
+    fn send<R>(&self, f: impl FnOnce(&mut SimExchange) -> R) -> Result<R> {
+        let failure = self
+            .failure
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if failure == Some(MockFailure::Dropped) {
+            return Err(ToolsetError::Network("Simulated dropped request".into()).into());
+        }
+        let result = self.with(f);
+        if failure == Some(MockFailure::LostResponse) {
+            return Err(ToolsetError::Network("Simulated lost response".into()).into());
+        }
+        Ok(result)
+    }
+
+    /// 设置标的的K线，查询时不区分周期
+    pub fn set_candles(&self, asset: &str, candles: Vec<Candle>) {
+        self.candles
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(asset.to_string(), candles);
+    }
+
+    /// 更新中间价并撮合挂单
+    pub fn set_mid(&self, asset: &str, mid: f64) {
+        self.with(|sim| sim.set_mid(asset, mid));
+    }
+
+    /// 直接读写模拟交易所状态，之前先执行已到期的定时撤单
+    pub fn with<R>(&self, f: impl FnOnce(&mut SimExchange) -> R) -> R {
+        let mut sim = self.sim.lock().unwrap_or_else(|e| e.into_inner());
+        sim.run_scheduled_cancel(now_ms());
+        f(&mut sim)
+    }
+}
+
+impl InfoBackend for MockExchange {
+    async fn all_mids(&self) -> Result<HashMap<String, String>> {
+        Ok(self.with(|sim| {
+            sim.markets
+                .values()
+                .map(|m| (m.asset.clone(), m.mid.to_string()))
+                .collect()
+        }))
+    }
+
+    async fn user_state(&self, _address: H160) -> Result<UserStateResponse> {
+        Ok(self.with(|sim| sim.user_state()))
+    }
+
+    async fn user_token_balances(&self, _address: H160) -> Result<UserTokenBalanceResponse> {
+        Ok(self.with(|sim| sim.token_balances()))
+    }
+
+    async fn open_orders(&self, _address: H160) -> Result<Vec<OpenOrdersResponse>> {
+        Ok(self.with(|sim| sim.open_orders()))
+    }
+
+    async fn open_order_cloids(&self, _address: H160) -> Result<HashMap<u64, String>> {
+        Ok(self.with(|sim| sim.open_order_cloids()))
+    }
+
+    async fn recent_trades(&self, asset: &str) -> Result<Vec<RecentTradesResponse>> {
+        Ok(self.with(|sim| sim.recent_trades(asset)))
+    }
+
+    async fn markets(&self) -> Result<Vec<MarketInfo>> {
+        Ok(self.with(|sim| sim.market_infos()))
+    }
+
+    async fn fees(&self, _address: H160) -> Result<FeeSummary> {
+        Ok(self.with(|sim| sim.fee_summary()))
+    }
+
+    async fn user_fills_by_time(&self, _address: H160, start_time: u64) -> Result<Vec<UserFill>> {
+        Ok(self.with(|sim| {
+            sim.account
+                .fills
+                .iter()
+                .filter(|f| f.time >= start_time)
+                .cloned()
+                .collect()
+        }))
+    }
+
+    async fn spot_pairs(&self) -> Result<HashMap<String, (String, String)>> {
+        Ok(HashMap::new())
+    }
+
+    /// 不模拟现货交易
+    async fn spot_markets(&self) -> Result<Vec<SpotMarket>> {
+        Ok(vec![])
+    }
+
+    async fn candles(&self, asset: &str, _interval: &str, start_time: u64) -> Result<Vec<Candle>> {
+        Ok(self
+            .candles
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(asset)
+            .map(|candles| {
+                candles
+                    .iter()
+                    .filter(|c| c.open_time >= start_time)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn l2_book(&self, asset: &str) -> Result<L2SnapshotResponse> {
+        Ok(self.with(|sim| sim.l2_book(asset)))
+    }
+}
+
+impl ExchangeBackend for MockExchange {
+    fn asset_index(&self, asset: &str) -> Option<u32> {
+        self.with(|sim| sim.asset_index(asset))
+    }
+
+    fn sz_decimals(&self, asset: &str) -> Option<u32> {
+        self.with(|sim| sim.markets.get(asset).map(|m| m.sz_decimals))
+    }
+
+    async fn bulk_order(&self, orders: Vec<ClientOrderRequest>) -> Result<ExchangeResponseStatus> {
+        let statuses = self.send(|sim| orders.into_iter().map(|o| sim.place(o)).collect())?;
+        Ok(exchange_response("order", statuses))
+    }
+
+    async fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        let statuses = self.send(|sim| {
+            cancels
+                .into_iter()
+                .map(|c| sim.cancel(&c.asset, c.oid))
+                .collect()
+        })?;
+        Ok(exchange_response("cancel", statuses))
+    }
+
+    async fn bulk_modify(
+        &self,
+        modifies: Vec<ClientModifyRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        let statuses = self.send(|sim| {
+            modifies
+                .into_iter()
+                .map(|m| sim.modify(m.oid, m.order))
+                .collect()
+        })?;
+        Ok(exchange_response("batchModify", statuses))
+    }
+
+    async fn schedule_cancel(&self, time: Option<u64>) -> Result<ExchangeResponseStatus> {
+        self.send(|sim| sim.schedule_cancel(time, now_ms()))
+    }
+}
+
+/// 单元测试共用的模拟账户及订单
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+    use crate::order::{LimitType, OrderKind, OrderSide, OrderSpec};
+    use crate::toolset::Toolset;
+
+    /// 10000 USDC 的空账户，BTC 中间价 50000、ETH 中间价 3000
+    pub fn toolset() -> Toolset<MockExchange> {
+        let mut sim = SimExchange::new(10_000.0);
+        sim.add_market("BTC", 50_000.0, 5, 50);
+        sim.add_market("ETH", 3_000.0, 4, 50);
+        Toolset::with_backend(H160::zero(), MockExchange::new(sim))
+    }
+
+    pub fn limit(
+        asset: &str,
+        side: OrderSide,
+        tif: LimitType,
+        price: f64,
+        quantity: f64,
+    ) -> OrderSpec {
+        OrderSpec {
+            asset: asset.to_string(),
+            side,
+            kind: OrderKind::Limit { tif },
+            reduce_only: false,
+            price: Some(price),
+            quantity,
+            cloid: None,
+        }
+    }
+
+    /// 带方向的仓位数量
+    pub fn position(toolset: &Toolset<MockExchange>, asset: &str) -> f64 {
+        toolset.backend().with(|sim| sim.position_szi(asset))
+    }
+}
@@ -0,0 +1,352 @@
+use crate::backend::Backend;
+use crate::error::ToolsetError;
+use crate::order::{self, LimitType, OrderKind, OrderSide, OrderSpec, TriggerType};
+use crate::store;
+use crate::toolset::{Toolset, now_ms};
+use anyhow::Result;
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// OCO 中的一个订单
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OcoLeg {
+    /// 限价单价格或止损单触发价
+    pub price: f64,
+    /// 当前订单ID
+    pub oid: u64,
+    /// 该订单用过的所有订单ID，修改订单后交易所可能分配新的订单ID
+    #[serde(default)]
+    pub oids: Vec<u64>,
+    /// 当前挂单数量，0 表示已撤销、已触发或全部成交
+    pub open_size: f64,
+    /// 已成交数量
+    pub filled: f64,
+}
+
+impl OcoLeg {
+    fn new(price: f64) -> Self {
+        Self {
+            price,
+            oid: 0,
+            oids: vec![],
+            open_size: 0.0,
+            filled: 0.0,
+        }
+    }
+
+    fn set_oid(&mut self, oid: u64) {
+        self.oid = oid;
+        if !self.oids.contains(&oid) {
+            self.oids.push(oid);
+        }
+    }
+}
+
+/// OCO 状态
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OcoStatus {
+    /// 两个订单都在监控中
+    Active,
+    /// 已全部成交，另一个订单已撤销
+    Filled,
+    /// 两个订单都已撤销
+    Cancelled,
+}
+
+/// 一个限价单与一个止损单组成的 OCO(one-cancels-other)，其中一个成交后撤销或缩减另一个
+///
+/// 买入时限价单在当前价之下、止损单(突破买入)在当前价之上，卖出时相反
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OcoPair {
+    pub id: u64,
+    pub asset: String,
+    pub is_buy: bool,
+    pub quantity: f64,
+    pub reduce_only: bool,
+    pub limit: OcoLeg,
+    pub stop: OcoLeg,
+    /// 止损单触发后的限价，为 None 时按市场价执行
+    pub stop_limit: Option<f64>,
+    pub status: OcoStatus,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl OcoPair {
+    pub fn new(
+        asset: &str,
+        side: OrderSide,
+        quantity: f64,
+        limit: f64,
+        stop: f64,
+        stop_limit: Option<f64>,
+        reduce_only: bool,
+    ) -> Self {
+        let now = now_ms();
+        Self {
+            id: 0,
+            asset: asset.to_string(),
+            is_buy: side == OrderSide::Buy,
+            quantity,
+            reduce_only,
+            limit: OcoLeg::new(limit),
+            stop: OcoLeg::new(stop),
+            stop_limit,
+            status: OcoStatus::Active,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn side(&self) -> OrderSide {
+        if self.is_buy {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        }
+    }
+
+    /// 两个订单的已成交数量之和
+    pub fn filled(&self) -> f64 {
+        self.limit.filled + self.stop.filled
+    }
+
+    /// 限价单
+    pub fn limit_order(&self, quantity: f64) -> OrderSpec {
+        OrderSpec {
+            asset: self.asset.clone(),
+            side: self.side(),
+            kind: OrderKind::Limit {
+                tif: LimitType::Gtc,
+            },
+            reduce_only: self.reduce_only,
+            price: Some(self.limit.price),
+            quantity,
+            cloid: None,
+        }
+    }
+
+    /// 止损单：买入时价格涨到触发价、卖出时价格跌到触发价后执行
+    pub fn stop_order(&self, quantity: f64) -> OrderSpec {
+        OrderSpec {
+            asset: self.asset.clone(),
+            side: self.side(),
+            kind: OrderKind::Trigger {
+                trigger_price: self.stop.price,
+                is_market: self.stop_limit.is_none(),
+                tpsl: TriggerType::Sl,
+            },
+            reduce_only: self.reduce_only,
+            price: self.stop_limit,
+            quantity,
+            cloid: None,
+        }
+    }
+
+    /// 检查两个订单相对当前价格的位置，避免下单后立即成交或触发
+    pub fn validate(&self, mid: f64) -> Result<(), ToolsetError> {
+        let valid = if self.is_buy {
+            self.limit.price < mid && self.stop.price > mid
+        } else {
+            self.limit.price > mid && self.stop.price < mid
+        };
+        if valid {
+            return Ok(());
+        }
+        Err(ToolsetError::Validation(format!(
+            "For a {} OCO the limit price must be {} and the stop price {} the mid price {}",
+            if self.is_buy { "buy" } else { "sell" },
+            if self.is_buy { "below" } else { "above" },
+            if self.is_buy { "above" } else { "below" },
+            mid
+        )))
+    }
+}
+
+/// 一次检查的结果
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OcoEvent {
+    /// 没有新的成交
+    Watching,
+    /// 一个订单部分成交，另一个订单已缩减为剩余数量
+    Resized,
+    /// 已全部成交，另一个订单已撤销
+    Filled,
+    /// 两个订单都已不存在且没有成交
+    Cancelled,
+}
+
+/// 一次检查后的状态
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OcoUpdate {
+    pub event: OcoEvent,
+    pub pair: OcoPair,
+}
+
+fn oco_file(address: H160) -> String {
+    format!("oco-{:?}.json", address)
+}
+
+/// 账户的所有 OCO，按 ID 索引
+pub fn load(address: H160) -> Result<BTreeMap<u64, OcoPair>> {
+    store::load(&oco_file(address))
+}
+
+pub fn save(address: H160, pairs: &BTreeMap<u64, OcoPair>) -> Result<()> {
+    store::save(&oco_file(address), pairs)
+}
+
+/// 下 OCO 的两个订单，其中一个被拒绝时撤销另一个并返回错误
+pub async fn place<B: Backend>(toolset: &Toolset<B>, pair: &mut OcoPair) -> Result<()> {
+    let specs = [
+        pair.limit_order(pair.quantity),
+        pair.stop_order(pair.quantity),
+    ];
+    let results = toolset.place_orders(&specs).await?;
+    let errors: Vec<String> = results.iter().filter_map(|r| r.error.clone()).collect();
+    if !errors.is_empty() {
+        for result in &results {
+            if let (None, Some(oid)) = (&result.error, result.oid) {
+                toolset.cancel(&pair.asset, oid).await?;
+            }
+        }
+        return Err(ToolsetError::Rejected(errors.join("; ")).into());
+    }
+    for (leg, result) in [&mut pair.limit, &mut pair.stop].into_iter().zip(&results) {
+        let oid = result
+            .oid
+            .ok_or_else(|| ToolsetError::Network("Missing order ID in response".into()))?;
+        leg.set_oid(oid);
+        leg.open_size = pair.quantity;
+        leg.filled = result.filled_size.unwrap_or(0.0);
+    }
+    Ok(())
+}
+
+/// 检查一次两个订单的成交情况：一个全部成交后撤销另一个，部分成交后把另一个缩减为剩余数量
+pub async fn tick<B: Backend>(toolset: &Toolset<B>, pair: &mut OcoPair) -> Result<OcoUpdate> {
+    let (fills, orders) = tokio::try_join!(
+        toolset
+            .backend()
+            .user_fills_by_time(toolset.address(), pair.created_at),
+        toolset.open_orders()
+    )?;
+    let sz_decimals = toolset
+        .backend()
+        .sz_decimals(&pair.asset)
+        .ok_or_else(|| ToolsetError::Validation(format!("Unknown asset: {}", pair.asset)))?;
+    for leg in [&mut pair.limit, &mut pair.stop] {
+        let filled = fills
+            .iter()
+            .filter(|f| f.coin == pair.asset && leg.oids.contains(&f.oid))
+            .fold(0.0, |sum, f| sum + f.sz.parse::<f64>().unwrap_or(0.0));
+        leg.filled = order::round_size(filled, sz_decimals);
+        leg.open_size = orders
+            .iter()
+            .find(|o| o.oid == leg.oid)
+            .map(|o| o.quantity)
+            .unwrap_or(0.0);
+    }
+
+    let filled = pair.filled();
+    let remaining = order::round_size(pair.quantity - filled, sz_decimals);
+    let mut event = OcoEvent::Watching;
+    if remaining <= 0.0 || (pair.limit.open_size == 0.0 && pair.stop.open_size == 0.0) {
+        for leg in [&pair.limit, &pair.stop] {
+            if leg.open_size > 0.0 {
+                toolset.cancel(&pair.asset, leg.oid).await?;
+            }
+        }
+        pair.limit.open_size = 0.0;
+        pair.stop.open_size = 0.0;
+        (pair.status, event) = if filled > 0.0 {
+            (OcoStatus::Filled, OcoEvent::Filled)
+        } else {
+            (OcoStatus::Cancelled, OcoEvent::Cancelled)
+        };
+    } else {
+        for is_limit in [true, false] {
+            let leg = if is_limit { &pair.limit } else { &pair.stop };
+            if leg.open_size <= remaining {
+                continue;
+            }
+            let spec = if is_limit {
+                pair.limit_order(remaining)
+            } else {
+                pair.stop_order(remaining)
+            };
+            let result = toolset.modify_order(leg.oid, &spec).await?;
+            if let Some(error) = result.error {
+                return Err(ToolsetError::Rejected(error).into());
+            }
+            let leg = if is_limit {
+                &mut pair.limit
+            } else {
+                &mut pair.stop
+            };
+            if let Some(oid) = result.oid {
+                leg.set_oid(oid);
+            }
+            leg.open_size = remaining;
+            event = OcoEvent::Resized;
+        }
+    }
+    if event != OcoEvent::Watching {
+        pair.updated_at = now_ms();
+    }
+    Ok(OcoUpdate {
+        event,
+        pair: pair.clone(),
+    })
+}
+
+/// 撤销 OCO 的两个订单
+pub async fn cancel<B: Backend>(toolset: &Toolset<B>, pair: &mut OcoPair) -> Result<()> {
+    let orders = toolset.open_orders().await?;
+    for leg in [&mut pair.limit, &mut pair.stop] {
+        if orders.iter().any(|o| o.oid == leg.oid) {
+            toolset.cancel(&pair.asset, leg.oid).await?;
+        }
+        leg.open_size = 0.0;
+    }
+    pair.status = OcoStatus::Cancelled;
+    pair.updated_at = now_ms();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::test_support::{position, toolset};
+
+    #[tokio::test]
+    async fn tick_cancels_the_stop_after_the_limit_fills() {
+        let toolset = toolset();
+        let mut pair = OcoPair::new("BTC", OrderSide::Buy, 0.1, 49_000.0, 51_000.0, None, false);
+        pair.validate(50_000.0).unwrap();
+        place(&toolset, &mut pair).await.unwrap();
+        assert_eq!(toolset.open_orders().await.unwrap().len(), 2);
+
+        toolset.backend().set_mid("BTC", 49_500.0);
+        let update = tick(&toolset, &mut pair).await.unwrap();
+        assert_eq!(update.event, OcoEvent::Watching);
+        assert_eq!((pair.limit.open_size, pair.stop.open_size), (0.1, 0.1));
+
+        toolset.backend().set_mid("BTC", 48_900.0);
+        let update = tick(&toolset, &mut pair).await.unwrap();
+        assert_eq!(update.event, OcoEvent::Filled);
+        assert_eq!(pair.status, OcoStatus::Filled);
+        assert_eq!((pair.limit.filled, pair.stop.filled), (0.1, 0.0));
+        assert!(toolset.open_orders().await.unwrap().is_empty());
+
+        // 止损单已撤销，价格上涨后不再触发
+        toolset.backend().set_mid("BTC", 51_500.0);
+        assert_eq!(position(&toolset, "BTC"), 0.1);
+    }
+}
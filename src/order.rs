@@ -0,0 +1,238 @@
+use crate::error::ToolsetError;
+use clap::ValueEnum;
+use hyperliquid_rust_sdk::{ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger};
+use uuid::Uuid;
+
+/// 限价订单类型
+/// - Alo: Add Liquidity Only
+/// - Ioc: Immediate or cancel
+/// - Gtc: Good till cancel
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+#[clap(rename_all = "verbatim")]
+pub enum LimitType {
+    /// 订单有效直到取消或成交，适合长期挂单，可能为 Maker 或 Taker
+    #[default]
+    Gtc,
+    /// 立即成交或取消，适合快速执行，通常为 Taker
+    Ioc,
+    /// 仅添加流动性，确保 Maker 费率，适合做市和成本优化
+    Alo,
+}
+
+/// 永续合约价格的最大小数位数，实际可用位数为 6 - szDecimals
+const MAX_PERP_DECIMALS: i32 = 6;
+/// 现货价格的最大小数位数，实际可用位数为 8 - szDecimals
+const MAX_SPOT_DECIMALS: i32 = 8;
+/// 价格的最大有效数字位数，整数价格不受限制
+const MAX_SIGNIFICANT_FIGURES: i32 = 5;
+/// 现货交易对在交易所中的编号从 10000 开始
+pub const SPOT_ASSET_OFFSET: u32 = 10_000;
+
+/// 按标的的数量精度取整
+pub fn round_size(size: f64, sz_decimals: u32) -> f64 {
+    let factor = 10f64.powi(sz_decimals as i32);
+    (size * factor).round() / factor
+}
+
+/// 按交易所价格规则取整：最多5位有效数字，且小数位数不超过 6 - szDecimals
+pub fn round_price(price: f64, sz_decimals: u32) -> f64 {
+    round_price_with(price, sz_decimals, MAX_PERP_DECIMALS)
+}
+
+/// 按现货价格规则取整：最多5位有效数字，且小数位数不超过 8 - szDecimals
+pub fn round_spot_price(price: f64, sz_decimals: u32) -> f64 {
+    round_price_with(price, sz_decimals, MAX_SPOT_DECIMALS)
+}
+
+fn round_price_with(price: f64, sz_decimals: u32, max_decimals: i32) -> f64 {
+    if price <= 0.0 {
+        return price;
+    }
+    let magnitude = price.log10().floor() as i32;
+    let decimals = (MAX_SIGNIFICANT_FIGURES - 1 - magnitude)
+        .min(max_decimals - sz_decimals as i32)
+        .max(0);
+    let factor = 10f64.powi(decimals);
+    (price * factor).round() / factor
+}
+
+/// 订单方向
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum OrderSide {
+    /// 订单方向: buy
+    Buy,
+    /// 订单方向: sell
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum TriggerType {
+    /// 触发类型: Take Profit 止赢
+    Tp,
+    /// 触发类型: Stop Loss 止损
+    Sl,
+}
+
+/// 订单类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderKind {
+    /// 限价订单
+    Limit { tif: LimitType },
+    /// 触发器订单，`is_market` 为 true 时触发后按市场价执行
+    Trigger {
+        trigger_price: f64,
+        is_market: bool,
+        tpsl: TriggerType,
+    },
+}
+
+/// 下单参数
+#[derive(Debug, Clone)]
+pub struct OrderSpec {
+    pub asset: String,
+    pub side: OrderSide,
+    pub kind: OrderKind,
+    pub reduce_only: bool,
+    /// 限价，市价触发单可省略
+    pub price: Option<f64>,
+    pub quantity: f64,
+    pub cloid: Option<Uuid>,
+}
+
+impl OrderSpec {
+    pub fn is_buy(&self) -> bool {
+        self.side == OrderSide::Buy
+    }
+
+    /// 订单的执行价格，市价触发单以触发价格计
+    pub fn limit_price(&self) -> Result<f64, ToolsetError> {
+        match self.kind {
+            OrderKind::Trigger {
+                trigger_price,
+                is_market: true,
+                ..
+            } => Ok(trigger_price),
+            _ => self
+                .price
+                .ok_or_else(|| ToolsetError::Validation("--price is required".into())),
+        }
+    }
+
+    pub fn to_client_request(&self) -> Result<ClientOrderRequest, ToolsetError> {
+        if self.quantity <= 0.0 {
+            return Err(ToolsetError::Validation(format!(
+                "Invalid quantity: {}",
+                self.quantity
+            )));
+        }
+        let order_type = match &self.kind {
+            OrderKind::Limit { tif } => ClientOrder::Limit(ClientLimit {
+                tif: format!("{:?}", tif),
+            }),
+            OrderKind::Trigger {
+                trigger_price,
+                is_market,
+                tpsl,
+            } => ClientOrder::Trigger(ClientTrigger {
+                is_market: *is_market,
+                trigger_px: *trigger_price,
+                tpsl: format!("{:?}", tpsl).to_lowercase(),
+            }),
+        };
+        Ok(ClientOrderRequest {
+            asset: self.asset.clone(),
+            is_buy: self.is_buy(),
+            reduce_only: self.reduce_only,
+            limit_px: self.limit_price()?,
+            sz: self.quantity,
+            cloid: self.cloid,
+            order_type,
+        })
+    }
+}
+
+/// 按标的精度对订单的价格、触发价格及数量取整，`is_spot` 为现货交易对
+pub fn round_request(
+    request: &mut ClientOrderRequest,
+    sz_decimals: u32,
+    is_spot: bool,
+) -> Result<(), ToolsetError> {
+    let round_price = if is_spot {
+        round_spot_price
+    } else {
+        round_price
+    };
+    request.sz = round_size(request.sz, sz_decimals);
+    if request.sz <= 0.0 {
+        return Err(ToolsetError::Validation(format!(
+            "Quantity rounds to zero with {} size decimals",
+            sz_decimals
+        )));
+    }
+    request.limit_px = round_price(request.limit_px, sz_decimals);
+    if let ClientOrder::Trigger(trigger) = &mut request.order_type {
+        trigger.trigger_px = round_price(trigger.trigger_px, sz_decimals);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_rounds_to_sz_decimals() {
+        assert_eq!(round_size(0.123456, 3), 0.123);
+        assert_eq!(round_size(0.1236, 3), 0.124);
+        assert_eq!(round_size(2.5, 0), 3.0);
+        assert_eq!(round_size(0.0004, 3), 0.0);
+    }
+
+    #[test]
+    fn price_keeps_five_significant_figures() {
+        assert_eq!(round_price(50123.456, 5), 50123.0);
+        assert_eq!(round_price(1.234567, 0), 1.2346);
+        // 整数价格不受有效数字限制
+        assert_eq!(round_price(123456.7, 0), 123457.0);
+    }
+
+    #[test]
+    fn price_decimals_are_limited_by_sz_decimals() {
+        assert_eq!(round_price(0.00123456, 0), 0.001235);
+        assert_eq!(round_price(0.123456, 2), 0.1235);
+        assert_eq!(round_price(0.123456, 4), 0.12);
+        assert_eq!(round_spot_price(0.00123456, 0), 0.0012346);
+        assert_eq!(round_spot_price(0.123456, 4), 0.1235);
+    }
+
+    #[test]
+    fn request_rounding_rejects_zero_size() {
+        let spec = OrderSpec {
+            asset: "BTC".to_string(),
+            side: OrderSide::Sell,
+            kind: OrderKind::Trigger {
+                trigger_price: 48765.4321,
+                is_market: false,
+                tpsl: TriggerType::Sl,
+            },
+            reduce_only: true,
+            price: Some(48700.987),
+            quantity: 0.0123456,
+            cloid: None,
+        };
+        let mut request = spec.to_client_request().unwrap();
+        round_request(&mut request, 5, false).unwrap();
+        assert_eq!(request.sz, 0.01235);
+        assert_eq!(request.limit_px, 48701.0);
+        let ClientOrder::Trigger(trigger) = &request.order_type else {
+            panic!("expected a trigger order");
+        };
+        assert_eq!(trigger.trigger_px, 48765.0);
+
+        let mut request = spec.to_client_request().unwrap();
+        request.sz = 0.00001;
+        assert!(round_request(&mut request, 3, false).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+
+/// 输出格式
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum OutputFormat {
+    /// 表格，适合人工查看
+    #[default]
+    Table,
+    /// JSON
+    Json,
+    /// 每行一个 JSON 对象
+    Ndjson,
+    /// CSV，包含表头
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn is_table(&self) -> bool {
+        *self == OutputFormat::Table
+    }
+
+    /// 输出一组记录，表格格式交由 `table` 绘制
+    pub fn rows<T: Serialize>(&self, rows: &[T], table: impl FnOnce(&[T])) -> Result<()> {
+        match self {
+            OutputFormat::Table => table(rows),
+            format => format.write_rows(&mut std::io::stdout().lock(), rows)?,
+        }
+        Ok(())
+    }
+
+    /// 输出单个结果，表格格式交由 `table` 绘制
+    ///
+    /// CSV 格式下，若结果的每个字段都是数组(如余额中的现货、保证金及仓位)，
+    /// 则每个字段输出为一段独立的 CSV，段与段之间以空行分隔
+    pub fn one<T: Serialize>(&self, value: &T, table: impl FnOnce(&T)) -> Result<()> {
+        match self {
+            OutputFormat::Table => table(value),
+            format => format.write_one(&mut std::io::stdout().lock(), value)?,
+        }
+        Ok(())
+    }
+
+    /// 按非表格格式写出一组记录，表格格式按 JSON 写出
+    fn write_rows<T: Serialize>(&self, out: &mut impl Write, rows: &[T]) -> Result<()> {
+        match self {
+            OutputFormat::Table | OutputFormat::Json => {
+                writeln!(out, "{}", serde_json::to_string_pretty(rows)?)?
+            }
+            OutputFormat::Ndjson => {
+                for row in rows {
+                    writeln!(out, "{}", serde_json::to_string(row)?)?;
+                }
+            }
+            OutputFormat::Csv => write_csv(out, &[serde_json::to_value(rows)?])?,
+        }
+        Ok(())
+    }
+
+    /// 按非表格格式写出单个结果，表格格式按 JSON 写出
+    fn write_one<T: Serialize>(&self, out: &mut impl Write, value: &T) -> Result<()> {
+        match self {
+            OutputFormat::Table | OutputFormat::Json => {
+                writeln!(out, "{}", serde_json::to_string_pretty(value)?)?
+            }
+            OutputFormat::Ndjson => writeln!(out, "{}", serde_json::to_string(value)?)?,
+            OutputFormat::Csv => {
+                let value = serde_json::to_value(value)?;
+                match value {
+                    Value::Object(ref map) if map.values().all(Value::is_array) => {
+                        write_csv(out, &map.values().cloned().collect::<Vec<_>>())?
+                    }
+                    value => write_csv(out, &[Value::Array(vec![value])])?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn write_csv(out: &mut impl Write, sections: &[Value]) -> Result<()> {
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let rows = section.as_array().map(Vec::as_slice).unwrap_or_default();
+        let mut writer = csv::Writer::from_writer(&mut *out);
+        if let Some(Value::Object(first)) = rows.first() {
+            writer.write_record(first.keys())?;
+        }
+        for row in rows {
+            if let Value::Object(row) = row {
+                writer.write_record(row.values().map(csv_cell))?;
+            }
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(write: impl FnOnce(&mut Vec<u8>) -> Result<()>) -> String {
+        let mut out = vec![];
+        write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_rows_have_header_and_empty_nulls() {
+        let rows = [
+            json!({"asset": "BTC", "price": 50000.5, "oid": null}),
+            json!({"asset": "ETH, perp", "price": 3000, "oid": 7}),
+        ];
+        let csv = render(|out| OutputFormat::Csv.write_rows(out, &rows));
+        assert_eq!(csv, "asset,price,oid\nBTC,50000.5,\n\"ETH, perp\",3000,7\n");
+    }
+
+    #[test]
+    fn csv_object_of_arrays_is_split_into_sections() {
+        let value = json!({
+            "spot": [{"coin": "USDC", "total": 100}],
+            "positions": [{"asset": "BTC", "size": -0.5}],
+        });
+        let csv = render(|out| OutputFormat::Csv.write_one(out, &value));
+        assert_eq!(csv, "coin,total\nUSDC,100\n\nasset,size\nBTC,-0.5\n");
+    }
+
+    #[test]
+    fn csv_single_object_is_one_row() {
+        let value = json!({"asset": "BTC", "filled": true});
+        let csv = render(|out| OutputFormat::Csv.write_one(out, &value));
+        assert_eq!(csv, "asset,filled\nBTC,true\n");
+    }
+
+    #[test]
+    fn json_and_ndjson_rows() {
+        let rows = [json!({"asset": "BTC"}), json!({"asset": "ETH"})];
+        let json = render(|out| OutputFormat::Json.write_rows(out, &rows));
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), json!(rows));
+        let ndjson = render(|out| OutputFormat::Ndjson.write_rows(out, &rows));
+        assert_eq!(ndjson, "{\"asset\":\"BTC\"}\n{\"asset\":\"ETH\"}\n");
+    }
+}
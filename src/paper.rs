@@ -0,0 +1,366 @@
+use crate::backend::{ExchangeBackend, InfoBackend};
+use crate::error::ToolsetError;
+use crate::hyperliquid;
+use crate::mock::{self, SimExchange, SimMarket};
+use crate::store;
+use crate::toolset::now_ms;
+use crate::types::{Candle, FeeSummary, MarketInfo, SpotMarket, UserFill};
+use anyhow::Result;
+use ethers::types::H160;
+use hyperliquid_rust_sdk::{
+    ClientCancelRequest, ClientModifyRequest, ClientOrderRequest, ExchangeResponseStatus,
+    InfoClient, L2SnapshotResponse, OpenOrdersResponse, RecentTradesResponse, UserStateResponse,
+    UserTokenBalanceResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 模拟账户的本地文件名
+const PAPER_STATE: &str = "paper.json";
+/// 新建模拟账户的默认 USDC 余额
+pub const DEFAULT_PAPER_BALANCE: f64 = 10_000.0;
+/// 资金费结算周期
+const FUNDING_INTERVAL_MS: u64 = 3_600_000;
+/// 标的及其买卖盘口
+type Book = (String, Vec<(f64, f64)>, Vec<(f64, f64)>);
+
+/// 同一进程内两次拉取行情的最小间隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 持久化的模拟账户
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PaperState {
+    exchange: SimExchange,
+    /// 上次结算资金费的时间
+    funding_time: u64,
+}
+
+impl Default for PaperState {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAPER_BALANCE)
+    }
+}
+
+impl PaperState {
+    fn new(balance: f64) -> Self {
+        Self {
+            exchange: SimExchange::new(balance),
+            funding_time: now_ms(),
+        }
+    }
+}
+
+/// 行情来源
+enum Feed {
+    /// 实时拉取 Hyperliquid 行情及盘口
+    Live(InfoClient),
+    /// 使用 `hlt markets --output json` 录制的行情
+    Recorded,
+}
+
+/// 模拟交易后端，以实时或录制的行情撮合订单，账户保存在本地，不会动用真实资金
+///
+/// 每次读写都重新读取本地文件，同时运行的多个模拟交易进程(如网格与手动下单)看到同一个账户
+pub struct PaperBackend {
+    feed: Feed,
+    markets: Mutex<Vec<MarketInfo>>,
+    refreshed: Mutex<Option<Instant>>,
+}
+
+impl PaperBackend {
+    /// 打开本地模拟账户，`recorded` 为录制的行情文件，不指定时使用实时行情
+    pub async fn open(recorded: Option<&Path>) -> Result<Self> {
+        let feed = match recorded {
+            Some(_) => Feed::Recorded,
+            None => Feed::Live(InfoClient::new(None, None).await?),
+        };
+        let backend = Self {
+            feed,
+            markets: Mutex::new(vec![]),
+            refreshed: Mutex::new(None),
+        };
+
+        match recorded {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|e| {
+                    ToolsetError::Config(format!("Failed to read {}: {}", path.display(), e))
+                })?;
+                let markets: Vec<MarketInfo> = serde_json::from_str(&content).map_err(|e| {
+                    ToolsetError::Validation(format!(
+                        "Invalid market data in {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                backend.apply_markets(markets)?;
+            }
+            None => backend.refresh().await?,
+        }
+        Ok(backend)
+    }
+
+    /// 以指定 USDC 余额重置模拟账户，清空仓位、挂单及成交记录
+    pub fn reset(balance: f64) -> Result<()> {
+        store::update(PAPER_STATE, |state: &mut PaperState| {
+            *state = PaperState::new(balance)
+        })
+    }
+
+    /// 使用实时行情时重新拉取所有市场
+    async fn refresh(&self) -> Result<()> {
+        let Feed::Live(info_client) = &self.feed else {
+            return Ok(());
+        };
+        {
+            let mut refreshed = self.refreshed.lock().unwrap_or_else(|e| e.into_inner());
+            if refreshed.is_some_and(|t| t.elapsed() < REFRESH_INTERVAL) {
+                return Ok(());
+            }
+            *refreshed = Some(Instant::now());
+        }
+        let markets = hyperliquid::markets(info_client).await?;
+        self.apply_markets(markets)
+    }
+
+    /// 更新行情，结算资金费并撮合满足条件的挂单
+    fn apply_markets(&self, markets: Vec<MarketInfo>) -> Result<()> {
+        self.update(|state| {
+            let exchange = &mut state.exchange;
+            for info in &markets {
+                let market = exchange
+                    .markets
+                    .entry(info.asset.clone())
+                    .or_insert_with(|| SimMarket {
+                        asset: info.asset.clone(),
+                        sz_decimals: info.sz_decimals,
+                        max_leverage: info.max_leverage,
+                        mid: info.mid_price.unwrap_or(info.mark_price),
+                        funding: info.funding,
+                        bids: vec![],
+                        asks: vec![],
+                    });
+                market.sz_decimals = info.sz_decimals;
+                market.max_leverage = info.max_leverage;
+                market.funding = info.funding;
+            }
+
+            // 资金费按整点结算，按当前费率补齐上次结算以来经过的每个整点
+            let now = now_ms();
+            // 时钟回拨或账户文件来自其它主机时不结算，也不回退结算时间，避免重复结算
+            let hours = (now / FUNDING_INTERVAL_MS)
+                .saturating_sub(state.funding_time / FUNDING_INTERVAL_MS);
+            if hours > 0 {
+                exchange.accrue_funding(hours as f64);
+            }
+            state.funding_time = state.funding_time.max(now);
+
+            for info in &markets {
+                exchange.set_mid(&info.asset, info.mid_price.unwrap_or(info.mark_price));
+            }
+        })?;
+        *self.markets.lock().unwrap_or_else(|e| e.into_inner()) = markets;
+        Ok(())
+    }
+
+    /// 拉取下单标的的盘口，用于逐档撮合
+    async fn load_books(&self, assets: &BTreeSet<String>) -> Result<Vec<Book>> {
+        let Feed::Live(info_client) = &self.feed else {
+            return Ok(vec![]);
+        };
+        let mut books = vec![];
+        for asset in assets {
+            let book = info_client.l2_snapshot(asset.clone()).await?;
+            let side = |i: usize| -> Vec<(f64, f64)> {
+                book.levels
+                    .get(i)
+                    .map(|levels| {
+                        levels
+                            .iter()
+                            .filter_map(|l| Some((l.px.parse().ok()?, l.sz.parse().ok()?)))
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+            books.push((asset.clone(), side(0), side(1)));
+        }
+        Ok(books)
+    }
+
+    /// 读取模拟账户的最新状态，之前先执行已到期的定时撤单(不写回)
+    fn with<R>(&self, f: impl FnOnce(&mut SimExchange) -> R) -> Result<R> {
+        let mut state: PaperState = store::load(PAPER_STATE)?;
+        state.exchange.run_scheduled_cancel(now_ms());
+        Ok(f(&mut state.exchange))
+    }
+
+    /// 在文件锁内重新读取、修改模拟账户并写回本地文件，之前先执行已到期的定时撤单
+    fn update<R>(&self, f: impl FnOnce(&mut PaperState) -> R) -> Result<R> {
+        store::update(PAPER_STATE, |state: &mut PaperState| {
+            state.exchange.run_scheduled_cancel(now_ms());
+            f(state)
+        })
+    }
+}
+
+impl InfoBackend for PaperBackend {
+    async fn all_mids(&self) -> Result<HashMap<String, String>> {
+        self.refresh().await?;
+        self.with(|exchange| {
+            exchange
+                .markets
+                .values()
+                .map(|m| (m.asset.clone(), m.mid.to_string()))
+                .collect()
+        })
+    }
+
+    async fn user_state(&self, _address: H160) -> Result<UserStateResponse> {
+        self.refresh().await?;
+        self.with(|exchange| exchange.user_state())
+    }
+
+    async fn user_token_balances(&self, _address: H160) -> Result<UserTokenBalanceResponse> {
+        self.with(|exchange| exchange.token_balances())
+    }
+
+    async fn open_orders(&self, _address: H160) -> Result<Vec<OpenOrdersResponse>> {
+        self.refresh().await?;
+        self.with(|exchange| exchange.open_orders())
+    }
+
+    async fn open_order_cloids(&self, _address: H160) -> Result<HashMap<u64, String>> {
+        self.refresh().await?;
+        self.with(|exchange| exchange.open_order_cloids())
+    }
+
+    async fn recent_trades(&self, asset: &str) -> Result<Vec<RecentTradesResponse>> {
+        match &self.feed {
+            Feed::Live(info_client) => Ok(info_client.recent_trades(asset.to_string()).await?),
+            Feed::Recorded => self.with(|exchange| exchange.recent_trades(asset)),
+        }
+    }
+
+    async fn markets(&self) -> Result<Vec<MarketInfo>> {
+        self.refresh().await?;
+        Ok(self
+            .markets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone())
+    }
+
+    async fn fees(&self, _address: H160) -> Result<FeeSummary> {
+        self.with(|exchange| exchange.fee_summary())
+    }
+
+    async fn user_fills_by_time(&self, _address: H160, start_time: u64) -> Result<Vec<UserFill>> {
+        self.with(|exchange| {
+            exchange
+                .account
+                .fills
+                .iter()
+                .filter(|f| f.time >= start_time)
+                .cloned()
+                .collect()
+        })
+    }
+
+    async fn spot_pairs(&self) -> Result<HashMap<String, (String, String)>> {
+        Ok(HashMap::new())
+    }
+
+    /// 不模拟现货交易
+    async fn spot_markets(&self) -> Result<Vec<SpotMarket>> {
+        Ok(vec![])
+    }
+
+    /// 录制的行情不包含K线
+    async fn candles(&self, asset: &str, interval: &str, start_time: u64) -> Result<Vec<Candle>> {
+        match &self.feed {
+            Feed::Live(info_client) => {
+                hyperliquid::candles(info_client, asset, interval, start_time).await
+            }
+            Feed::Recorded => Ok(vec![]),
+        }
+    }
+
+    async fn l2_book(&self, asset: &str) -> Result<L2SnapshotResponse> {
+        match &self.feed {
+            Feed::Live(info_client) => Ok(info_client.l2_snapshot(asset.to_string()).await?),
+            Feed::Recorded => self.with(|exchange| exchange.l2_book(asset)),
+        }
+    }
+}
+
+impl ExchangeBackend for PaperBackend {
+    fn asset_index(&self, asset: &str) -> Option<u32> {
+        self.with(|exchange| exchange.asset_index(asset))
+            .ok()
+            .flatten()
+    }
+
+    fn sz_decimals(&self, asset: &str) -> Option<u32> {
+        self.with(|exchange| exchange.markets.get(asset).map(|m| m.sz_decimals))
+            .ok()
+            .flatten()
+    }
+
+    async fn bulk_order(&self, orders: Vec<ClientOrderRequest>) -> Result<ExchangeResponseStatus> {
+        self.refresh().await?;
+        let assets = orders
+            .iter()
+            .map(|o| o.asset.clone())
+            .collect::<BTreeSet<_>>();
+        let books = self.load_books(&assets).await?;
+        let statuses = self.update(|state| {
+            for (asset, bids, asks) in books {
+                state.exchange.set_book(&asset, bids, asks);
+            }
+            let statuses = orders
+                .into_iter()
+                .map(|o| state.exchange.place(o))
+                .collect();
+            // 盘口只用于本次撮合，之后的挂单按中间价撮合
+            for asset in &assets {
+                state.exchange.set_book(asset, vec![], vec![]);
+            }
+            statuses
+        })?;
+        Ok(mock::exchange_response("order", statuses))
+    }
+
+    async fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        let statuses = self.update(|state| {
+            cancels
+                .into_iter()
+                .map(|c| state.exchange.cancel(&c.asset, c.oid))
+                .collect()
+        })?;
+        Ok(mock::exchange_response("cancel", statuses))
+    }
+
+    async fn bulk_modify(
+        &self,
+        modifies: Vec<ClientModifyRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.refresh().await?;
+        let statuses = self.update(|state| {
+            modifies
+                .into_iter()
+                .map(|m| state.exchange.modify(m.oid, m.order))
+                .collect()
+        })?;
+        Ok(mock::exchange_response("batchModify", statuses))
+    }
+
+    async fn schedule_cancel(&self, time: Option<u64>) -> Result<ExchangeResponseStatus> {
+        self.update(|state| state.exchange.schedule_cancel(time, now_ms()))
+    }
+}
@@ -0,0 +1,373 @@
+use crate::store;
+use anyhow::Result;
+use hyperliquid_rust_sdk::ClientOrderRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// 下单前的风控限制，未设置的项不做限制
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RiskLimits {
+    /// 单笔订单最大名义价值(美元)
+    pub max_order_notional: Option<f64>,
+    /// 单个标的最大仓位名义价值(美元)
+    pub max_position_notional: Option<f64>,
+    /// 成交后的最大账户杠杆
+    pub max_leverage: Option<f64>,
+    /// 订单价格偏离中间价的最大比例，0.05 表示 5%
+    pub price_band: Option<f64>,
+    /// 允许交易的标的
+    pub allowed_assets: Option<Vec<String>>,
+    /// 每日(UTC)最大下单数，不含只减仓订单
+    pub max_daily_orders: Option<u32>,
+}
+
+impl RiskLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_order_notional.is_none()
+            && self.max_position_notional.is_none()
+            && self.max_leverage.is_none()
+            && self.price_band.is_none()
+            && self.allowed_assets.is_none()
+            && self.max_daily_orders.is_none()
+    }
+}
+
+/// 下单时生效的风控配置
+#[derive(Debug)]
+pub struct RiskGuard {
+    /// 每日下单计数的名称，通常为 profile 名称
+    pub counter: String,
+    pub limits: RiskLimits,
+    /// 用户确认跳过检查的订单，每个只跳过一次
+    pub overrides: Mutex<Vec<String>>,
+}
+
+impl RiskGuard {
+    /// 所有订单都是用户确认跳过检查的订单时，移除这些确认并返回 true；否则不做修改
+    pub fn take_overrides(&self, orders: &[ClientOrderRequest]) -> bool {
+        let mut overrides = self.overrides.lock().unwrap_or_else(|e| e.into_inner());
+        let mut remaining = overrides.clone();
+        for order in orders {
+            let key = order_key(order);
+            match remaining.iter().position(|k| *k == key) {
+                Some(index) => {
+                    remaining.remove(index);
+                }
+                None => return false,
+            }
+        }
+        *overrides = remaining;
+        true
+    }
+}
+
+/// 识别订单的键，取整后的价格、数量、类型及 cloid 都相同才视为同一订单
+pub fn order_key(order: &ClientOrderRequest) -> String {
+    format!("{:?}", order)
+}
+
+/// 风控检查所需的账户及行情状态
+#[derive(Debug, Default)]
+pub struct RiskContext {
+    pub mids: HashMap<String, f64>,
+    /// 标的 -> 带方向的仓位数量
+    pub positions: HashMap<String, f64>,
+    pub account_value: f64,
+    /// 所有仓位的名义价值之和
+    pub total_notional: f64,
+    pub orders_today: u32,
+}
+
+/// 检查一组订单，返回所有违反的限制
+///
+/// 只减仓订单(一键平仓、止损等)不受仓位及杠杆限制，也不计入每日下单数，避免在需要平仓时被风控拦住；
+/// 价格偏离及单笔名义价值仍然检查，防止价格或数量输错
+pub fn check(limits: &RiskLimits, orders: &[ClientOrderRequest], ctx: &RiskContext) -> Vec<String> {
+    let mut violations = vec![];
+    let mut positions = ctx.positions.clone();
+    let mut total_notional = ctx.total_notional;
+
+    if let Some(max) = limits.max_daily_orders {
+        let count = ctx.orders_today + counted(orders) as u32;
+        if count > max {
+            violations.push(format!(
+                "daily order count {} would exceed the limit of {}",
+                count, max
+            ));
+        }
+    }
+
+    for order in orders {
+        let px = order.limit_px;
+        let mid = ctx.mids.get(&order.asset).copied();
+
+        if let (Some(band), Some(mid)) = (limits.price_band, mid) {
+            let deviation = (px - mid).abs() / mid;
+            if deviation > band {
+                violations.push(format!(
+                    "{} price {} is {:.2}% from mid {}, band is {:.2}%",
+                    order.asset,
+                    px,
+                    deviation * 100.0,
+                    mid,
+                    band * 100.0
+                ));
+            }
+        }
+
+        if let Some(allowed) = &limits.allowed_assets
+            && !allowed.iter().any(|a| a.eq_ignore_ascii_case(&order.asset))
+        {
+            violations.push(format!("{} is not in the allowed assets", order.asset));
+        }
+
+        let notional = px * order.sz;
+        if let Some(max) = limits.max_order_notional
+            && notional > max
+        {
+            violations.push(format!(
+                "{} order notional {:.2} exceeds the limit of {:.2}",
+                order.asset, notional, max
+            ));
+        }
+        if order.reduce_only {
+            continue;
+        }
+
+        let position = positions.entry(order.asset.clone()).or_default();
+        let before = *position;
+        *position += if order.is_buy { order.sz } else { -order.sz };
+        if position.abs() <= before.abs() {
+            continue;
+        }
+        let mark = mid.unwrap_or(px);
+        total_notional += (position.abs() - before.abs()) * mark;
+
+        if let Some(max) = limits.max_position_notional {
+            let position_notional = position.abs() * mark;
+            if position_notional > max {
+                violations.push(format!(
+                    "{} position notional {:.2} would exceed the limit of {:.2}",
+                    order.asset, position_notional, max
+                ));
+            }
+        }
+        if let Some(max) = limits.max_leverage {
+            let leverage = if ctx.account_value > 0.0 {
+                total_notional / ctx.account_value
+            } else {
+                f64::INFINITY
+            };
+            if leverage > max {
+                violations.push(format!(
+                    "account leverage {:.2}x would exceed the limit of {:.2}x",
+                    leverage, max
+                ));
+            }
+        }
+    }
+    violations
+}
+
+/// 计入每日下单数的订单数，只减仓订单不计入
+pub fn counted<'a>(orders: impl IntoIterator<Item = &'a ClientOrderRequest>) -> usize {
+    orders.into_iter().filter(|o| !o.reduce_only).count()
+}
+
+/// 每日下单计数
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct DailyOrders {
+    /// UTC 日期
+    date: String,
+    count: u32,
+}
+
+fn counter_file(counter: &str) -> String {
+    format!("orders-{}.json", counter)
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// 当日(UTC)已发送的订单数
+pub fn orders_today(counter: &str) -> Result<u32> {
+    let daily: DailyOrders = store::load(&counter_file(counter))?;
+    Ok(if daily.date == today() {
+        daily.count
+    } else {
+        0
+    })
+}
+
+/// 记录已发送的订单数，在文件锁内更新，多个守护进程同时下单时不会丢失计数
+pub fn record_orders(counter: &str, count: usize) -> Result<()> {
+    let today = today();
+    store::update(&counter_file(counter), |daily: &mut DailyOrders| {
+        if daily.date != today {
+            *daily = DailyOrders {
+                date: today,
+                count: 0,
+            };
+        }
+        daily.count += count as u32;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyperliquid_rust_sdk::{ClientLimit, ClientOrder};
+
+    fn order(asset: &str, is_buy: bool, px: f64, sz: f64, reduce_only: bool) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: asset.to_string(),
+            is_buy,
+            reduce_only,
+            limit_px: px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: "Gtc".to_string(),
+            }),
+        }
+    }
+
+    fn context() -> RiskContext {
+        RiskContext {
+            mids: HashMap::from([("BTC".to_string(), 50000.0), ("ETH".to_string(), 3000.0)]),
+            positions: HashMap::from([("BTC".to_string(), 0.1)]),
+            account_value: 10000.0,
+            total_notional: 5000.0,
+            orders_today: 0,
+        }
+    }
+
+    #[test]
+    fn empty_limits_allow_everything() {
+        let orders = [order("BTC", true, 1.0, 1000.0, false)];
+        assert!(check(&RiskLimits::default(), &orders, &context()).is_empty());
+    }
+
+    #[test]
+    fn each_limit_reports_a_violation() {
+        let limits = RiskLimits {
+            max_order_notional: Some(10000.0),
+            max_position_notional: Some(15000.0),
+            max_leverage: Some(2.0),
+            price_band: Some(0.05),
+            allowed_assets: Some(vec!["btc".to_string()]),
+            max_daily_orders: Some(1),
+        };
+        let orders = [
+            order("BTC", true, 50000.0, 0.3, false),
+            order("ETH", true, 2000.0, 1.0, false),
+        ];
+        let violations = check(&limits, &orders, &context());
+        let expected = [
+            "daily order count 2 would exceed the limit of 1",
+            "BTC order notional 15000.00 exceeds the limit of 10000.00",
+            "BTC position notional 20000.00 would exceed the limit of 15000.00",
+            "account leverage 2.30x would exceed the limit of 2.00x",
+            "ETH price 2000 is 33.33% from mid 3000, band is 5.00%",
+            "ETH is not in the allowed assets",
+        ];
+        for expected in expected {
+            assert!(
+                violations.iter().any(|v| v == expected),
+                "missing {:?} in {:?}",
+                expected,
+                violations
+            );
+        }
+    }
+
+    #[test]
+    fn reducing_orders_skip_position_and_leverage_limits() {
+        let limits = RiskLimits {
+            max_position_notional: Some(1000.0),
+            max_leverage: Some(0.1),
+            ..Default::default()
+        };
+        // 卖出 0.05 BTC 减少已有的多头仓位
+        let orders = [order("BTC", false, 50000.0, 0.05, false)];
+        assert!(check(&limits, &orders, &context()).is_empty());
+    }
+
+    #[test]
+    fn reduce_only_orders_skip_position_leverage_and_daily_limits() {
+        let limits = RiskLimits {
+            max_position_notional: Some(1000.0),
+            max_leverage: Some(0.1),
+            price_band: Some(0.01),
+            max_daily_orders: Some(3),
+            ..Default::default()
+        };
+        let ctx = RiskContext {
+            orders_today: 3,
+            positions: HashMap::from([("BTC".to_string(), -0.5)]),
+            ..context()
+        };
+        // 买入数量超过空头仓位，仍按只减仓订单处理
+        let orders = [order("BTC", true, 50100.0, 2.0, true)];
+        assert!(check(&limits, &orders, &ctx).is_empty());
+        assert_eq!(counted(&orders), 0);
+
+        let orders = [order("BTC", true, 50100.0, 2.0, false)];
+        assert_eq!(check(&limits, &orders, &ctx).len(), 3);
+        assert_eq!(counted(&orders), 1);
+    }
+
+    #[test]
+    fn far_off_mid_reduce_only_order_is_rejected() {
+        let limits = RiskLimits {
+            max_order_notional: Some(1000.0),
+            price_band: Some(0.05),
+            ..Default::default()
+        };
+        // -p 5000 误写成了 50000 的十分之一
+        let orders = [order("BTC", false, 5000.0, 0.1, true)];
+        assert_eq!(
+            check(&limits, &orders, &context()),
+            ["BTC price 5000 is 90.00% from mid 50000, band is 5.00%"]
+        );
+
+        // -q 1 误写成了 10
+        let orders = [order("BTC", false, 50000.0, 10.0, true)];
+        assert_eq!(
+            check(&limits, &orders, &context()),
+            ["BTC order notional 500000.00 exceeds the limit of 1000.00"]
+        );
+    }
+
+    #[test]
+    fn concurrent_records_are_not_lost() {
+        let counter = "concurrent-records";
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..5 {
+                        record_orders(counter, 1).unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(orders_today(counter).unwrap(), 40);
+    }
+
+    #[test]
+    fn overrides_apply_once_and_only_to_confirmed_orders() {
+        let confirmed = || order("BTC", true, 50000.0, 0.1, false);
+        let guard = RiskGuard {
+            counter: "test".to_string(),
+            limits: RiskLimits::default(),
+            overrides: Mutex::new(vec![order_key(&confirmed())]),
+        };
+        let other = order("BTC", true, 50000.0, 0.2, false);
+        assert!(!guard.take_overrides(&[confirmed(), other]));
+        assert!(guard.take_overrides(&[confirmed()]));
+        assert!(!guard.take_overrides(&[confirmed()]));
+    }
+}
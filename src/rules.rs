@@ -0,0 +1,1040 @@
+use crate::action;
+use crate::backend::Backend;
+use crate::batch::OrderRow;
+use crate::error::ToolsetError;
+use crate::order::{LimitType, OrderKind, OrderSide, OrderSpec};
+use crate::store;
+use crate::toolset::{Toolset, now_ms};
+use crate::types::{OrderResult, UserFill};
+use anyhow::Result;
+use clap::ValueEnum;
+use ethers::types::H160;
+use ethers::utils::keccak256;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use uuid::Uuid;
+
+/// 市价单及平仓订单的默认滑点
+const DEFAULT_SLIPPAGE: f64 = 0.05;
+/// 计算 EMA 使用的K线数量为周期的倍数，越多越接近交易所图表上的数值
+const EMA_HISTORY: usize = 3;
+/// 查找上次执行的成交时，允许本机与交易所时钟的偏差
+const CLOCK_SKEW_MS: u64 = 60_000;
+/// EMA 的最大周期，交易所单次最多返回 5000 根K线
+const MAX_EMA_PERIOD: usize = 1000;
+/// 交易所支持的K线周期及其毫秒数
+const CANDLE_INTERVALS: [(&str, u64); 14] = [
+    ("1m", 60_000),
+    ("3m", 180_000),
+    ("5m", 300_000),
+    ("15m", 900_000),
+    ("30m", 1_800_000),
+    ("1h", 3_600_000),
+    ("2h", 7_200_000),
+    ("4h", 14_400_000),
+    ("8h", 28_800_000),
+    ("12h", 43_200_000),
+    ("1d", 86_400_000),
+    ("3d", 259_200_000),
+    ("1w", 604_800_000),
+    ("1M", 2_592_000_000),
+];
+
+fn default_slippage() -> f64 {
+    DEFAULT_SLIPPAGE
+}
+
+/// 阈值可以是数字，也可以是百分比，如 `0.05%`
+fn threshold<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Number(f64),
+        Text(String),
+    }
+    let text = match Option::<Value>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Value::Number(number)) => return Ok(Some(number)),
+        Some(Value::Text(text)) => text,
+    };
+    let value = text.trim();
+    match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+        None => value.parse::<f64>(),
+    }
+    .map(Some)
+    .map_err(|_| D::Error::custom(format!("invalid threshold: {}", text)))
+}
+
+/// 规则文件，YAML 格式
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RuleFile {
+    pub rules: Vec<Rule>,
+}
+
+/// 一条规则：条件成立时执行动作
+///
+/// 条件由不成立变为成立时执行一次；未设置 `repeat` 时只执行一次，之后规则结束
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// 规则名称，同一账户的规则名称不能重复，用于保存规则状态
+    pub name: String,
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub when: Condition,
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub then: RuleAction,
+    /// 条件再次由不成立变为成立时重复执行
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+impl Rule {
+    /// 第 `fired + 1` 次执行的订单使用的 cloid，由规则名称及已执行次数决定，重试时不变；
+    /// 下单动作设置了 cloid 时使用该 cloid
+    fn cloid(&self, fired: u32) -> Uuid {
+        if let RuleAction::Order(row) = &self.then
+            && let Some(cloid) = row.cloid.as_deref().and_then(|c| Uuid::parse_str(c).ok())
+        {
+            return cloid;
+        }
+        let hash = keccak256(format!("{}/{}", self.name, fired));
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&hash[..16]);
+        Uuid::from_bytes(bytes)
+    }
+}
+
+/// EMA 穿越方向
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossDirection {
+    /// 价格由下向上穿越
+    Above,
+    /// 价格由上向下穿越
+    Below,
+}
+
+/// 规则条件，`above`/`below` 二选一
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Condition {
+    /// 中间价
+    Price {
+        asset: String,
+        #[serde(default, deserialize_with = "threshold")]
+        above: Option<f64>,
+        #[serde(default, deserialize_with = "threshold")]
+        below: Option<f64>,
+    },
+    /// 两个标的中间价之比 base/quote，如 ETH/BTC
+    Ratio {
+        base: String,
+        quote: String,
+        #[serde(default, deserialize_with = "threshold")]
+        above: Option<f64>,
+        #[serde(default, deserialize_with = "threshold")]
+        below: Option<f64>,
+    },
+    /// 每小时资金费率，如 0.05% 或 0.0005
+    Funding {
+        asset: String,
+        #[serde(default, deserialize_with = "threshold")]
+        above: Option<f64>,
+        #[serde(default, deserialize_with = "threshold")]
+        below: Option<f64>,
+    },
+    /// 中间价穿越已收盘K线收盘价的 EMA
+    EmaCross {
+        asset: String,
+        /// K线周期，如 15m、1h、1d
+        interval: String,
+        period: usize,
+        cross: CrossDirection,
+    },
+}
+
+impl Condition {
+    fn bounds(&self) -> Option<(Option<f64>, Option<f64>)> {
+        match self {
+            Condition::Price { above, below, .. }
+            | Condition::Ratio { above, below, .. }
+            | Condition::Funding { above, below, .. } => Some((*above, *below)),
+            Condition::EmaCross { .. } => None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(bounds) = self.bounds() {
+            return match bounds {
+                (Some(_), None) | (None, Some(_)) => Ok(()),
+                _ => Err("exactly one of above and below is required".into()),
+            };
+        }
+        if let Condition::EmaCross {
+            interval, period, ..
+        } = self
+        {
+            interval_ms(interval)?;
+            if !(1..=MAX_EMA_PERIOD).contains(period) {
+                return Err(format!(
+                    "EMA period must be between 1 and {}: {}",
+                    MAX_EMA_PERIOD, period
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn assets_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Condition::Price { asset, .. }
+            | Condition::Funding { asset, .. }
+            | Condition::EmaCross { asset, .. } => vec![asset],
+            Condition::Ratio { base, quote, .. } => vec![base, quote],
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |above: &Option<f64>, below: &Option<f64>, percent: bool| {
+            let (sign, value) = match (above, below) {
+                (Some(above), _) => (">", *above),
+                (_, Some(below)) => ("<", *below),
+                _ => ("?", 0.0),
+            };
+            if percent {
+                format!("{} {}%", sign, value * 100.0)
+            } else {
+                format!("{} {}", sign, value)
+            }
+        };
+        match self {
+            Condition::Price {
+                asset,
+                above,
+                below,
+            } => write!(f, "{} price {}", asset, bound(above, below, false)),
+            Condition::Ratio {
+                base,
+                quote,
+                above,
+                below,
+            } => write!(f, "{}/{} {}", base, quote, bound(above, below, false)),
+            Condition::Funding {
+                asset,
+                above,
+                below,
+            } => write!(f, "{} funding {}", asset, bound(above, below, true)),
+            Condition::EmaCross {
+                asset,
+                interval,
+                period,
+                cross,
+            } => write!(
+                f,
+                "{} crosses {} {} EMA({})",
+                asset,
+                match cross {
+                    CrossDirection::Above => "above",
+                    CrossDirection::Below => "below",
+                },
+                interval,
+                period
+            ),
+        }
+    }
+}
+
+/// 平仓时只平指定方向的仓位
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionSide {
+    Long,
+    Short,
+}
+
+/// 条件成立时执行的动作
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum RuleAction {
+    /// 下单，字段与批量下单文件中的一行相同
+    Order(OrderRow),
+    /// 以中间价加减滑点为限价的 Ioc 订单
+    Market {
+        asset: String,
+        /// buy / sell
+        side: String,
+        quantity: f64,
+        #[serde(default = "default_slippage")]
+        slippage: f64,
+        #[serde(default)]
+        reduce_only: bool,
+    },
+    /// 以只减仓 Ioc 订单平仓，设置 `side` 时只平该方向的仓位
+    Close {
+        asset: String,
+        #[serde(default)]
+        side: Option<PositionSide>,
+        #[serde(default = "default_slippage")]
+        slippage: f64,
+    },
+}
+
+impl RuleAction {
+    fn asset_mut(&mut self) -> &mut String {
+        match self {
+            RuleAction::Order(row) => &mut row.asset,
+            RuleAction::Market { asset, .. } | RuleAction::Close { asset, .. } => asset,
+        }
+    }
+
+    fn slippage(&self) -> Option<f64> {
+        match self {
+            RuleAction::Order(_) => None,
+            RuleAction::Market { slippage, .. } | RuleAction::Close { slippage, .. } => {
+                Some(*slippage)
+            }
+        }
+    }
+
+    /// 按当前中间价生成的订单，最多一个；平仓时没有对应仓位返回空列表
+    async fn specs<B: Backend>(
+        &self,
+        toolset: &Toolset<B>,
+        mids: &HashMap<String, f64>,
+    ) -> Result<Vec<OrderSpec>> {
+        match self {
+            RuleAction::Order(row) => Ok(vec![
+                row.clone().into_spec().map_err(ToolsetError::Validation)?,
+            ]),
+            RuleAction::Market {
+                asset,
+                side,
+                quantity,
+                slippage,
+                reduce_only,
+            } => {
+                let side = OrderSide::from_str(side, true)
+                    .map_err(|_| ToolsetError::Validation(format!("Invalid side: {}", side)))?;
+                let mid = mids
+                    .get(asset)
+                    .ok_or_else(|| ToolsetError::Network(format!("No price for {}", asset)))?;
+                Ok(vec![OrderSpec {
+                    asset: asset.clone(),
+                    side,
+                    kind: OrderKind::Limit {
+                        tif: LimitType::Ioc,
+                    },
+                    reduce_only: *reduce_only,
+                    price: Some(if side == OrderSide::Buy {
+                        mid * (1.0 + slippage)
+                    } else {
+                        mid * (1.0 - slippage)
+                    }),
+                    quantity: *quantity,
+                    cloid: None,
+                }])
+            }
+            RuleAction::Close {
+                asset,
+                side,
+                slippage,
+            } => {
+                // 平多头为卖出，平空头为买入
+                Ok(toolset
+                    .flatten_orders(std::slice::from_ref(asset), *slippage)
+                    .await?
+                    .into_iter()
+                    .filter(|spec| match side {
+                        Some(PositionSide::Long) => !spec.is_buy(),
+                        Some(PositionSide::Short) => spec.is_buy(),
+                        None => true,
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+impl fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleAction::Order(row) => {
+                write!(f, "{} {} {}", row.side, row.quantity, row.asset)?;
+                if let Some(trigger_price) = row.trigger_price {
+                    write!(f, " trigger {}", trigger_price)?;
+                }
+                if let Some(price) = row.price {
+                    write!(f, " @ {}", price)?;
+                }
+                Ok(())
+            }
+            RuleAction::Market {
+                asset,
+                side,
+                quantity,
+                ..
+            } => write!(f, "market {} {} {}", side, quantity, asset),
+            RuleAction::Close { asset, side, .. } => match side {
+                Some(PositionSide::Long) => write!(f, "close {} long", asset),
+                Some(PositionSide::Short) => write!(f, "close {} short", asset),
+                None => write!(f, "close {}", asset),
+            },
+        }
+    }
+}
+
+/// K线周期的毫秒数
+fn interval_ms(interval: &str) -> Result<u64, String> {
+    CANDLE_INTERVALS
+        .iter()
+        .find(|(name, _)| *name == interval)
+        .map(|(_, ms)| *ms)
+        .ok_or_else(|| {
+            let names: Vec<&str> = CANDLE_INTERVALS.iter().map(|(name, _)| *name).collect();
+            format!(
+                "invalid interval: {}, expected one of {}",
+                interval,
+                names.join(", ")
+            )
+        })
+}
+
+/// 以前 `period` 个收盘价的均值为初始值计算 EMA，数据不足时返回 None
+pub fn ema(closes: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || closes.len() < period {
+        return None;
+    }
+    let k = 2.0 / (period as f64 + 1.0);
+    let seed = closes[..period].iter().fold(0.0, |sum, c| sum + c) / period as f64;
+    Some(
+        closes[period..]
+            .iter()
+            .fold(seed, |ema, close| close * k + ema * (1.0 - k)),
+    )
+}
+
+/// 读取规则文件并校验所有规则，标的名称统一为交易所中的名称
+pub async fn load<B: Backend>(toolset: &Toolset<B>, path: &Path) -> Result<Vec<Rule>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        ToolsetError::Validation(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    let file: RuleFile = serde_yaml::from_str(&content)
+        .map_err(|e| ToolsetError::Validation(format!("{}: {}", path.display(), e)))?;
+    if file.rules.is_empty() {
+        return Err(ToolsetError::Validation(format!("{} has no rules", path.display())).into());
+    }
+
+    let assets: HashMap<String, String> = toolset
+        .markets()
+        .await?
+        .into_iter()
+        .map(|m| (m.asset.to_lowercase(), m.asset))
+        .collect();
+    let mut names = HashSet::new();
+    let mut errors = vec![];
+    let mut rules = vec![];
+    for mut rule in file.rules {
+        let mut check = || -> Result<(), String> {
+            if !names.insert(rule.name.clone()) {
+                return Err("duplicate rule name".into());
+            }
+            rule.when.validate()?;
+            for asset in rule
+                .when
+                .assets_mut()
+                .into_iter()
+                .chain([rule.then.asset_mut()])
+            {
+                *asset = assets
+                    .get(&asset.to_lowercase())
+                    .ok_or_else(|| format!("Unknown asset: {}", asset))?
+                    .clone();
+            }
+            if let Some(slippage) = rule.then.slippage()
+                && !(0.0..1.0).contains(&slippage)
+            {
+                return Err(format!("slippage must be between 0 and 1: {}", slippage));
+            }
+            match &rule.then {
+                RuleAction::Order(row) => {
+                    let spec = row.clone().into_spec()?;
+                    toolset.validate_order(&spec).map_err(|e| {
+                        match e.downcast_ref::<ToolsetError>() {
+                            Some(ToolsetError::Validation(message)) => message.clone(),
+                            _ => e.to_string(),
+                        }
+                    })?;
+                }
+                RuleAction::Market { side, quantity, .. } => {
+                    OrderSide::from_str(side, true)
+                        .map_err(|_| format!("Invalid side: {}", side))?;
+                    if *quantity <= 0.0 {
+                        return Err(format!("quantity must be positive: {}", quantity));
+                    }
+                }
+                RuleAction::Close { .. } => {}
+            }
+            Ok(())
+        };
+        match check() {
+            Ok(()) => rules.push(rule),
+            Err(error) => errors.push(format!("rule {}: {}", rule.name, error)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ToolsetError::Validation(errors.join("; ")).into());
+    }
+    Ok(rules)
+}
+
+/// 规则的运行状态，持久化在本地，重启后不会重复执行已执行过的规则
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleState {
+    /// 上次检查时条件是否成立
+    pub active: bool,
+    /// 上次检查时价格是否在 EMA 之上
+    pub above_ema: Option<bool>,
+    /// 已执行次数
+    pub fired: u32,
+    /// 上次执行时间，毫秒时间戳
+    pub last_fired: Option<u64>,
+    /// 下单时出现网络错误的时间，毫秒时间戳；交易所可能已收到订单，下次检查时先按 cloid 查找
+    pub pending: Option<u64>,
+}
+
+impl RuleState {
+    /// 非重复规则执行过一次后结束
+    pub fn is_done(&self, rule: &Rule) -> bool {
+        !rule.repeat && self.fired > 0
+    }
+}
+
+fn states_file(address: H160) -> String {
+    format!("rules-{:?}.json", address)
+}
+
+/// 账户的所有规则状态，按规则名称索引
+pub fn load_states(address: H160) -> Result<BTreeMap<String, RuleState>> {
+    store::load(&states_file(address))
+}
+
+/// 保存 `rules` 的状态，保留其它规则文件的状态
+pub fn save_states(
+    address: H160,
+    rules: &[Rule],
+    states: &BTreeMap<String, RuleState>,
+) -> Result<()> {
+    let mut saved = load_states(address)?;
+    for rule in rules {
+        match states.get(&rule.name) {
+            Some(state) => saved.insert(rule.name.clone(), state.clone()),
+            None => saved.remove(&rule.name),
+        };
+    }
+    store::save(&states_file(address), &saved)
+}
+
+/// 规则的当前状态，用于展示
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleStatus {
+    pub name: String,
+    pub condition: String,
+    pub action: String,
+    /// 条件对应的当前数值：价格、比值、资金费率或 EMA
+    pub value: Option<f64>,
+    /// 条件当前是否成立
+    pub met: bool,
+    pub repeat: bool,
+    pub fired: u32,
+    pub last_fired: Option<u64>,
+}
+
+/// 一条规则被执行的结果
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleEvent {
+    pub rule: String,
+    /// 执行时间，毫秒时间戳
+    pub time: u64,
+    pub condition: String,
+    pub action: String,
+    /// 触发时条件对应的数值
+    pub value: Option<f64>,
+    pub results: Vec<OrderResult>,
+    /// 未下单或订单被拒绝的原因
+    pub error: Option<String>,
+}
+
+/// 一次检查使用的行情，资金费率及 EMA 按需获取
+#[derive(Default)]
+struct Snapshot {
+    mids: HashMap<String, f64>,
+    funding: Option<HashMap<String, f64>>,
+    /// (标的, 周期, 周期数) -> EMA
+    emas: HashMap<(String, String, usize), Option<f64>>,
+}
+
+impl Snapshot {
+    async fn new<B: Backend>(toolset: &Toolset<B>) -> Result<Self> {
+        let mids = toolset
+            .backend()
+            .all_mids()
+            .await?
+            .into_iter()
+            .filter_map(|(asset, mid)| Some((asset, mid.parse().ok()?)))
+            .collect();
+        Ok(Self {
+            mids,
+            ..Default::default()
+        })
+    }
+
+    /// 标的的资金费率，没有该标的时为 None
+    async fn funding<B: Backend>(
+        &mut self,
+        toolset: &Toolset<B>,
+        asset: &str,
+    ) -> Result<Option<f64>> {
+        if self.funding.is_none() {
+            let markets = toolset.markets().await?;
+            self.funding = Some(markets.into_iter().map(|m| (m.asset, m.funding)).collect());
+        }
+        Ok(self
+            .funding
+            .as_ref()
+            .and_then(|funding| funding.get(asset).copied()))
+    }
+
+    /// 已收盘K线收盘价的 EMA，K线不足时为 None
+    async fn ema<B: Backend>(
+        &mut self,
+        toolset: &Toolset<B>,
+        asset: &str,
+        interval: &str,
+        period: usize,
+    ) -> Result<Option<f64>> {
+        let key = (asset.to_string(), interval.to_string(), period);
+        if let Some(ema) = self.emas.get(&key) {
+            return Ok(*ema);
+        }
+        let now = now_ms();
+        let span = interval_ms(interval).map_err(ToolsetError::Validation)?
+            * (period * EMA_HISTORY + 1) as u64;
+        let closes: Vec<f64> = toolset
+            .backend()
+            .candles(asset, interval, now.saturating_sub(span))
+            .await?
+            .iter()
+            .filter(|c| c.close_time < now)
+            .map(|c| c.close)
+            .collect();
+        let value = ema(&closes, period);
+        self.emas.insert(key, value);
+        Ok(value)
+    }
+}
+
+/// 一次检查的结果
+struct Evaluation {
+    value: Option<f64>,
+    met: bool,
+    /// EMA 条件中价格是否在 EMA 之上
+    above_ema: Option<bool>,
+}
+
+/// 按当前行情检查条件，`state` 提供 EMA 穿越的上一次位置；缺少数据时条件不成立
+async fn evaluate<B: Backend>(
+    toolset: &Toolset<B>,
+    snapshot: &mut Snapshot,
+    condition: &Condition,
+    state: &RuleState,
+) -> Result<Evaluation> {
+    let compare = |value: Option<f64>, above: &Option<f64>, below: &Option<f64>| Evaluation {
+        value,
+        met: value.is_some_and(|v| {
+            above.is_some_and(|above| v > above) || below.is_some_and(|below| v < below)
+        }),
+        above_ema: None,
+    };
+    let evaluation = match condition {
+        Condition::Price {
+            asset,
+            above,
+            below,
+        } => compare(snapshot.mids.get(asset).copied(), above, below),
+        Condition::Ratio {
+            base,
+            quote,
+            above,
+            below,
+        } => {
+            let ratio = match (snapshot.mids.get(base), snapshot.mids.get(quote)) {
+                (Some(base), Some(quote)) if *quote > 0.0 => Some(base / quote),
+                _ => None,
+            };
+            compare(ratio, above, below)
+        }
+        Condition::Funding {
+            asset,
+            above,
+            below,
+        } => {
+            let funding = snapshot.funding(toolset, asset).await?;
+            compare(funding, above, below)
+        }
+        Condition::EmaCross {
+            asset,
+            interval,
+            period,
+            cross,
+        } => {
+            let ema = snapshot.ema(toolset, asset, interval, *period).await?;
+            let (Some(ema), Some(mid)) = (ema, snapshot.mids.get(asset).copied()) else {
+                return Ok(Evaluation {
+                    value: ema,
+                    met: false,
+                    above_ema: state.above_ema,
+                });
+            };
+            // 价格等于 EMA 时保持上一次的位置
+            let above = if mid == ema {
+                state.above_ema
+            } else {
+                Some(mid > ema)
+            };
+            let crossed = state.above_ema.is_some() && above != state.above_ema;
+            Evaluation {
+                value: Some(ema),
+                met: crossed && above == Some(*cross == CrossDirection::Above),
+                above_ema: above,
+            }
+        }
+    };
+    Ok(evaluation)
+}
+
+/// 所有规则的当前状态，不会下单也不会更新状态
+pub async fn statuses<B: Backend>(
+    toolset: &Toolset<B>,
+    rules: &[Rule],
+    states: &BTreeMap<String, RuleState>,
+) -> Result<Vec<RuleStatus>> {
+    let mut snapshot = Snapshot::new(toolset).await?;
+    let mut statuses = vec![];
+    for rule in rules {
+        let state = states.get(&rule.name).cloned().unwrap_or_default();
+        let evaluation = evaluate(toolset, &mut snapshot, &rule.when, &state).await?;
+        statuses.push(RuleStatus {
+            name: rule.name.clone(),
+            condition: rule.when.to_string(),
+            action: rule.then.to_string(),
+            value: evaluation.value,
+            met: evaluation.met,
+            repeat: rule.repeat,
+            fired: state.fired,
+            last_fired: state.last_fired,
+        });
+    }
+    Ok(statuses)
+}
+
+/// 按当前价格计算的下单及市价单动作的订单，用于启动前的风控检查及确认；平仓订单取决于执行时的仓位，不包含在内
+pub async fn order_specs<B: Backend>(
+    toolset: &Toolset<B>,
+    rules: &[Rule],
+) -> Result<Vec<OrderSpec>> {
+    let snapshot = Snapshot::new(toolset).await?;
+    let mut specs = vec![];
+    for rule in rules {
+        if !matches!(rule.then, RuleAction::Close { .. }) {
+            specs.extend(rule.then.specs(toolset, &snapshot.mids).await?);
+        }
+    }
+    Ok(specs)
+}
+
+impl RuleEvent {
+    fn new(rule: &Rule, value: Option<f64>) -> Self {
+        Self {
+            rule: rule.name.clone(),
+            time: now_ms(),
+            condition: rule.when.to_string(),
+            action: rule.then.to_string(),
+            value,
+            results: vec![],
+            error: None,
+        }
+    }
+}
+
+/// 执行规则的动作，订单使用 `cloid`；网络错误返回 Err，其它失败记录在结果的 `error` 中
+///
+/// 没有可平的仓位时不下单，返回 None
+async fn fire<B: Backend>(
+    toolset: &Toolset<B>,
+    snapshot: &Snapshot,
+    rule: &Rule,
+    cloid: Uuid,
+    value: Option<f64>,
+) -> Result<Option<RuleEvent>> {
+    let mut event = RuleEvent::new(rule, value);
+    let placed = match rule.then.specs(toolset, &snapshot.mids).await {
+        Ok(specs) if specs.is_empty() => return Ok(None),
+        Ok(specs) => {
+            let specs: Vec<OrderSpec> = specs
+                .into_iter()
+                .map(|spec| OrderSpec {
+                    cloid: Some(cloid),
+                    ..spec
+                })
+                .collect();
+            toolset.place_orders(&specs).await
+        }
+        Err(error) => Err(error),
+    };
+    match placed {
+        Ok(results) => {
+            event.error = ToolsetError::from_results(&results).map(|e| e.to_string());
+            event.results = results;
+        }
+        Err(error) if ToolsetError::is_retryable(&error) => return Err(error),
+        Err(error) => event.error = Some(error.to_string()),
+    }
+    Ok(Some(event))
+}
+
+/// 查找上次执行的订单：上次下单时网络出错，但交易所可能已经收到该订单
+///
+/// 有 `cloid` 的成交或挂单时视为已执行，返回对应的执行结果
+async fn previous_fire<B: Backend>(
+    toolset: &Toolset<B>,
+    rule: &Rule,
+    cloid: Uuid,
+    since: u64,
+    value: Option<f64>,
+) -> Result<Option<RuleEvent>> {
+    let cloid = action::wire_cloid(&cloid);
+    let fills: Vec<UserFill> = toolset
+        .fills_since(since.saturating_sub(CLOCK_SKEW_MS))
+        .await?
+        .into_iter()
+        .filter(|f| f.cloid.as_deref() == Some(cloid.as_str()))
+        .collect();
+    let resting = toolset
+        .backend()
+        .open_order_cloids(toolset.address())
+        .await?
+        .into_iter()
+        .find(|(_, c)| *c == cloid)
+        .map(|(oid, _)| oid);
+    if fills.is_empty() && resting.is_none() {
+        return Ok(None);
+    }
+    let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+    let filled: f64 = fills.iter().map(|f| parse(&f.sz)).sum();
+    let cost: f64 = fills.iter().map(|f| parse(&f.sz) * parse(&f.px)).sum();
+    let mut event = RuleEvent::new(rule, value);
+    event.results.push(OrderResult {
+        status: if resting.is_some() {
+            "resting"
+        } else {
+            "filled"
+        }
+        .to_string(),
+        oid: resting.or(fills.first().map(|f| f.oid)),
+        filled_size: (filled > 0.0).then_some(filled),
+        average_price: (filled > 0.0).then(|| cost / filled),
+        error: None,
+    });
+    Ok(Some(event))
+}
+
+/// 检查一次所有未结束的规则，条件由不成立变为成立时执行动作并更新 `states`
+///
+/// 平仓规则在条件成立但没有对应仓位时不算执行，出现仓位后在条件仍成立时执行；
+/// 订单被拒绝时同样不算执行，下次检查时条件仍成立则再次执行
+///
+/// 网络错误时返回 Err，出错的规则只记录下单出错的时间，下次检查时先按 cloid 查找已执行的订单再重试；
+/// 之前的规则已更新的状态保留在 `states` 中
+pub async fn tick<B: Backend>(
+    toolset: &Toolset<B>,
+    rules: &[Rule],
+    states: &mut BTreeMap<String, RuleState>,
+) -> Result<Vec<RuleEvent>> {
+    let mut snapshot = Snapshot::new(toolset).await?;
+    let mut events = vec![];
+    for rule in rules {
+        let state = states.entry(rule.name.clone()).or_default();
+        if state.is_done(rule) {
+            continue;
+        }
+        let evaluation = evaluate(toolset, &mut snapshot, &rule.when, state).await?;
+        let mut above_ema = evaluation.above_ema;
+        let cloid = rule.cloid(state.fired);
+        let previous = match state.pending {
+            Some(since) => previous_fire(toolset, rule, cloid, since, evaluation.value).await?,
+            None => None,
+        };
+        state.pending = None;
+        if let Some(event) = previous {
+            state.active = true;
+            state.fired += 1;
+            state.last_fired = Some(event.time);
+            events.push(event);
+        } else if evaluation.met && !state.active {
+            state.pending = Some(now_ms());
+            let fired = fire(toolset, &snapshot, rule, cloid, evaluation.value).await?;
+            state.pending = None;
+            if let Some(event) = fired {
+                if event.error.is_none() {
+                    state.active = true;
+                    state.fired += 1;
+                    state.last_fired = Some(event.time);
+                } else {
+                    // 被拒绝时保留上一次的 EMA 位置，下次检查时仍视为穿越
+                    above_ema = state.above_ema;
+                }
+                events.push(event);
+            }
+        } else {
+            state.active = evaluation.met;
+        }
+        state.above_ema = above_ema;
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockFailure;
+    use crate::mock::test_support::{position, toolset};
+
+    fn rules(yaml: &str) -> Vec<Rule> {
+        serde_yaml::from_str::<RuleFile>(yaml).unwrap().rules
+    }
+
+    #[tokio::test]
+    async fn rejected_orders_are_not_counted_as_fired() {
+        let toolset = toolset();
+        let mut rules = rules(
+            "rules:
+  - name: dip
+    when:
+      price: { asset: BTC, below: 51000 }
+    then:
+      market: { asset: BTC, side: buy, quantity: 100 }",
+        );
+        let mut states = BTreeMap::new();
+
+        // 保证金不足，订单被拒绝
+        for _ in 0..2 {
+            let events = tick(&toolset, &rules, &mut states).await.unwrap();
+            assert_eq!(events.len(), 1);
+            assert!(events[0].error.is_some());
+            assert_eq!((states["dip"].fired, states["dip"].active), (0, false));
+        }
+
+        let RuleAction::Market { quantity, .. } = &mut rules[0].then else {
+            unreachable!()
+        };
+        *quantity = 0.01;
+        let events = tick(&toolset, &rules, &mut states).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].error.is_none());
+        assert_eq!((states["dip"].fired, states["dip"].active), (1, true));
+        assert_eq!(position(&toolset, "BTC"), 0.01);
+        assert!(
+            tick(&toolset, &rules, &mut states)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn funding_of_an_unknown_asset_never_matches() {
+        let toolset = toolset();
+        let rules = rules(
+            "rules:
+  - name: known
+    when:
+      funding: { asset: BTC, below: 0.01% }
+    then:
+      close: { asset: BTC }
+  - name: missing
+    when:
+      funding: { asset: DOGE, below: 0.01% }
+    then:
+      market: { asset: BTC, side: buy, quantity: 0.01 }",
+        );
+        let statuses = statuses(&toolset, &rules, &BTreeMap::new()).await.unwrap();
+        assert_eq!((statuses[0].value, statuses[0].met), (Some(0.0), true));
+        assert_eq!((statuses[1].value, statuses[1].met), (None, false));
+
+        let mut states = BTreeMap::new();
+        assert!(
+            tick(&toolset, &rules, &mut states)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn orders_accepted_before_a_network_error_are_not_placed_again() {
+        let toolset = toolset();
+        let rules = rules(
+            "rules:
+  - name: dip
+    when:
+      price: { asset: BTC, below: 51000 }
+    then:
+      market: { asset: BTC, side: buy, quantity: 0.01 }
+    repeat: true",
+        );
+        let mut states = BTreeMap::new();
+
+        // 交易所已成交，但响应丢失
+        toolset.backend().fail_next(MockFailure::LostResponse);
+        assert!(tick(&toolset, &rules, &mut states).await.is_err());
+        assert_eq!(states["dip"].fired, 0);
+        assert!(states["dip"].pending.is_some());
+        assert_eq!(position(&toolset, "BTC"), 0.01);
+
+        let events = tick(&toolset, &rules, &mut states).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].results[0].status, "filled");
+        assert_eq!(events[0].results[0].filled_size, Some(0.01));
+        assert_eq!((states["dip"].fired, states["dip"].pending), (1, None));
+        assert_eq!(position(&toolset, "BTC"), 0.01);
+
+        // 请求未到达交易所时重试下单，再次执行使用新的 cloid
+        toolset.backend().set_mid("BTC", 52_000.0);
+        assert!(
+            tick(&toolset, &rules, &mut states)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        toolset.backend().set_mid("BTC", 50_000.0);
+        toolset.backend().fail_next(MockFailure::Dropped);
+        assert!(tick(&toolset, &rules, &mut states).await.is_err());
+        let events = tick(&toolset, &rules, &mut states).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].error.is_none());
+        assert_eq!(states["dip"].fired, 2);
+        assert_eq!(position(&toolset, "BTC"), 0.02);
+        assert_ne!(rules[0].cloid(0), rules[0].cloid(1));
+    }
+}
@@ -0,0 +1,471 @@
+use crate::backend::Backend;
+use crate::batch::OrderRow;
+use crate::error::{ErrorReport, ToolsetError};
+use crate::store;
+use crate::toolset::{Toolset, now_ms};
+use crate::types::{OpenOrder, OrderResult, Position, PriceIndex};
+use anyhow::Result;
+use axum::extract::{Path, State};
+use axum::http::{Request, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// 保存自动生成的访问令牌的文件
+const TOKEN_FILE: &str = "serve-token";
+
+/// 每个请求追加一行 JSON 的审计日志
+const AUDIT_FILE: &str = "serve-audit.jsonl";
+
+/// 未指定令牌时读取数据目录中保存的令牌，不存在时生成一个新令牌并保存(仅当前用户可读)
+pub fn token(token: Option<String>) -> Result<String> {
+    if let Some(token) = token {
+        if token.trim().is_empty() {
+            return Err(ToolsetError::Validation("The token must not be empty".into()).into());
+        }
+        return Ok(token);
+    }
+    let path = store::data_dir()?.join(TOKEN_FILE);
+    if let Ok(token) = fs::read_to_string(&path)
+        && !token.trim().is_empty()
+    {
+        return Ok(token.trim().to_string());
+    }
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&path)?.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+/// 自动生成的令牌的保存位置
+pub fn token_path() -> Result<std::path::PathBuf> {
+    Ok(store::data_dir()?.join(TOKEN_FILE))
+}
+
+/// 审计日志的保存位置
+pub fn audit_path() -> Result<std::path::PathBuf> {
+    Ok(store::data_dir()?.join(AUDIT_FILE))
+}
+
+/// 一条审计记录，下单请求记录请求体
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// 毫秒时间戳
+    pub time: u64,
+    pub remote: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn audit(entry: &AuditEntry) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_path()?)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+struct ServerState<B> {
+    toolset: Toolset<B>,
+    token: String,
+    /// 下单及撤单逐个执行，避免并发请求绕过风控的每日下单计数
+    trading: Mutex<()>,
+}
+
+type SharedState<B> = Arc<ServerState<B>>;
+
+/// 请求失败时的响应，响应体与 `--output json` 时 stderr 中的错误相同
+struct ApiError(anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(error: E) -> Self {
+        Self(error.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match ToolsetError::classify(&self.0) {
+            Some(ToolsetError::Validation(_)) => StatusCode::BAD_REQUEST,
+            Some(ToolsetError::Network(_)) => StatusCode::BAD_GATEWAY,
+            Some(ToolsetError::Rejected(_) | ToolsetError::PartialFailure { .. }) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Some(ToolsetError::Risk(_)) => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let report = ErrorReport::new(&self.0);
+        let mut response = (status, Json(serde_json::json!({ "error": report }))).into_response();
+        response.extensions_mut().insert(AuditError(report.message));
+        response
+    }
+}
+
+/// 随响应传给审计中间件的错误信息
+#[derive(Clone)]
+struct AuditError(String);
+
+/// 随响应传给审计中间件的下单请求体
+#[derive(Clone)]
+struct AuditBody(serde_json::Value);
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+/// 按固定时间比较令牌，避免通过响应时间猜测令牌
+fn token_matches(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// 校验 `Authorization: Bearer <token>`，并为每个请求写一条审计记录
+async fn authorize<B: Backend + 'static>(
+    State(state): State<SharedState<B>>,
+    request: Request<axum::body::Body>,
+    next: Next<axum::body::Body>,
+) -> Response {
+    let remote = request
+        .extensions()
+        .get::<axum::extract::ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.to_string())
+        .unwrap_or_default();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token_matches(&state.token, token.trim()));
+
+    let response = if authorized {
+        next.run(request).await
+    } else {
+        let mut response = (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "error": { "kind": "unauthorized", "message": "Missing or invalid bearer token" }
+            })),
+        )
+            .into_response();
+        response
+            .extensions_mut()
+            .insert(AuditError("Unauthorized".into()));
+        response
+    };
+
+    let entry = AuditEntry {
+        time: now_ms(),
+        remote,
+        method,
+        path,
+        status: response.status().as_u16(),
+        body: response
+            .extensions()
+            .get::<AuditBody>()
+            .map(|b| b.0.clone()),
+        error: response
+            .extensions()
+            .get::<AuditError>()
+            .map(|e| e.0.clone()),
+    };
+    if let Err(error) = audit(&entry) {
+        eprintln!("  🔴 Failed to write audit log: {}", error);
+    }
+    response
+}
+
+async fn mids<B: Backend + 'static>(
+    State(state): State<SharedState<B>>,
+) -> ApiResult<Vec<PriceIndex>> {
+    Ok(Json(state.toolset.mids().await?))
+}
+
+async fn positions<B: Backend + 'static>(
+    State(state): State<SharedState<B>>,
+) -> ApiResult<Vec<Position>> {
+    Ok(Json(state.toolset.positions().await?))
+}
+
+async fn orders<B: Backend + 'static>(
+    State(state): State<SharedState<B>>,
+) -> ApiResult<Vec<OpenOrder>> {
+    Ok(Json(state.toolset.open_orders().await?))
+}
+
+/// 下单，请求体与批量下单文件中的一行相同；所有订单被拒绝时返回 422，响应体中仍包含执行结果
+async fn place_order<B: Backend + 'static>(
+    State(state): State<SharedState<B>>,
+    body: axum::body::Bytes,
+) -> Response {
+    let value: Option<serde_json::Value> = serde_json::from_slice(&body).ok();
+    let mut response = match place(&state, &body).await {
+        Ok(results) => match ToolsetError::from_results(&results) {
+            Some(error) => {
+                let mut response =
+                    (StatusCode::UNPROCESSABLE_ENTITY, Json(&results)).into_response();
+                response
+                    .extensions_mut()
+                    .insert(AuditError(error.to_string()));
+                response
+            }
+            None => Json(&results).into_response(),
+        },
+        Err(error) => error.into_response(),
+    };
+    if let Some(value) = value {
+        response.extensions_mut().insert(AuditBody(value));
+    }
+    response
+}
+
+async fn place<B: Backend + 'static>(
+    state: &ServerState<B>,
+    body: &[u8],
+) -> std::result::Result<Vec<OrderResult>, ApiError> {
+    let row: OrderRow = serde_json::from_slice(body)
+        .map_err(|e| ToolsetError::Validation(format!("Invalid order: {}", e)))?;
+    let spec = row.into_spec().map_err(ToolsetError::Validation)?;
+    state.toolset.validate_order(&spec)?;
+    let _guard = state.trading.lock().await;
+    Ok(state.toolset.place_order(&spec).await?)
+}
+
+/// 按订单ID撤单，标的从当前挂单中查找
+async fn cancel_order<B: Backend + 'static>(
+    State(state): State<SharedState<B>>,
+    Path(oid): Path<u64>,
+) -> ApiResult<Vec<OrderResult>> {
+    let _guard = state.trading.lock().await;
+    let order = state
+        .toolset
+        .open_orders()
+        .await?
+        .into_iter()
+        .find(|o| o.oid == oid)
+        .ok_or_else(|| ToolsetError::Validation(format!("No open order {}", oid)))?;
+    let results = state.toolset.cancel(&order.asset, oid).await?;
+    if let Some(error) = ToolsetError::from_results(&results) {
+        return Err(error.into());
+    }
+    Ok(Json(results))
+}
+
+async fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({
+            "error": { "kind": "not_found", "message": "Unknown endpoint" }
+        })),
+    )
+        .into_response()
+}
+
+/// 所有接口的路由，每个请求都先校验令牌并写审计记录
+fn router<B: Backend + 'static>(toolset: Toolset<B>, token: String) -> Router {
+    let state = Arc::new(ServerState {
+        toolset,
+        token,
+        trading: Mutex::new(()),
+    });
+    Router::new()
+        .route("/mids", get(mids::<B>))
+        .route("/positions", get(positions::<B>))
+        .route("/orders", get(orders::<B>))
+        .route("/order", post(place_order::<B>))
+        .route("/order/:id", delete(cancel_order::<B>))
+        .fallback(not_found)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            authorize::<B>,
+        ))
+        .with_state(state)
+}
+
+/// 在 `listen` 上提供 HTTP/JSON 接口，直到 Ctrl-C
+///
+/// 所有请求都需要 `Authorization: Bearer <token>`；下单不会交互确认，但仍受风控限制约束
+pub async fn serve<B: Backend + 'static>(
+    toolset: Toolset<B>,
+    listen: SocketAddr,
+    token: String,
+) -> Result<()> {
+    let app = router(toolset, token);
+    let server = axum::Server::try_bind(&listen)
+        .map_err(|e| ToolsetError::Config(format!("Cannot listen on {}: {}", listen, e)))?
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+    server
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+/// 支持的接口，启动时显示
+pub const ENDPOINTS: [&str; 5] = [
+    "GET /mids",
+    "GET /positions",
+    "GET /orders",
+    "POST /order",
+    "DELETE /order/:id",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::test_support::toolset;
+    use serde_json::{Value, json};
+
+    const TOKEN: &str = "secret-token";
+
+    /// 在本地随机端口启动接口，返回地址
+    fn start() -> String {
+        let app = router(toolset(), TOKEN.to_string());
+        let server = axum::Server::bind(&([127, 0, 0, 1], 0).into())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        url
+    }
+
+    async fn send(request: reqwest::RequestBuilder) -> (u16, Value) {
+        let response = request.send().await.unwrap();
+        let status = response.status().as_u16();
+        (status, response.json().await.unwrap())
+    }
+
+    fn audit_entries(path: &str) -> Vec<Value> {
+        fs::read_to_string(audit_path().unwrap())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .filter(|entry| entry["path"] == path)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn missing_or_wrong_token_is_unauthorized() {
+        let url = start();
+        let client = reqwest::Client::new();
+        for token in [None, Some("secret-tokem"), Some("secret")] {
+            let mut request = client.get(format!("{}/mids", url));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            let (status, body) = send(request).await;
+            assert_eq!(status, 401);
+            assert_eq!(body["error"]["kind"], "unauthorized");
+        }
+        let (status, body) = send(client.get(format!("{}/mids", url)).bearer_auth(TOKEN)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert!(token_matches(TOKEN, TOKEN));
+        assert!(!token_matches(TOKEN, ""));
+    }
+
+    #[tokio::test]
+    async fn orders_are_placed_rejected_and_audited() {
+        let url = start();
+        let client = reqwest::Client::new();
+        let order = json!({
+            "asset": "BTC", "side": "buy", "quantity": 0.01, "price": 49000.0
+        });
+        let (status, body) = send(
+            client
+                .post(format!("{}/order", url))
+                .bearer_auth(TOKEN)
+                .json(&order),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["status"], "resting");
+        let (_, orders) = send(client.get(format!("{}/orders", url)).bearer_auth(TOKEN)).await;
+        assert_eq!(orders[0]["oid"], body[0]["oid"]);
+
+        // Ioc 订单未能立即成交，被交易所拒绝
+        let ioc = json!({
+            "asset": "BTC", "side": "buy", "quantity": 0.01, "price": 40000.0, "limit": "Ioc"
+        });
+        let (status, body) = send(
+            client
+                .post(format!("{}/order", url))
+                .bearer_auth(TOKEN)
+                .json(&ioc),
+        )
+        .await;
+        assert_eq!(status, 422);
+        assert_eq!(body[0]["status"], "error");
+
+        let (status, body) = send(
+            client
+                .post(format!("{}/order", url))
+                .bearer_auth(TOKEN)
+                .json(&json!({ "asset": "BTC" })),
+        )
+        .await;
+        assert_eq!(status, 400);
+        assert_eq!(body["error"]["kind"], "validation");
+
+        let entries: Vec<Value> = audit_entries("/order")
+            .into_iter()
+            .filter(|e| e["body"] == ioc)
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["method"], "POST");
+        assert_eq!(entries[0]["status"], 422);
+        assert!(
+            entries[0]["remote"]
+                .as_str()
+                .unwrap()
+                .starts_with("127.0.0.1:")
+        );
+        assert!(entries[0]["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn cancelling_an_unknown_order_is_a_bad_request() {
+        let url = start();
+        let client = reqwest::Client::new();
+        let path = "/order/987654321";
+        let (status, body) =
+            send(client.delete(format!("{}{}", url, path)).bearer_auth(TOKEN)).await;
+        assert_eq!(status, 400);
+        assert_eq!(
+            body["error"]["message"],
+            "Invalid input: No open order 987654321"
+        );
+
+        let entries = audit_entries(path);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["status"], 400);
+        assert_eq!(
+            entries[0]["error"],
+            "Invalid input: No open order 987654321"
+        );
+    }
+}
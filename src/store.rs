@@ -0,0 +1,60 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+
+/// 本地数据目录，默认为 `~/.hlt`，可通过 `HLT_HOME` 环境变量覆盖；单元测试使用临时目录
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = if cfg!(test) {
+        env::temp_dir().join(format!("hlt-test-{}", std::process::id()))
+    } else {
+        match env::var("HLT_HOME") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => {
+                let home = env::var("HOME").map_err(|_| anyhow::anyhow!("HOME not set"))?;
+                PathBuf::from(home).join(".hlt")
+            }
+        }
+    };
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// 读取本地 JSON 文件，文件不存在时返回默认值
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T> {
+    let path = data_dir()?.join(name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Error parsing {}: {}", path.display(), e))
+}
+
+/// 写入本地 JSON 文件，先写临时文件再替换，避免中断时损坏原文件
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    let path = data_dir()?.join(name);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// 在文件锁内重新读取、修改并写回本地 JSON 文件，多个进程同时修改同一文件时不会互相覆盖
+pub fn update<T, R>(name: &str, f: impl FnOnce(&mut T) -> R) -> Result<R>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(data_dir()?.join(format!("{}.lock", name)))?;
+    lock.lock()?;
+    let mut value: T = load(name)?;
+    let result = f(&mut value);
+    save(name, &value)?;
+    Ok(result)
+}
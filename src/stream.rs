@@ -0,0 +1,509 @@
+use crate::backend::Backend;
+use crate::toolset::Toolset;
+use crate::tui::LiveUpdate;
+use crate::types::{BookLevel, OpenOrder, Position, PriceIndex, Trade};
+use anyhow::Result;
+use futures::stream::{self, Stream};
+use hyperliquid_rust_sdk::{InfoClient, Message, Subscription, UserData};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::time::sleep;
+
+/// 实时成交面板保留的最多成交数
+pub const MAX_LIVE_TRADES: usize = 200;
+
+/// 补拉快照失败后的重试间隔
+const RESYNC_RETRY: Duration = Duration::from_secs(5);
+
+/// 连接断开时面板底部显示的状态
+const DISCONNECTED: &str = "🔴 disconnected, reconnecting";
+
+/// WebSocket 推送的事件
+pub enum FeedEvent {
+    Message(Message),
+    /// 连接断开，SDK 每秒重连一次并自动重新订阅
+    Disconnected,
+    /// 断开后重新收到推送；断开期间的增量数据(成交、订单更新、账户事件)可能缺失，需要重新拉取快照
+    Resumed,
+}
+
+/// 一条 WebSocket 连接上的一组订阅
+///
+/// 断线重连及重新订阅由 SDK 完成，这里只识别断开及恢复，供面板补拉断开期间缺失的数据
+pub struct LiveFeed {
+    // 持有 InfoClient 以保持连接，释放时连接关闭
+    _info_client: InfoClient,
+    receiver: UnboundedReceiver<Message>,
+    connected: bool,
+    /// 恢复时先返回 Resumed，恢复后的第一条推送留到下一次返回
+    pending: Option<Message>,
+}
+
+impl LiveFeed {
+    pub async fn subscribe(subscriptions: Vec<Subscription>) -> Result<Self> {
+        let mut info_client = InfoClient::with_reconnect(None, None).await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        for subscription in subscriptions {
+            info_client.subscribe(subscription, sender.clone()).await?;
+        }
+        Ok(Self {
+            _info_client: info_client,
+            receiver,
+            connected: true,
+            pending: None,
+        })
+    }
+
+    /// 下一个事件，连接关闭且不再重连时返回 None
+    pub async fn next(&mut self) -> Option<FeedEvent> {
+        if let Some(message) = self.pending.take() {
+            return Some(FeedEvent::Message(message));
+        }
+        loop {
+            match self.receiver.recv().await? {
+                // 断开时每个订阅各收到一次，重连失败时每秒重复
+                Message::NoData => {
+                    if self.connected {
+                        self.connected = false;
+                        return Some(FeedEvent::Disconnected);
+                    }
+                }
+                Message::SubscriptionResponse | Message::Pong => {}
+                message if !self.connected => {
+                    self.connected = true;
+                    self.pending = Some(message);
+                    return Some(FeedEvent::Resumed);
+                }
+                message => return Some(FeedEvent::Message(message)),
+            }
+        }
+    }
+}
+
+/// 连接状态对应的面板状态：断开时显示提示，恢复时清除
+fn status_update<D>(event: &FeedEvent) -> Option<LiveUpdate<D>> {
+    match event {
+        FeedEvent::Disconnected => Some(LiveUpdate::Status(Some(DISCONNECTED.to_string()))),
+        FeedEvent::Resumed => Some(LiveUpdate::Status(None)),
+        FeedEvent::Message(_) => None,
+    }
+}
+
+fn mids_from(mids: &HashMap<String, String>) -> Vec<PriceIndex> {
+    mids.iter()
+        .filter(|(k, _)| !k.starts_with('@'))
+        .map(|(k, v)| PriceIndex {
+            asset: k.clone(),
+            price: v.parse::<f64>().unwrap_or(0.0),
+        })
+        .collect()
+}
+
+/// 所有永续合约的中间价，每次推送都是完整数据，断开期间的变化在恢复后自然补齐
+pub async fn mids() -> Result<impl Stream<Item = LiveUpdate<PriceIndex>>> {
+    let feed = LiveFeed::subscribe(vec![Subscription::AllMids]).await?;
+    Ok(stream::unfold(feed, |mut feed| async move {
+        loop {
+            let event = feed.next().await?;
+            if let Some(update) = status_update(&event) {
+                return Some((update, feed));
+            }
+            if let FeedEvent::Message(Message::AllMids(mids)) = event {
+                return Some((LiveUpdate::Rows(mids_from(&mids.data.mids)), feed));
+            }
+        }
+    }))
+}
+
+/// 盘口，每次推送都是完整快照
+pub async fn book(asset: &str, depth: usize) -> Result<impl Stream<Item = LiveUpdate<BookLevel>>> {
+    let feed = LiveFeed::subscribe(vec![Subscription::L2Book {
+        coin: asset.to_string(),
+    }])
+    .await?;
+    Ok(stream::unfold(feed, move |mut feed| async move {
+        loop {
+            let event = feed.next().await?;
+            if let Some(update) = status_update(&event) {
+                return Some((update, feed));
+            }
+            if let FeedEvent::Message(Message::L2Book(book)) = event {
+                let side = |i: usize| {
+                    book.data
+                        .levels
+                        .get(i)
+                        .map(|levels| {
+                            levels
+                                .iter()
+                                .map(|l| BookLevel::parse(&l.px, &l.sz, l.n))
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default()
+                };
+                let levels = BookLevel::from_levels(side(0), side(1), depth);
+                return Some((LiveUpdate::Rows(levels), feed));
+            }
+        }
+    }))
+}
+
+/// 最近的成交，按时间保留最新的 [`MAX_LIVE_TRADES`] 笔，快照与推送按成交去重
+#[derive(Default)]
+struct TradeTape {
+    trades: Vec<Trade>,
+    keys: HashSet<String>,
+}
+
+impl TradeTape {
+    fn extend(&mut self, trades: impl IntoIterator<Item = Trade>) {
+        for trade in trades {
+            if self.keys.insert(trade.key.clone()) {
+                self.trades.push(trade);
+            }
+        }
+        self.trades.sort_by_key(|t| std::cmp::Reverse(t.time));
+        for trade in self.trades.drain(MAX_LIVE_TRADES.min(self.trades.len())..) {
+            self.keys.remove(&trade.key);
+        }
+    }
+}
+
+/// 面板数据的状态：推送的增量与 HTTP 快照合并，启动时及断开恢复后重新拉取快照
+struct Synced<T> {
+    feed: LiveFeed,
+    data: T,
+    resync: bool,
+    /// 上一次拉取快照失败，成功后清除面板上的错误
+    failed: bool,
+}
+
+/// 标的的最新成交：先拉取一次快照，之后追加推送的成交；断开恢复后重新拉取快照补齐缺失的成交
+pub async fn trades<'a, B: Backend>(
+    toolset: &'a Toolset<B>,
+    asset: &'a str,
+    large_notional: f64,
+) -> Result<impl Stream<Item = LiveUpdate<Trade>> + 'a> {
+    let feed = LiveFeed::subscribe(vec![Subscription::Trades {
+        coin: asset.to_string(),
+    }])
+    .await?;
+    Ok(trade_updates(toolset, asset, large_notional, feed))
+}
+
+/// 已订阅成交推送的 `feed` 对应的成交面板数据
+fn trade_updates<'a, B: Backend>(
+    toolset: &'a Toolset<B>,
+    asset: &'a str,
+    large_notional: f64,
+    feed: LiveFeed,
+) -> impl Stream<Item = LiveUpdate<Trade>> + 'a {
+    let state = Synced {
+        feed,
+        data: TradeTape::default(),
+        resync: true,
+        failed: false,
+    };
+    stream::unfold(state, move |mut state| async move {
+        loop {
+            if state.failed && !state.resync {
+                state.failed = false;
+                return Some((LiveUpdate::Status(None), state));
+            }
+            if state.resync {
+                if state.failed {
+                    sleep(RESYNC_RETRY).await;
+                }
+                return Some(match toolset.trades(asset, large_notional).await {
+                    Ok(trades) => {
+                        state.resync = false;
+                        state.data.extend(trades);
+                        (LiveUpdate::Rows(state.data.trades.clone()), state)
+                    }
+                    Err(error) => {
+                        state.failed = true;
+                        (LiveUpdate::Status(Some(format!("🔴 {}", error))), state)
+                    }
+                });
+            }
+            let event = state.feed.next().await?;
+            if matches!(event, FeedEvent::Resumed) {
+                state.resync = true;
+            }
+            if let Some(update) = status_update(&event) {
+                return Some((update, state));
+            }
+            if let FeedEvent::Message(Message::Trades(trades)) = event {
+                state.data.extend(
+                    trades
+                        .data
+                        .iter()
+                        .map(|t| Trade::from_ws(t, large_notional)),
+                );
+                return Some((LiveUpdate::Rows(state.data.trades.clone()), state));
+            }
+        }
+    })
+}
+
+/// 账户的挂单：按订单更新增删，断开恢复后重新拉取挂单
+///
+/// 订单更新可能很久才有一条，同时订阅中间价作为心跳，以便及时发现连接恢复
+pub async fn orders<'a, B: Backend>(
+    toolset: &'a Toolset<B>,
+) -> Result<impl Stream<Item = LiveUpdate<OpenOrder>> + 'a> {
+    let feed = LiveFeed::subscribe(vec![
+        Subscription::OrderUpdates {
+            user: toolset.address(),
+        },
+        Subscription::AllMids,
+    ])
+    .await?;
+    let state = Synced {
+        feed,
+        data: BTreeMap::<u64, OpenOrder>::new(),
+        resync: true,
+        failed: false,
+    };
+    Ok(stream::unfold(state, move |mut state| async move {
+        loop {
+            if state.failed && !state.resync {
+                state.failed = false;
+                return Some((LiveUpdate::Status(None), state));
+            }
+            if state.resync {
+                if state.failed {
+                    sleep(RESYNC_RETRY).await;
+                }
+                return Some(match toolset.open_orders().await {
+                    Ok(orders) => {
+                        state.resync = false;
+                        state.data = orders.into_iter().map(|o| (o.oid, o)).collect();
+                        (
+                            LiveUpdate::Rows(state.data.values().cloned().collect()),
+                            state,
+                        )
+                    }
+                    Err(error) => {
+                        state.failed = true;
+                        (LiveUpdate::Status(Some(format!("🔴 {}", error))), state)
+                    }
+                });
+            }
+            let event = state.feed.next().await?;
+            if matches!(event, FeedEvent::Resumed) {
+                state.resync = true;
+            }
+            if let Some(update) = status_update(&event) {
+                return Some((update, state));
+            }
+            if let FeedEvent::Message(Message::OrderUpdates(updates)) = event {
+                for update in &updates.data {
+                    // open 为新挂单或部分成交后的剩余数量，其它状态(filled、canceled 等)都已不在盘口
+                    if update.status == "open" {
+                        let order = OpenOrder::from(&update.order);
+                        state.data.insert(order.oid, order);
+                    } else {
+                        state.data.remove(&update.order.oid);
+                    }
+                }
+                return Some((
+                    LiveUpdate::Rows(state.data.values().cloned().collect()),
+                    state,
+                ));
+            }
+        }
+    }))
+}
+
+/// 账户的仓位：成交、资金费及强平事件后重新拉取仓位，中间价推送时重新估算仓位价值及盈亏
+///
+/// 断开期间可能错过账户事件，恢复后同样重新拉取
+pub async fn positions<'a, B: Backend>(
+    toolset: &'a Toolset<B>,
+) -> Result<impl Stream<Item = LiveUpdate<Position>> + 'a> {
+    let feed = LiveFeed::subscribe(vec![
+        Subscription::UserEvents {
+            user: toolset.address(),
+        },
+        Subscription::AllMids,
+    ])
+    .await?;
+    let state = Synced {
+        feed,
+        data: Vec::<Position>::new(),
+        resync: true,
+        failed: false,
+    };
+    Ok(stream::unfold(state, move |mut state| async move {
+        loop {
+            if state.failed && !state.resync {
+                state.failed = false;
+                return Some((LiveUpdate::Status(None), state));
+            }
+            if state.resync {
+                if state.failed {
+                    sleep(RESYNC_RETRY).await;
+                }
+                return Some(match toolset.positions().await {
+                    Ok(positions) => {
+                        state.resync = false;
+                        state.data = positions;
+                        (LiveUpdate::Rows(state.data.clone()), state)
+                    }
+                    Err(error) => {
+                        state.failed = true;
+                        (LiveUpdate::Status(Some(format!("🔴 {}", error))), state)
+                    }
+                });
+            }
+            let event = state.feed.next().await?;
+            if matches!(event, FeedEvent::Resumed) {
+                state.resync = true;
+            }
+            if let Some(update) = status_update(&event) {
+                return Some((update, state));
+            }
+            match event {
+                FeedEvent::Message(Message::User(user))
+                    if !matches!(user.data, UserData::NonUserCancel(_)) =>
+                {
+                    state.resync = true;
+                }
+                FeedEvent::Message(Message::AllMids(mids)) if !state.data.is_empty() => {
+                    for position in &mut state.data {
+                        if let Some(mid) = mids
+                            .data
+                            .mids
+                            .get(&position.asset)
+                            .and_then(|m| m.parse::<f64>().ok())
+                        {
+                            position.reprice(mid);
+                        }
+                    }
+                    return Some((LiveUpdate::Rows(state.data.clone()), state));
+                }
+                _ => {}
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::test_support::{limit, toolset};
+    use crate::order::{LimitType, OrderSide};
+    use crate::toolset::now_ms;
+    use futures::StreamExt;
+    use hyperliquid_rust_sdk::{Trade as WsTrade, Trades};
+    use tokio::sync::mpsc::UnboundedSender;
+
+    /// 不连接交易所的推送，测试通过返回的 sender 发送消息
+    async fn channel() -> (UnboundedSender<Message>, LiveFeed) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let feed = LiveFeed {
+            _info_client: InfoClient::new(None, None).await.unwrap(),
+            receiver,
+            connected: true,
+            pending: None,
+        };
+        (sender, feed)
+    }
+
+    fn ws_trade(hash: &str, time: u64) -> WsTrade {
+        WsTrade {
+            coin: "BTC".into(),
+            side: "B".into(),
+            px: "50000".into(),
+            sz: "0.1".into(),
+            time,
+            hash: hash.into(),
+            tid: 0,
+        }
+    }
+
+    fn trades_message(hash: &str) -> Message {
+        Message::Trades(Trades {
+            data: vec![ws_trade(hash, now_ms())],
+        })
+    }
+
+    fn rows<D>(update: Option<LiveUpdate<D>>) -> Vec<D> {
+        match update {
+            Some(LiveUpdate::Rows(rows)) => rows,
+            _ => panic!("expected rows"),
+        }
+    }
+
+    fn status<D>(update: Option<LiveUpdate<D>>) -> Option<String> {
+        match update {
+            Some(LiveUpdate::Status(status)) => status,
+            _ => panic!("expected a status"),
+        }
+    }
+
+    #[tokio::test]
+    async fn feed_reports_one_disconnect_and_resumes_before_the_next_message() {
+        let (sender, mut feed) = channel().await;
+        for message in [
+            trades_message("a"),
+            Message::NoData,
+            Message::NoData,
+            Message::Pong,
+            Message::SubscriptionResponse,
+            trades_message("b"),
+        ] {
+            sender.send(message).unwrap();
+        }
+        drop(sender);
+
+        let hash = |event: Option<FeedEvent>| match event {
+            Some(FeedEvent::Message(Message::Trades(trades))) => trades.data[0].hash.clone(),
+            _ => panic!("expected trades"),
+        };
+        assert_eq!(hash(feed.next().await), "a");
+        assert!(matches!(feed.next().await, Some(FeedEvent::Disconnected)));
+        assert!(matches!(feed.next().await, Some(FeedEvent::Resumed)));
+        assert_eq!(hash(feed.next().await), "b");
+        assert!(feed.next().await.is_none());
+    }
+
+    #[test]
+    fn tape_deduplicates_and_keeps_the_latest_trades() {
+        let mut tape = TradeTape::default();
+        let trades = (0..MAX_LIVE_TRADES as u64 + 50)
+            .map(|i| Trade::from_ws(&ws_trade(&format!("{:#x}", i), i), f64::MAX));
+        tape.extend(trades);
+        tape.extend([Trade::from_ws(&ws_trade("0xf9", 249), f64::MAX)]);
+        assert_eq!(tape.trades.len(), MAX_LIVE_TRADES);
+        assert_eq!(tape.keys.len(), MAX_LIVE_TRADES);
+        assert_eq!(tape.trades[0].time, 249);
+        assert_eq!(tape.trades[MAX_LIVE_TRADES - 1].time, 50);
+    }
+
+    #[tokio::test]
+    async fn trades_missed_while_disconnected_are_fetched_after_resuming() {
+        let toolset = toolset();
+        let buy = || limit("BTC", OrderSide::Buy, LimitType::Ioc, 51_000.0, 0.01);
+        toolset.place_order(&buy()).await.unwrap();
+        let (sender, feed) = channel().await;
+        let updates = trade_updates(&toolset, "BTC", 1_000_000.0, feed);
+        futures::pin_mut!(updates);
+
+        assert_eq!(rows(updates.next().await).len(), 1);
+        sender.send(trades_message("ws-1")).unwrap();
+        assert_eq!(rows(updates.next().await).len(), 2);
+
+        // 断开期间的成交只能从快照中补齐，快照中已有的成交不会重复
+        sender.send(Message::NoData).unwrap();
+        assert_eq!(status(updates.next().await).as_deref(), Some(DISCONNECTED));
+        toolset.place_order(&buy()).await.unwrap();
+        sender.send(trades_message("ws-2")).unwrap();
+        assert_eq!(status(updates.next().await), None);
+        assert_eq!(rows(updates.next().await).len(), 3);
+        let trades = rows(updates.next().await);
+        assert_eq!(trades.len(), 4);
+        assert!(trades.iter().any(|t| t.key.starts_with("ws-2/")));
+    }
+}
@@ -0,0 +1,1068 @@
+use crate::action;
+use crate::backend::{Backend, HyperliquidBackend};
+use crate::cost::{self, CostMethod};
+use crate::error::ToolsetError;
+use crate::hyperliquid::{self, HyperLiquidConfig};
+use crate::order::{self, LimitType, OrderKind, OrderSide, OrderSpec};
+use crate::risk::{self, RiskContext, RiskGuard, RiskLimits};
+use crate::store;
+use crate::types::{
+    BalanceReport, BookLevel, CancelPreview, DeadManSwitch, FeeSummary, FlattenPreview,
+    FlattenReport, FlattenResult, MarginState, MarketInfo, OpenOrder, OrderPreview, OrderResult,
+    OrderSummary, Position, PriceIndex, SpotBalance, SpotMarket, Trade, UserFill,
+};
+use anyhow::Result;
+use ethers::types::H160;
+use hyperliquid_rust_sdk::{
+    ClientCancelRequest, ClientModifyRequest, ClientOrder, ClientOrderRequest, ExchangeClient,
+    ExchangeDataStatus, InfoClient, UserStateResponse,
+};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// 单个下单或撤单请求最多包含的订单数，超出时分批发送
+pub const MAX_BATCH_SIZE: usize = 40;
+
+/// 未持仓时交易所使用的默认杠杆
+pub(crate) const DEFAULT_LEVERAGE: u32 = 20;
+
+/// 一键平仓两次重试之间的间隔
+const FLATTEN_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// `assets` 为空表示所有标的，否则按名称匹配(不区分大小写)
+fn selected(assets: &[String], asset: &str) -> bool {
+    assets.is_empty() || assets.iter().any(|a| a.eq_ignore_ascii_case(asset))
+}
+
+/// 当前毫秒时间戳
+pub(crate) fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// Gtc / Ioc / Alo，触发单为触发条件
+fn order_type(request: &ClientOrderRequest) -> String {
+    match &request.order_type {
+        ClientOrder::Limit(limit) => limit.tif.clone(),
+        ClientOrder::Trigger(trigger) => format!(
+            "Trigger {} @ {} ({})",
+            trigger.tpsl,
+            trigger.trigger_px,
+            if trigger.is_market { "market" } else { "limit" }
+        ),
+    }
+}
+
+/// Hyperliquid 账户操作的统一入口，`hlt` 的所有命令都通过它完成
+///
+/// 默认访问 Hyperliquid，也可以通过 [`Toolset::with_backend`] 使用其他后端，例如 [`crate::mock::MockExchange`]
+pub struct Toolset<B = HyperliquidBackend> {
+    address: H160,
+    backend: B,
+    risk: Option<RiskGuard>,
+}
+
+impl Toolset<HyperliquidBackend> {
+    pub async fn new(config: &HyperLiquidConfig) -> Result<Self> {
+        let info_client = InfoClient::new(None, None).await?;
+        let wallet = config.wallet()?;
+        let (exchange_client, spot_markets) = tokio::try_join!(
+            async { Ok(ExchangeClient::new(None, wallet, None, None, None).await?) },
+            hyperliquid::spot_markets(&info_client)
+        )?;
+        Ok(Self::with_backend(
+            config.account_address,
+            HyperliquidBackend {
+                info_client,
+                exchange_client,
+                spot_sz_decimals: spot_markets
+                    .into_iter()
+                    .map(|m| (m.pair, m.sz_decimals))
+                    .collect(),
+            },
+        ))
+    }
+}
+
+impl<B: Backend> Toolset<B> {
+    pub fn with_backend(address: H160, backend: B) -> Self {
+        Self {
+            address,
+            backend,
+            risk: None,
+        }
+    }
+
+    /// 启用下单前风控检查，`counter` 为每日下单计数的名称
+    pub fn with_risk(mut self, counter: &str, limits: RiskLimits) -> Self {
+        self.risk = Some(RiskGuard {
+            counter: counter.to_string(),
+            limits,
+            overrides: Default::default(),
+        });
+        self
+    }
+
+    /// 允许这些订单跳过风控检查，仅在用户明确确认后调用
+    ///
+    /// 每个订单只跳过一次，且只有与确认时取整后完全相同的订单才跳过，之后守护进程发出的其它订单仍然检查
+    pub fn override_risk(&self, specs: &[OrderSpec]) -> Result<()> {
+        let Some(guard) = &self.risk else {
+            return Ok(());
+        };
+        let keys = specs
+            .iter()
+            .map(|spec| Ok(risk::order_key(&self.client_request(spec)?)))
+            .collect::<Result<Vec<_>>>()?;
+        guard
+            .overrides
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(keys);
+        Ok(())
+    }
+
+    pub fn address(&self) -> H160 {
+        self.address
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// 所有永续合约的中间价
+    pub async fn mids(&self) -> Result<Vec<PriceIndex>> {
+        let response = self.backend.all_mids().await?;
+        Ok(response
+            .iter()
+            .filter(|(k, _)| !k.starts_with("@"))
+            .map(|(k, v)| PriceIndex {
+                asset: k.clone(),
+                price: v.parse::<f64>().unwrap_or(0.0),
+            })
+            .collect())
+    }
+
+    /// 最新公开成交，名义价值不低于 `large_notional` 的成交标记为大单
+    pub async fn trades(&self, asset: &str, large_notional: f64) -> Result<Vec<Trade>> {
+        let response = self.backend.recent_trades(asset).await?;
+        Ok(response
+            .iter()
+            .map(|t| Trade::from_response(t, large_notional))
+            .collect())
+    }
+
+    /// 盘口，买卖两侧各最多 `depth` 档
+    pub async fn book(&self, asset: &str, depth: usize) -> Result<Vec<BookLevel>> {
+        let book = self.backend.l2_book(asset).await?;
+        let side = |i: usize| {
+            book.levels
+                .get(i)
+                .map(|levels| {
+                    levels
+                        .iter()
+                        .map(|l| BookLevel::parse(&l.px, &l.sz, l.n))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        Ok(BookLevel::from_levels(side(0), side(1), depth))
+    }
+
+    pub async fn markets(&self) -> Result<Vec<MarketInfo>> {
+        self.backend.markets().await
+    }
+
+    /// 单个永续合约的市场信息
+    pub async fn market(&self, asset: &str) -> Result<MarketInfo> {
+        self.markets()
+            .await?
+            .into_iter()
+            .find(|m| m.asset.eq_ignore_ascii_case(asset))
+            .ok_or_else(|| ToolsetError::Validation(format!("Unknown asset: {}", asset)).into())
+    }
+
+    /// 现货交易对，`asset` 可以是交易对名称(如 UBTC/USDC)或基础代币；
+    /// 按基础代币查找时匹配以 USDC 计价的交易对，也匹配交易所的包装代币(如 BTC 对应 UBTC)
+    pub async fn spot_market(&self, asset: &str) -> Result<SpotMarket> {
+        let markets = self.backend.spot_markets().await?;
+        let by_base = |base: &str| {
+            markets
+                .iter()
+                .find(|m| m.quote == "USDC" && m.base.eq_ignore_ascii_case(base))
+        };
+        markets
+            .iter()
+            .find(|m| m.pair.eq_ignore_ascii_case(asset))
+            .or_else(|| by_base(asset))
+            .or_else(|| by_base(&format!("U{}", asset)))
+            .cloned()
+            .ok_or_else(|| {
+                ToolsetError::Validation(format!("Unknown spot asset: {}", asset)).into()
+            })
+    }
+
+    pub async fn fees(&self) -> Result<FeeSummary> {
+        self.backend.fees(self.address).await
+    }
+
+    /// 账户余额，`with_positions` 为 true 时包含仓位
+    pub async fn balances(
+        &self,
+        with_positions: bool,
+        cost_method: CostMethod,
+    ) -> Result<BalanceReport> {
+        let (state, balance, costs) = tokio::join! {
+            self.backend.user_state(self.address),
+            self.backend.user_token_balances(self.address),
+            cost::spot_costs(&self.backend, self.address, cost_method),
+        };
+        let state = state?;
+        let balance = balance?;
+        let (costs, cost_error) = match costs {
+            Ok(costs) => (costs, None),
+            Err(error) => (Default::default(), Some(error.to_string())),
+        };
+        Ok(BalanceReport {
+            spot: balance
+                .balances
+                .iter()
+                .map(|token| SpotBalance::new(token, costs.get(&token.coin)))
+                .collect(),
+            margin: vec![
+                MarginState::new("Isolated", &state.margin_summary),
+                MarginState::new("Cross", &state.cross_margin_summary),
+            ],
+            positions: with_positions
+                .then(|| state.asset_positions.iter().map(Position::from).collect()),
+            cost_error,
+        })
+    }
+
+    pub async fn positions(&self) -> Result<Vec<Position>> {
+        let state = self.backend.user_state(self.address).await?;
+        Ok(state.asset_positions.iter().map(Position::from).collect())
+    }
+
+    pub async fn open_orders(&self) -> Result<Vec<OpenOrder>> {
+        let orders = self.backend.open_orders(self.address).await?;
+        Ok(orders.iter().map(OpenOrder::from).collect())
+    }
+
+    /// `start_time` 之后的所有成交，超过单次请求的上限时分页查询
+    pub async fn fills_since(&self, start_time: u64) -> Result<Vec<UserFill>> {
+        let mut fills = vec![];
+        let mut seen = HashSet::new();
+        let mut start_time = start_time;
+        loop {
+            let page = self
+                .backend
+                .user_fills_by_time(self.address, start_time)
+                .await?;
+            let page_len = page.len();
+            let last_time = page.iter().map(|f| f.time).max().unwrap_or(start_time);
+            fills.extend(page.into_iter().filter(|f| seen.insert(f.tid)));
+            if page_len < cost::FILLS_PAGE_SIZE || last_time <= start_time {
+                break;
+            }
+            start_time = last_time;
+        }
+        Ok(fills)
+    }
+
+    /// 将下单参数转换为按标的精度取整后的订单
+    fn client_request(&self, spec: &OrderSpec) -> Result<ClientOrderRequest> {
+        let mut request = spec.to_client_request()?;
+        let sz_decimals = self
+            .backend
+            .sz_decimals(&spec.asset)
+            .ok_or_else(|| ToolsetError::Validation(format!("Unknown asset: {}", spec.asset)))?;
+        let is_spot = self
+            .backend
+            .asset_index(&spec.asset)
+            .is_some_and(|index| index >= order::SPOT_ASSET_OFFSET);
+        order::round_request(&mut request, sz_decimals, is_spot)?;
+        Ok(request)
+    }
+
+    /// 预览下单：取整后的订单、预估保证金、成交后的杠杆及强平价格，以及待签名的请求体，不会发送
+    pub async fn preview_order(&self, spec: &OrderSpec) -> Result<OrderPreview> {
+        let mut previews = self.preview_orders(std::slice::from_ref(spec)).await?;
+        Ok(previews.remove(0))
+    }
+
+    /// 逐个预览一组订单，每个订单都基于当前账户状态独立估算；现货订单不估算保证金、杠杆及强平价格
+    pub async fn preview_orders(&self, specs: &[OrderSpec]) -> Result<Vec<OrderPreview>> {
+        let (state, markets) =
+            tokio::try_join!(self.backend.user_state(self.address), self.markets())?;
+        let is_perp = |asset: &str| markets.iter().any(|m| m.asset == asset);
+        let spot_markets = if specs.iter().all(|spec| is_perp(&spec.asset)) {
+            vec![]
+        } else {
+            self.backend.spot_markets().await?
+        };
+        specs
+            .iter()
+            .map(|spec| {
+                if let Some(market) = markets.iter().find(|m| m.asset == spec.asset) {
+                    return self.preview_with(spec, &state, market);
+                }
+                let market = spot_markets
+                    .iter()
+                    .find(|m| m.pair == spec.asset)
+                    .ok_or_else(|| {
+                        ToolsetError::Validation(format!("Unknown asset: {}", spec.asset))
+                    })?;
+                Ok(self.preview_request(spec, market.mid_price)?.1)
+            })
+            .collect()
+    }
+
+    /// 预览中与市场类型无关的部分：取整后的订单、订单类型及待签名的请求体
+    fn preview_request(
+        &self,
+        spec: &OrderSpec,
+        mid: Option<f64>,
+    ) -> Result<(ClientOrderRequest, OrderPreview)> {
+        let request = self.client_request(spec)?;
+        let asset_index = self
+            .backend
+            .asset_index(&spec.asset)
+            .ok_or_else(|| ToolsetError::Validation(format!("Unknown asset: {}", spec.asset)))?;
+        let order_type = order_type(&request);
+        let payload =
+            action::unsigned_payload(action::order_action(&[(asset_index, &request)]), now_ms());
+        let preview = OrderPreview {
+            asset: spec.asset.clone(),
+            asset_index,
+            side: format!("{:?}", spec.side).to_lowercase(),
+            order_type,
+            reduce_only: request.reduce_only,
+            price: request.limit_px,
+            size: request.sz,
+            notional: request.limit_px * request.sz,
+            mid,
+            margin_required: None,
+            resulting_position: None,
+            resulting_leverage: None,
+            liquidation_price: None,
+            payload,
+        };
+        Ok((request, preview))
+    }
+
+    fn preview_with(
+        &self,
+        spec: &OrderSpec,
+        state: &UserStateResponse,
+        market: &MarketInfo,
+    ) -> Result<OrderPreview> {
+        let (request, preview) = self.preview_request(spec, market.mid_price)?;
+
+        let (price, size) = (request.limit_px, request.sz);
+        let mid = market.mid_price.unwrap_or(market.mark_price);
+        let current = state
+            .asset_positions
+            .iter()
+            .find(|p| p.position.coin == spec.asset)
+            .map(|p| &p.position);
+        let position = current
+            .map(|p| p.szi.parse::<f64>().unwrap_or(0.0))
+            .unwrap_or(0.0);
+        let leverage = current
+            .map(|p| p.leverage.value)
+            .unwrap_or(DEFAULT_LEVERAGE.min(market.max_leverage)) as f64;
+
+        let signed = if request.is_buy { size } else { -size };
+        let mut resulting_position = position + signed;
+        if request.reduce_only && resulting_position.signum() != position.signum() {
+            resulting_position = 0.0;
+        }
+        let increase = (resulting_position.abs() - position.abs()).max(0.0);
+
+        let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+        let summary = &state.cross_margin_summary;
+        let account_value = parse(&summary.account_value);
+        let notional_after =
+            parse(&summary.total_ntl_pos) - position.abs() * mid + resulting_position.abs() * price;
+        // 维持保证金按最大杠杆下初始保证金的一半估算
+        let maintenance: f64 = state
+            .asset_positions
+            .iter()
+            .filter(|p| p.position.coin != spec.asset)
+            .map(|p| parse(&p.position.position_value) / (2.0 * p.position.max_leverage as f64))
+            .sum::<f64>()
+            + resulting_position.abs() * price / (2.0 * market.max_leverage as f64);
+        let liquidation_price = (resulting_position != 0.0)
+            .then(|| price - (account_value - maintenance) / resulting_position)
+            .filter(|p| *p > 0.0);
+
+        Ok(OrderPreview {
+            margin_required: Some(increase * price / leverage),
+            resulting_position: Some(resulting_position),
+            resulting_leverage: (account_value > 0.0).then(|| notional_after / account_value),
+            liquidation_price,
+            ..preview
+        })
+    }
+
+    /// 下单确认时显示的订单摘要：取整后的订单及中间价，不需要账户状态，永续及现货订单都适用
+    pub async fn order_summaries(&self, specs: &[OrderSpec]) -> Result<Vec<OrderSummary>> {
+        let requests = specs
+            .iter()
+            .map(|spec| self.client_request(spec))
+            .collect::<Result<Vec<_>>>()?;
+        let mut mids: HashMap<String, f64> = self
+            .backend
+            .all_mids()
+            .await?
+            .into_iter()
+            .filter_map(|(asset, mid)| Some((asset, mid.parse().ok()?)))
+            .collect();
+        // 现货交易对在 allMids 中以 @序号 表示，按交易对名称从现货市场查找
+        if requests.iter().any(|r| !mids.contains_key(&r.asset)) {
+            mids.extend(
+                self.backend
+                    .spot_markets()
+                    .await?
+                    .into_iter()
+                    .filter_map(|m| Some((m.pair, m.mid_price?))),
+            );
+        }
+        Ok(requests
+            .iter()
+            .map(|request| OrderSummary {
+                asset: request.asset.clone(),
+                side: if request.is_buy { "buy" } else { "sell" }.to_string(),
+                order_type: order_type(request),
+                price: request.limit_px,
+                size: request.sz,
+                notional: request.limit_px * request.sz,
+                mid: mids.get(&request.asset).copied(),
+            })
+            .collect())
+    }
+
+    /// 订单违反的风控限制，未启用风控时为空
+    pub async fn risk_violations(&self, specs: &[OrderSpec]) -> Result<Vec<String>> {
+        let requests = specs
+            .iter()
+            .map(|spec| self.client_request(spec))
+            .collect::<Result<Vec<_>>>()?;
+        self.check_risk(&requests).await
+    }
+
+    async fn check_risk(&self, requests: &[ClientOrderRequest]) -> Result<Vec<String>> {
+        let Some(guard) = self.risk.as_ref().filter(|g| !g.limits.is_empty()) else {
+            return Ok(vec![]);
+        };
+        let (mids, state) = tokio::try_join!(
+            self.backend.all_mids(),
+            self.backend.user_state(self.address)
+        )?;
+        let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+        let context = RiskContext {
+            mids: mids.iter().map(|(k, v)| (k.clone(), parse(v))).collect(),
+            positions: state
+                .asset_positions
+                .iter()
+                .map(|p| (p.position.coin.clone(), parse(&p.position.szi)))
+                .collect(),
+            account_value: parse(&state.cross_margin_summary.account_value),
+            total_notional: parse(&state.cross_margin_summary.total_ntl_pos),
+            orders_today: risk::orders_today(&guard.counter)?,
+        };
+        Ok(risk::check(&guard.limits, requests, &context))
+    }
+
+    /// 启用风控时，订单违反任一限制则返回错误；全部是用户确认跳过检查的订单时不检查
+    async fn enforce_risk(&self, requests: &[ClientOrderRequest]) -> Result<()> {
+        let Some(guard) = &self.risk else {
+            return Ok(());
+        };
+        if guard.take_overrides(requests) {
+            return Ok(());
+        }
+        let violations = self.check_risk(requests).await?;
+        if !violations.is_empty() {
+            return Err(ToolsetError::Risk(violations.join("; ")).into());
+        }
+        Ok(())
+    }
+
+    /// 风控检查通过后分批发送订单，所有订单都经过这里
+    ///
+    /// 第一批发送失败时返回错误；之后某批失败时，该批及剩余订单的结果记为错误，不再继续发送
+    async fn submit_orders(&self, requests: Vec<ClientOrderRequest>) -> Result<Vec<OrderResult>> {
+        self.enforce_risk(&requests).await?;
+
+        let total = requests.len();
+        let mut results = Vec::with_capacity(total);
+        let mut requests = requests.into_iter().peekable();
+        while requests.peek().is_some() {
+            let chunk: Vec<_> = requests.by_ref().take(MAX_BATCH_SIZE).collect();
+            let count = risk::counted(&chunk);
+            let sent = self.backend.bulk_order(chunk).await;
+            if sent.is_ok()
+                && let Some(guard) = &self.risk
+            {
+                risk::record_orders(&guard.counter, count)?;
+            }
+            match sent.and_then(hyperliquid::statuses) {
+                Ok(statuses) => results.extend(statuses),
+                Err(error) if results.is_empty() => return Err(error),
+                Err(error) => {
+                    let message = format!("Not placed: {}", error);
+                    results.extend((results.len()..total).map(|_| OrderResult::failed(&message)));
+                    break;
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// 校验订单参数及标的，不会发送
+    pub fn validate_order(&self, spec: &OrderSpec) -> Result<()> {
+        self.client_request(spec).map(|_| ())
+    }
+
+    /// 下单，返回每个订单的执行结果，被交易所拒绝的订单体现在结果的 `error` 中
+    pub async fn place_order(&self, spec: &OrderSpec) -> Result<Vec<OrderResult>> {
+        self.place_orders(std::slice::from_ref(spec)).await
+    }
+
+    /// 批量下单，所有订单校验通过后才会发送，结果与 `specs` 一一对应
+    pub async fn place_orders(&self, specs: &[OrderSpec]) -> Result<Vec<OrderResult>> {
+        let requests = specs
+            .iter()
+            .map(|spec| self.client_request(spec))
+            .collect::<Result<Vec<_>>>()?;
+        self.submit_orders(requests).await
+    }
+
+    /// 将挂单修改为新的参数，修改后的订单同样经过风控检查；交易所可能分配新的订单ID
+    pub async fn modify_order(&self, oid: u64, spec: &OrderSpec) -> Result<OrderResult> {
+        let request = self.client_request(spec)?;
+        self.enforce_risk(std::slice::from_ref(&request)).await?;
+        let count = risk::counted(std::slice::from_ref(&request));
+        let response = self
+            .backend
+            .bulk_modify(vec![ClientModifyRequest {
+                oid,
+                order: request,
+            }])
+            .await?;
+        if let Some(guard) = &self.risk {
+            risk::record_orders(&guard.counter, count)?;
+        }
+        let mut results = hyperliquid::statuses(response)?;
+        Ok(if results.is_empty() {
+            // 部分接口只返回成功，不包含每个订单的状态
+            OrderResult::from(&ExchangeDataStatus::Success)
+        } else {
+            results.remove(0)
+        })
+    }
+
+    /// 预览撤单及待签名的请求体，不会发送
+    pub async fn preview_cancel(&self, asset: &str, oid: u64) -> Result<CancelPreview> {
+        let asset_index = self
+            .backend
+            .asset_index(asset)
+            .ok_or_else(|| ToolsetError::Validation(format!("Unknown asset: {}", asset)))?;
+        let order = self
+            .open_orders()
+            .await?
+            .into_iter()
+            .find(|o| o.oid == oid && o.asset == asset);
+        Ok(CancelPreview {
+            asset: asset.to_string(),
+            asset_index,
+            oid,
+            order,
+            payload: action::unsigned_payload(
+                action::cancel_action(&[(asset_index, oid)]),
+                now_ms(),
+            ),
+        })
+    }
+
+    /// 撤单，返回每个订单的执行结果
+    pub async fn cancel(&self, asset: &str, oid: u64) -> Result<Vec<OrderResult>> {
+        self.cancel_orders(&[(asset.to_string(), oid)]).await
+    }
+
+    /// 批量撤单，`orders` 为 (标的, 订单ID)，分批发送，结果与 `orders` 一一对应
+    pub async fn cancel_orders(&self, orders: &[(String, u64)]) -> Result<Vec<OrderResult>> {
+        if let Some((asset, _)) = orders
+            .iter()
+            .find(|(asset, _)| self.backend.asset_index(asset).is_none())
+        {
+            return Err(ToolsetError::Validation(format!("Unknown asset: {}", asset)).into());
+        }
+        let mut results = Vec::with_capacity(orders.len());
+        for chunk in orders.chunks(MAX_BATCH_SIZE) {
+            let requests = chunk
+                .iter()
+                .map(|(asset, oid)| ClientCancelRequest {
+                    asset: asset.clone(),
+                    oid: *oid,
+                })
+                .collect();
+            let sent = self.backend.bulk_cancel(requests).await;
+            match sent.and_then(hyperliquid::statuses) {
+                Ok(statuses) => results.extend(statuses),
+                Err(error) if results.is_empty() => return Err(error),
+                Err(error) => {
+                    let message = format!("Not cancelled: {}", error);
+                    results.extend(
+                        (results.len()..orders.len()).map(|_| OrderResult::failed(&message)),
+                    );
+                    break;
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    fn dead_man_file(&self) -> String {
+        format!("deadman-{:?}.json", self.address)
+    }
+
+    /// 预览定时撤单的待签名请求体，不会发送
+    pub fn preview_schedule_cancel(&self, after: Option<Duration>) -> serde_json::Value {
+        let now = now_ms();
+        let time = after.map(|after| now + after.as_millis() as u64);
+        action::unsigned_payload(action::schedule_cancel_action(time), now)
+    }
+
+    /// 设置定时撤单：`after` 之后撤销所有挂单，为 None 时取消已设置的定时撤单
+    ///
+    /// 重复调用会延长撤单时间，成功后在本地记录状态
+    pub async fn schedule_cancel(&self, after: Option<Duration>) -> Result<DeadManSwitch> {
+        let now = now_ms();
+        let status = DeadManSwitch {
+            cancel_at: after.map(|after| now + after.as_millis() as u64),
+            updated_at: now,
+        };
+        let response = self.backend.schedule_cancel(status.cancel_at).await?;
+        hyperliquid::statuses(response)?;
+        store::save(&self.dead_man_file(), &status)?;
+        Ok(status)
+    }
+
+    /// 本地记录的定时撤单状态
+    pub fn dead_man_switch(&self) -> Result<DeadManSwitch> {
+        store::load(&self.dead_man_file())
+    }
+
+    /// 选中标的的非零仓位，(标的, 带方向的数量)
+    async fn open_positions(&self, assets: &[String]) -> Result<Vec<(String, f64)>> {
+        let state = self.backend.user_state(self.address).await?;
+        Ok(state
+            .asset_positions
+            .iter()
+            .map(|p| {
+                let szi = p.position.szi.parse::<f64>().unwrap_or(0.0);
+                (p.position.coin.clone(), szi)
+            })
+            .filter(|(asset, szi)| *szi != 0.0 && selected(assets, asset))
+            .collect())
+    }
+
+    /// 仓位的平仓订单：以中间价加减 `slippage` 为限价的只减仓 Ioc 订单，数量取整后为 0 时返回 None
+    fn close_order(
+        &self,
+        asset: &str,
+        szi: f64,
+        mids: &HashMap<String, String>,
+        slippage: f64,
+    ) -> Option<OrderSpec> {
+        let mid = mids.get(asset)?.parse::<f64>().ok()?;
+        let sz_decimals = self.backend.sz_decimals(asset)?;
+        if order::round_size(szi.abs(), sz_decimals) <= 0.0 {
+            return None;
+        }
+        let (side, price) = if szi > 0.0 {
+            (OrderSide::Sell, mid * (1.0 - slippage))
+        } else {
+            (OrderSide::Buy, mid * (1.0 + slippage))
+        };
+        Some(OrderSpec {
+            asset: asset.to_string(),
+            side,
+            kind: OrderKind::Limit {
+                tif: LimitType::Ioc,
+            },
+            reduce_only: true,
+            price: Some(price),
+            quantity: szi.abs(),
+            cloid: None,
+        })
+    }
+
+    /// 平掉选中标的所有仓位的订单，`assets` 为空时包含所有仓位
+    pub async fn flatten_orders(&self, assets: &[String], slippage: f64) -> Result<Vec<OrderSpec>> {
+        let (positions, mids) =
+            tokio::try_join!(self.open_positions(assets), self.backend.all_mids())?;
+        Ok(positions
+            .iter()
+            .filter_map(|(asset, szi)| self.close_order(asset, *szi, &mids, slippage))
+            .collect())
+    }
+
+    /// 预览一键平仓：将撤销的挂单及平仓订单，不会发送
+    pub async fn preview_flatten(
+        &self,
+        assets: &[String],
+        keep_orders: bool,
+        slippage: f64,
+    ) -> Result<FlattenPreview> {
+        let orders = if keep_orders {
+            vec![]
+        } else {
+            self.open_orders()
+                .await?
+                .into_iter()
+                .filter(|o| selected(assets, &o.asset))
+                .collect()
+        };
+        let specs = self.flatten_orders(assets, slippage).await?;
+        Ok(FlattenPreview {
+            orders,
+            positions: self.preview_orders(&specs).await?,
+        })
+    }
+
+    /// 一键平仓：撤销选中标的的挂单(`keep_orders` 时保留)，并以只减仓 Ioc 订单平掉所有仓位
+    ///
+    /// 部分成交或被拒绝时按最新仓位重试，直到全部平仓或超过 `timeout`；`assets` 为空时处理所有标的
+    pub async fn flatten(
+        &self,
+        assets: &[String],
+        keep_orders: bool,
+        slippage: f64,
+        timeout: Duration,
+    ) -> Result<FlattenReport> {
+        let deadline = Instant::now() + timeout;
+        let mut report = FlattenReport::default();
+
+        if !keep_orders {
+            let orders: Vec<(String, u64)> = self
+                .open_orders()
+                .await?
+                .into_iter()
+                .filter(|o| selected(assets, &o.asset))
+                .map(|o| (o.asset, o.oid))
+                .collect();
+            if !orders.is_empty() {
+                // 撤单失败时仍继续平仓
+                let results = match self.cancel_orders(&orders).await {
+                    Ok(results) => results,
+                    Err(error) => orders
+                        .iter()
+                        .map(|_| OrderResult::failed(&error.to_string()))
+                        .collect(),
+                };
+                for ((asset, oid), result) in orders.iter().zip(results) {
+                    match result.error {
+                        Some(error) => report
+                            .cancel_errors
+                            .push(format!("{} {}: {}", asset, oid, error)),
+                        None => report.cancelled_orders += 1,
+                    }
+                }
+            }
+        }
+
+        loop {
+            let (positions, mids) =
+                tokio::try_join!(self.open_positions(assets), self.backend.all_mids())?;
+            for result in report.positions.iter_mut() {
+                result.remaining_quantity = positions
+                    .iter()
+                    .find(|(asset, _)| *asset == result.asset)
+                    .map(|(_, szi)| *szi)
+                    .unwrap_or(0.0);
+            }
+            for (asset, szi) in &positions {
+                if !report.positions.iter().any(|r| r.asset == *asset) {
+                    report.positions.push(FlattenResult::new(asset, *szi));
+                }
+            }
+
+            let specs: Vec<OrderSpec> = positions
+                .iter()
+                .filter_map(|(asset, szi)| self.close_order(asset, *szi, &mids, slippage))
+                .collect();
+            if specs.is_empty() || Instant::now() >= deadline {
+                break;
+            }
+            let results = match self.place_orders(&specs).await {
+                Ok(results) => results,
+                Err(error) => match error.downcast_ref::<ToolsetError>() {
+                    // 风控拒绝及参数错误重试也不会成功
+                    Some(ToolsetError::Risk(_) | ToolsetError::Validation(_)) => return Err(error),
+                    _ => specs
+                        .iter()
+                        .map(|_| OrderResult::failed(&error.to_string()))
+                        .collect(),
+                },
+            };
+            for (spec, result) in specs.iter().zip(&results) {
+                if let Some(position) = report.positions.iter_mut().find(|r| r.asset == spec.asset)
+                {
+                    position.record(result);
+                }
+            }
+            tokio::time::sleep(FLATTEN_RETRY_DELAY).await;
+        }
+
+        for position in report.positions.iter_mut() {
+            if !position.is_flat() && position.error.is_none() {
+                position.error = Some(if position.attempts == 0 {
+                    "Size below the minimum order size".to_string()
+                } else {
+                    "Timed out".to_string()
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::MockFailure;
+    use crate::mock::test_support::{limit, position, toolset};
+    use crate::order::{LimitType, OrderKind, OrderSide, OrderSpec, TriggerType};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn gtc_rests_until_the_mid_crosses() {
+        let toolset = toolset();
+        let spec = limit("BTC", OrderSide::Buy, LimitType::Gtc, 49_000.0, 0.1);
+        let result = toolset.place_order(&spec).await.unwrap().remove(0);
+        assert_eq!(result.status, "resting");
+        let oid = result.oid.unwrap();
+        let open = toolset.open_orders().await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!((open[0].oid, open[0].quantity), (oid, 0.1));
+
+        toolset.backend().set_mid("BTC", 49_500.0);
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+        toolset.backend().set_mid("BTC", 48_900.0);
+        assert!(toolset.open_orders().await.unwrap().is_empty());
+        assert_eq!(position(&toolset, "BTC"), 0.1);
+        let fills = toolset.fills_since(0).await.unwrap();
+        assert_eq!(
+            (fills.len(), fills[0].oid, fills[0].px.as_str()),
+            (1, oid, "49000")
+        );
+    }
+
+    #[tokio::test]
+    async fn ioc_fills_at_mid_or_is_rejected() {
+        let toolset = toolset();
+        let spec = limit("ETH", OrderSide::Sell, LimitType::Ioc, 2_990.0, 1.0);
+        let result = toolset.place_order(&spec).await.unwrap().remove(0);
+        assert_eq!(result.status, "filled");
+        assert_eq!(
+            (result.filled_size, result.average_price),
+            (Some(1.0), Some(3_000.0))
+        );
+        assert_eq!(position(&toolset, "ETH"), -1.0);
+
+        let spec = limit("ETH", OrderSide::Sell, LimitType::Ioc, 3_010.0, 1.0);
+        let result = toolset.place_order(&spec).await.unwrap().remove(0);
+        assert_eq!(result.status, "error");
+        assert!(toolset.open_orders().await.unwrap().is_empty());
+        assert_eq!(position(&toolset, "ETH"), -1.0);
+    }
+
+    #[tokio::test]
+    async fn alo_is_rejected_when_it_would_cross() {
+        let toolset = toolset();
+        let crossing = limit("BTC", OrderSide::Buy, LimitType::Alo, 50_100.0, 0.1);
+        let result = toolset.place_order(&crossing).await.unwrap().remove(0);
+        assert!(result.error.unwrap().starts_with("Post only order"));
+
+        let passive = limit("BTC", OrderSide::Buy, LimitType::Alo, 49_900.0, 0.1);
+        let result = toolset.place_order(&passive).await.unwrap().remove(0);
+        assert_eq!(result.status, "resting");
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn stop_loss_triggers_when_the_mid_falls() {
+        let toolset = toolset();
+        let entry = limit("BTC", OrderSide::Buy, LimitType::Ioc, 50_500.0, 0.1);
+        toolset.place_order(&entry).await.unwrap();
+        assert_eq!(position(&toolset, "BTC"), 0.1);
+
+        let stop = OrderSpec {
+            asset: "BTC".to_string(),
+            side: OrderSide::Sell,
+            kind: OrderKind::Trigger {
+                trigger_price: 49_000.0,
+                is_market: true,
+                tpsl: TriggerType::Sl,
+            },
+            reduce_only: true,
+            price: None,
+            quantity: 0.1,
+            cloid: None,
+        };
+        let result = toolset.place_order(&stop).await.unwrap().remove(0);
+        assert_eq!(result.status, "resting");
+
+        toolset.backend().set_mid("BTC", 49_500.0);
+        assert_eq!(position(&toolset, "BTC"), 0.1);
+        toolset.backend().set_mid("BTC", 48_800.0);
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+        assert!(toolset.open_orders().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn book_depth_fills_partially() {
+        let toolset = toolset();
+        let asks = vec![(50_010.0, 0.05), (50_020.0, 0.05), (50_100.0, 1.0)];
+        toolset
+            .backend()
+            .with(|sim| sim.set_book("BTC", vec![(49_990.0, 1.0)], asks));
+
+        let gtc = limit("BTC", OrderSide::Buy, LimitType::Gtc, 50_020.0, 0.2);
+        let result = toolset.place_order(&gtc).await.unwrap().remove(0);
+        assert_eq!(result.status, "filled");
+        assert_eq!(result.filled_size, Some(0.1));
+        assert_eq!(result.average_price, Some(50_015.0));
+        // 剩余部分以原价挂单
+        let open = toolset.open_orders().await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert!((open[0].quantity - 0.1).abs() < 1e-9);
+        assert_eq!(open[0].limit_price, 50_020.0);
+
+        let ioc = limit("BTC", OrderSide::Buy, LimitType::Ioc, 50_100.0, 1.5);
+        let result = toolset.place_order(&ioc).await.unwrap().remove(0);
+        assert_eq!(result.filled_size, Some(1.0));
+        assert_eq!(toolset.open_orders().await.unwrap().len(), 1);
+        assert!((position(&toolset, "BTC") - 1.1).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn flatten_retries_the_unfilled_remainder() {
+        let toolset = toolset();
+        let entry = limit("BTC", OrderSide::Buy, LimitType::Ioc, 50_500.0, 0.1);
+        toolset.place_order(&entry).await.unwrap();
+        let resting = limit("BTC", OrderSide::Buy, LimitType::Gtc, 40_000.0, 0.1);
+        toolset.place_order(&resting).await.unwrap();
+        // 第一次平仓只能成交盘口上的 0.05，剩余部分下一次按中间价成交
+        toolset
+            .backend()
+            .with(|sim| sim.set_book("BTC", vec![(49_990.0, 0.05)], vec![]));
+
+        let report = toolset
+            .flatten(&[], false, 0.05, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(report.cancelled_orders, 1);
+        assert!(report.is_flat());
+        let btc = &report.positions[0];
+        assert_eq!((btc.attempts, btc.closed_quantity), (2, 0.1));
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn flatten_gives_up_after_the_timeout() {
+        let toolset = toolset();
+        let entry = limit("ETH", OrderSide::Sell, LimitType::Ioc, 2_900.0, 1.0);
+        toolset.place_order(&entry).await.unwrap();
+        // 卖盘远高于滑点允许的价格，平仓 Ioc 订单无法成交
+        toolset
+            .backend()
+            .with(|sim| sim.set_book("ETH", vec![], vec![(3_500.0, 10.0)]));
+
+        let report = toolset
+            .flatten(&[], true, 0.05, Duration::from_millis(1500))
+            .await
+            .unwrap();
+        assert!(!report.is_flat());
+        let eth = &report.positions[0];
+        assert_eq!(eth.remaining_quantity, -1.0);
+        assert_eq!(eth.attempts, 2);
+        assert!(
+            eth.error
+                .as_ref()
+                .unwrap()
+                .contains("could not immediately match")
+        );
+    }
+
+    #[tokio::test]
+    async fn flatten_closes_positions_when_cancelling_fails() {
+        let toolset = toolset();
+        let entry = limit("BTC", OrderSide::Buy, LimitType::Ioc, 50_500.0, 0.1);
+        toolset.place_order(&entry).await.unwrap();
+        let resting = limit("ETH", OrderSide::Buy, LimitType::Gtc, 2_000.0, 1.0);
+        toolset.place_order(&resting).await.unwrap();
+
+        toolset.backend().fail_next(MockFailure::Dropped);
+        let report = toolset
+            .flatten(&[], false, 0.05, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(report.cancelled_orders, 0);
+        assert_eq!(report.cancel_errors.len(), 1);
+        assert!(report.cancel_errors[0].contains("Simulated dropped request"));
+        assert!(report.is_flat());
+        assert_eq!(position(&toolset, "BTC"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn scheduled_cancel_clears_open_orders_and_is_recorded() {
+        let toolset = toolset();
+        let spec = limit("BTC", OrderSide::Buy, LimitType::Gtc, 49_000.0, 0.1);
+        toolset.place_order(&spec).await.unwrap();
+
+        // 交易所拒绝 5 秒内的定时撤单，本地记录不变
+        let error = toolset
+            .schedule_cancel(Some(Duration::from_secs(2)))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("too early"));
+        assert_eq!(toolset.dead_man_switch().unwrap().cancel_at, None);
+
+        let status = toolset
+            .schedule_cancel(Some(Duration::from_secs(60)))
+            .await
+            .unwrap();
+        let cancel_at = status.cancel_at.unwrap();
+        assert_eq!(cancel_at, status.updated_at + 60_000);
+        assert_eq!(
+            toolset.dead_man_switch().unwrap().cancel_at,
+            Some(cancel_at)
+        );
+
+        // 到达撤单时间前挂单保留，之后全部撤销
+        toolset
+            .backend()
+            .with(|sim| sim.run_scheduled_cancel(cancel_at - 1));
+        assert_eq!(toolset.open_orders().await.unwrap().len(), 1);
+        toolset
+            .backend()
+            .with(|sim| sim.run_scheduled_cancel(cancel_at));
+        assert!(toolset.open_orders().await.unwrap().is_empty());
+
+        // 取消定时撤单
+        toolset
+            .schedule_cancel(Some(Duration::from_secs(60)))
+            .await
+            .unwrap();
+        let status = toolset.schedule_cancel(None).await.unwrap();
+        assert_eq!(status.cancel_at, None);
+        assert_eq!(toolset.dead_man_switch().unwrap().cancel_at, None);
+        assert_eq!(
+            toolset.backend().with(|sim| sim.account.scheduled_cancel),
+            None
+        );
+    }
+}
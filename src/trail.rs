@@ -0,0 +1,351 @@
+use crate::backend::Backend;
+use crate::error::ToolsetError;
+use crate::order::{self, OrderKind, OrderSide, OrderSpec, TriggerType};
+use crate::store;
+use crate::toolset::{Toolset, now_ms};
+use anyhow::Result;
+use ethers::types::H160;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// 止损价与有利方向极值之间的距离
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TrailDistance {
+    /// 按极值的比例，0.015 表示 1.5%
+    Percent(f64),
+    /// 固定价差
+    Price(f64),
+}
+
+impl TrailDistance {
+    /// 以 `extreme` 为基准的价差
+    pub fn offset(&self, extreme: f64) -> f64 {
+        match self {
+            TrailDistance::Percent(percent) => extreme * percent,
+            TrailDistance::Price(price) => *price,
+        }
+    }
+}
+
+impl FromStr for TrailDistance {
+    type Err = String;
+
+    /// `1.5%` 为比例，`500` 为固定价差
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let invalid = || format!("invalid distance: {}", value);
+        let distance = match value.strip_suffix('%') {
+            Some(percent) => {
+                let percent: f64 = percent.trim().parse().map_err(|_| invalid())?;
+                if !(percent > 0.0 && percent < 100.0) {
+                    return Err(invalid());
+                }
+                TrailDistance::Percent(percent / 100.0)
+            }
+            None => {
+                let price: f64 = value.parse().map_err(|_| invalid())?;
+                if price <= 0.0 {
+                    return Err(invalid());
+                }
+                TrailDistance::Price(price)
+            }
+        };
+        Ok(distance)
+    }
+}
+
+impl fmt::Display for TrailDistance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrailDistance::Percent(percent) => write!(f, "{}%", percent * 100.0),
+            TrailDistance::Price(price) => write!(f, "{}", price),
+        }
+    }
+}
+
+/// 客户端追踪止损，持久化在本地以便重启后继续追踪
+///
+/// 多头追踪激活后的最高价，空头追踪最低价，止损价只向有利方向移动
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrailingStop {
+    pub asset: String,
+    pub distance: TrailDistance,
+    /// 价格到达激活价后才开始追踪，未设置时立即开始
+    pub activation: Option<f64>,
+    pub is_long: bool,
+    /// 激活以来有利方向的极值，未激活时为 None
+    pub extreme: Option<f64>,
+    /// 当前止损单的触发价
+    pub stop_price: Option<f64>,
+    /// 交易所中的止损单
+    pub oid: Option<u64>,
+    /// 止损单数量
+    pub size: f64,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl TrailingStop {
+    /// `szi` 为带方向的仓位数量
+    pub fn new(asset: &str, distance: TrailDistance, activation: Option<f64>, szi: f64) -> Self {
+        let now = now_ms();
+        Self {
+            asset: asset.to_string(),
+            distance,
+            activation,
+            is_long: szi > 0.0,
+            extreme: None,
+            stop_price: None,
+            oid: None,
+            size: szi.abs(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// 根据最新价格更新极值，返回按当前极值计算的止损价，未激活时返回 None
+    pub fn observe(&mut self, price: f64) -> Option<f64> {
+        if self.extreme.is_none()
+            && let Some(activation) = self.activation
+            && (self.is_long && price < activation || !self.is_long && price > activation)
+        {
+            return None;
+        }
+        let extreme = match self.extreme {
+            Some(extreme) if self.is_long => extreme.max(price),
+            Some(extreme) => extreme.min(price),
+            None => price,
+        };
+        self.extreme = Some(extreme);
+        let offset = self.distance.offset(extreme);
+        Some(if self.is_long {
+            extreme - offset
+        } else {
+            extreme + offset
+        })
+    }
+
+    /// `stop` 是否比当前止损价更有利
+    fn improves(&self, stop: f64) -> bool {
+        match self.stop_price {
+            Some(current) if self.is_long => stop > current,
+            Some(current) => stop < current,
+            None => true,
+        }
+    }
+
+    /// 以 `stop` 为触发价、按市场价平仓的只减仓止损单
+    pub fn stop_order(&self, stop: f64, size: f64) -> OrderSpec {
+        OrderSpec {
+            asset: self.asset.clone(),
+            side: if self.is_long {
+                OrderSide::Sell
+            } else {
+                OrderSide::Buy
+            },
+            kind: OrderKind::Trigger {
+                trigger_price: stop,
+                is_market: true,
+                tpsl: TriggerType::Sl,
+            },
+            reduce_only: true,
+            price: None,
+            quantity: size,
+            cloid: None,
+        }
+    }
+}
+
+/// 一次检查的结果
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TrailEvent {
+    /// 价格尚未到达激活价
+    Waiting,
+    /// 止损单无需调整
+    Unchanged,
+    /// 已下单或调整止损单
+    Moved,
+    /// 仓位已平仓，追踪结束
+    Closed,
+}
+
+/// 一次检查后的状态
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrailUpdate {
+    pub event: TrailEvent,
+    pub price: Option<f64>,
+    pub trail: TrailingStop,
+}
+
+fn trails_file(address: H160) -> String {
+    format!("trails-{:?}.json", address)
+}
+
+/// 账户的所有追踪止损，按标的索引
+pub fn load(address: H160) -> Result<BTreeMap<String, TrailingStop>> {
+    store::load(&trails_file(address))
+}
+
+pub fn save(address: H160, trails: &BTreeMap<String, TrailingStop>) -> Result<()> {
+    store::save(&trails_file(address), trails)
+}
+
+/// 带方向的仓位数量，无仓位时为 0
+pub async fn position_size<B: Backend>(toolset: &Toolset<B>, asset: &str) -> Result<f64> {
+    Ok(toolset
+        .positions()
+        .await?
+        .iter()
+        .find(|p| p.asset == asset)
+        .map(|p| p.quantity)
+        .unwrap_or(0.0))
+}
+
+/// 检查一次仓位及价格，必要时下单或调整止损单
+///
+/// 仓位平仓后撤销剩余的止损单并返回 [`TrailEvent::Closed`]；仓位方向改变时返回错误
+pub async fn tick<B: Backend>(
+    toolset: &Toolset<B>,
+    trail: &mut TrailingStop,
+) -> Result<TrailUpdate> {
+    let asset = trail.asset.clone();
+    let szi = position_size(toolset, &asset).await?;
+    let price = toolset
+        .mids()
+        .await?
+        .into_iter()
+        .find(|m| m.asset == asset)
+        .map(|m| m.price);
+    let update = |event: TrailEvent, trail: &TrailingStop| TrailUpdate {
+        event,
+        price,
+        trail: trail.clone(),
+    };
+
+    if szi == 0.0 {
+        if let Some(oid) = trail.oid.take() {
+            // 止损单触发后已不存在，撤单失败可以忽略
+            let _ = toolset.cancel(&asset, oid).await;
+        }
+        return Ok(update(TrailEvent::Closed, trail));
+    }
+    if (szi > 0.0) != trail.is_long {
+        return Err(ToolsetError::Validation(format!(
+            "{} position changed direction, trailing stop stopped",
+            asset
+        ))
+        .into());
+    }
+    let Some(price) = price else {
+        return Err(ToolsetError::Network(format!("No price for {}", asset)).into());
+    };
+    let Some(stop) = trail.observe(price) else {
+        return Ok(update(TrailEvent::Waiting, trail));
+    };
+
+    let sz_decimals = toolset
+        .backend()
+        .sz_decimals(&asset)
+        .ok_or_else(|| ToolsetError::Validation(format!("Unknown asset: {}", asset)))?;
+    let stop = order::round_price(stop, sz_decimals);
+    let size = szi.abs();
+    let before: Vec<u64> = toolset
+        .open_orders()
+        .await?
+        .iter()
+        .filter(|o| o.asset == asset)
+        .map(|o| o.oid)
+        .collect();
+    let resting = trail.oid.is_some_and(|oid| before.contains(&oid));
+    let stop = match trail.stop_price {
+        Some(current) if !trail.improves(stop) => current,
+        _ => stop,
+    };
+    if resting && trail.stop_price == Some(stop) && trail.size == size {
+        return Ok(update(TrailEvent::Unchanged, trail));
+    }
+
+    let spec = trail.stop_order(stop, size);
+    let result = match trail.oid {
+        Some(oid) if resting => toolset.modify_order(oid, &spec).await?,
+        _ => toolset
+            .place_order(&spec)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ToolsetError::Network("Empty order response".into()))?,
+    };
+    if let Some(error) = result.error {
+        return Err(ToolsetError::Rejected(error).into());
+    }
+    trail.oid = match result.oid {
+        Some(oid) => Some(oid),
+        None if resting => trail.oid,
+        // 交易所未返回订单ID时，按新增的挂单查找
+        None => toolset
+            .open_orders()
+            .await?
+            .iter()
+            .find(|o| o.asset == asset && !before.contains(&o.oid))
+            .map(|o| o.oid),
+    };
+    trail.stop_price = Some(stop);
+    trail.size = size;
+    trail.updated_at = now_ms();
+    Ok(update(TrailEvent::Moved, trail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::test_support::{limit, toolset};
+    use crate::order::LimitType;
+
+    #[tokio::test]
+    async fn tick_moves_the_stop_up_and_closes_after_trigger() {
+        let toolset = toolset();
+        let entry = limit("BTC", OrderSide::Buy, LimitType::Ioc, 50_500.0, 0.1);
+        toolset.place_order(&entry).await.unwrap();
+        let szi = position_size(&toolset, "BTC").await.unwrap();
+        let mut trail =
+            TrailingStop::new("BTC", TrailDistance::Price(1_000.0), Some(50_500.0), szi);
+
+        let update = tick(&toolset, &mut trail).await.unwrap();
+        assert_eq!(update.event, TrailEvent::Waiting);
+        assert!(toolset.open_orders().await.unwrap().is_empty());
+
+        toolset.backend().set_mid("BTC", 51_000.0);
+        let update = tick(&toolset, &mut trail).await.unwrap();
+        assert_eq!(update.event, TrailEvent::Moved);
+        assert_eq!(trail.stop_price, Some(50_000.0));
+        let open = toolset.open_orders().await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(Some(open[0].oid), trail.oid);
+
+        // 止损价只向有利方向移动
+        toolset.backend().set_mid("BTC", 50_800.0);
+        let update = tick(&toolset, &mut trail).await.unwrap();
+        assert_eq!(update.event, TrailEvent::Unchanged);
+        assert_eq!(trail.stop_price, Some(50_000.0));
+
+        toolset.backend().set_mid("BTC", 52_000.0);
+        let update = tick(&toolset, &mut trail).await.unwrap();
+        assert_eq!(update.event, TrailEvent::Moved);
+        assert_eq!(trail.stop_price, Some(51_000.0));
+        let open = toolset.open_orders().await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(Some(open[0].oid), trail.oid);
+
+        toolset.backend().set_mid("BTC", 50_900.0);
+        let update = tick(&toolset, &mut trail).await.unwrap();
+        assert_eq!(update.event, TrailEvent::Closed);
+        assert!(trail.oid.is_none());
+        assert!(toolset.open_orders().await.unwrap().is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use core::future::Future;
 use crossterm::event::{Event, EventStream, KeyCode, KeyEvent};
+use futures::stream::{self, LocalBoxStream, Stream};
 use futures::{StreamExt, future::FutureExt, select};
 use futures_timer::Delay;
 use ratatui::layout::{Constraint, Layout, Rect};
@@ -10,37 +11,47 @@ use std::io::Write;
 use std::time::Duration;
 use std::time::Instant;
 
-pub struct LivePanel<D, F, Fut>
-where
-    D: TableData,
-    F: Fn() -> Fut,
-    Fut: Future<Output = Vec<D>>,
-{
+/// 实时面板的数据更新
+pub enum LiveUpdate<D> {
+    /// 完整的表格数据
+    Rows(Vec<D>),
+    /// 数据源状态，显示在底部，None 表示恢复正常
+    Status(Option<String>),
+}
+
+/// 实时刷新的表格，数据来自轮询或 WebSocket 推送，按 q/Esc 退出
+pub struct LivePanel<'a, D: TableData> {
     state: TableState,
     items: Vec<D>,
-    updater: Box<F>,
+    updates: LocalBoxStream<'a, LiveUpdate<D>>,
     last_update: Option<Instant>,
+    status: Option<String>,
 }
 
-impl<D, F, Fut> LivePanel<D, F, Fut>
-where
-    D: TableData,
-    F: Fn() -> Fut,
-    Fut: Future<Output = Vec<D>>,
-{
-    pub fn with_updater(updater: F) -> Self {
+impl<'a, D: TableData + 'a> LivePanel<'a, D> {
+    /// 轮询：反复调用 `updater` 获取完整数据，刷新间隔由 `updater` 控制
+    pub fn with_updater<F, Fut>(updater: F) -> Self
+    where
+        F: Fn() -> Fut + 'a,
+        Fut: Future<Output = Vec<D>> + 'a,
+    {
+        Self::with_stream(stream::unfold(updater, |updater| async move {
+            let items = updater().await;
+            Some((LiveUpdate::Rows(items), updater))
+        }))
+    }
+
+    /// 推送：每收到一次更新就重新绘制
+    pub fn with_stream(updates: impl Stream<Item = LiveUpdate<D>> + 'a) -> Self {
         Self {
             state: TableState::default(),
             items: vec![],
-            updater: Box::new(updater),
+            updates: updates.boxed_local(),
             last_update: None,
+            status: None,
         }
     }
 
-    async fn update_data(&mut self) {
-        self.items = (self.updater)().await;
-    }
-
     pub async fn run_tui(&mut self) -> anyhow::Result<()> {
         let mut reader = EventStream::new();
         let mut term = ratatui::init();
@@ -48,42 +59,40 @@ where
         loop {
             let mut event = reader.next().fuse();
             let mut delay = Delay::new(Duration::from_millis(200)).fuse();
+            let mut update = self.updates.next().fuse();
 
             select! {
-                _ = delay => {
-                    if self.last_update.is_none() ||
-                    self.last_update.map(|t|t.elapsed()).unwrap() > Duration::from_secs(5) {
-                        let _ = self.update_data().await;
+                update = update => match update {
+                    Some(LiveUpdate::Rows(items)) => {
+                        self.items = items;
                         self.last_update = Some(Instant::now());
                     }
-                    // let _ = term.clear().unwrap();
-                    let _ = term.draw(|frame| {
-                        self.render(frame);
-                    });
-                }
+                    Some(LiveUpdate::Status(status)) => self.status = status,
+                    None => break,
+                },
+                _ = delay => {}
                 maybe_event = event => {
                     match maybe_event {
-                        Some(Ok(event)) => match event {
-                            Event::Key(KeyEvent {
-                                code,
-                                ..
-                            }) => match code {
-                                KeyCode::Esc | KeyCode::Char('q') => break,
-                                KeyCode::Up | KeyCode::Char('k') => {
-                                    self.scroll_up(&mut term);
-                                }
-                                KeyCode::Down | KeyCode::Char('j') => {
-                                    self.scroll_down(&mut term);
-                                }
-                                _ => {}
-                            },
+                        Some(Ok(Event::Key(KeyEvent { code, .. }))) => match code {
+                            KeyCode::Esc | KeyCode::Char('q') => break,
+                            KeyCode::Up | KeyCode::Char('k') => {
+                                self.scroll_up(&mut term);
+                            }
+                            KeyCode::Down | KeyCode::Char('j') => {
+                                self.scroll_down(&mut term);
+                            }
                             _ => {}
                         },
+                        Some(Ok(_)) => {}
                         Some(Err(e)) => println!("Error: {:?}\r", e),
                         None => break,
                     }
                 }
             };
+            // let _ = term.clear().unwrap();
+            let _ = term.draw(|frame| {
+                self.render(frame);
+            });
         }
         ratatui::restore();
         Ok(())
@@ -128,14 +137,14 @@ where
 
     fn render_footer(&mut self, frame: &mut Frame, area: Rect) {
         // let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let info_footer = Paragraph::new(Text::from(format!(
-            "updated {} seconds ago",
-            self.last_update
-                .map(|t| t.elapsed())
-                .unwrap_or(Duration::from_secs(0))
-                .as_secs()
-        )))
-        .centered();
+        let mut footer = match self.last_update {
+            Some(time) => format!("updated {} seconds ago", time.elapsed().as_secs()),
+            None => String::from("waiting for data"),
+        };
+        if let Some(status) = &self.status {
+            footer = format!("{} · {}", footer, status);
+        }
+        let info_footer = Paragraph::new(Text::from(footer)).centered();
         frame.render_widget(info_footer, area);
     }
 
@@ -146,7 +155,7 @@ where
             .collect::<Row>()
             .height(1);
         self.items.sort_by(D::comparator);
-        let items = self.items.iter().map(|d| d).collect::<Vec<_>>();
+        let items = self.items.iter().collect::<Vec<_>>();
         let rows = D::to_rows(&items);
         let t = Table::new(rows, D::column_constraints()).header(header);
         frame.render_stateful_widget(t, area, &mut self.state);
@@ -158,7 +167,7 @@ pub trait TableData {
 
     fn column_constraints() -> Vec<Constraint>;
 
-    fn to_rows<'a>(data: &'a Vec<&Self>) -> Vec<Row<'a>>;
+    fn to_rows<'a>(data: &'a [&Self]) -> Vec<Row<'a>>;
 
     fn comparator(&self, other: &Self) -> std::cmp::Ordering;
 }
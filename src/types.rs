@@ -1,7 +1,18 @@
+use crate::cost::SpotCost;
 use crate::tui::TableData;
+use crate::ui;
+use chrono::{Local, TimeZone, Utc};
+use clap::ValueEnum;
+use hyperliquid_rust_sdk::{
+    AssetPosition, BasicOrder, ExchangeDataStatus, MarginSummary, OpenOrdersResponse,
+    RecentTradesResponse, Trade as WsTrade, UserTokenBalance,
+};
 use ratatui::layout::Constraint;
-use ratatui::widgets::Cell;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Cell, Row};
+use serde::{Deserialize, Serialize};
 
+#[derive(Serialize)]
 pub struct PriceIndex {
     pub asset: String,
     pub price: f64,
@@ -34,7 +45,7 @@ impl TableData for PriceIndex {
         ]
     }
 
-    fn to_rows<'a>(data: &'a Vec<&Self>) -> Vec<ratatui::widgets::Row<'a>> {
+    fn to_rows<'a>(data: &'a [&Self]) -> Vec<Row<'a>> {
         let mut rows = vec![];
         let mut i = 0;
         for _ in 0..data.len() / 4 {
@@ -48,16 +59,16 @@ impl TableData for PriceIndex {
                 Cell::from(data[i + 3].asset.clone()),
                 Cell::from(format!("{:.5}", data[i + 3].price)),
             ];
-            rows.push(ratatui::widgets::Row::new(cells));
+            rows.push(Row::new(cells));
             i += 4;
         }
         if i < data.len() {
             let mut cells = vec![];
-            for j in i..data.len() {
-                cells.push(Cell::from(data[j].asset.clone()));
-                cells.push(Cell::from(format!("{:.5}", data[j].price)));
+            for d in &data[i..] {
+                cells.push(Cell::from(d.asset.clone()));
+                cells.push(Cell::from(format!("{:.5}", d.price)));
             }
-            rows.push(ratatui::widgets::Row::new(cells));
+            rows.push(Row::new(cells));
         }
         rows
     }
@@ -65,6 +76,942 @@ impl TableData for PriceIndex {
     fn comparator(&self, other: &Self) -> std::cmp::Ordering {
         self.asset
             .cmp(&other.asset)
-            .then(self.price.partial_cmp(&other.price).unwrap())
+            .then(self.price.total_cmp(&other.price))
     }
 }
+
+/// 公开成交记录
+#[derive(Serialize, Clone)]
+pub struct Trade {
+    pub time: u64,
+    pub side: String,
+    pub price: f64,
+    pub size: f64,
+    /// 名义价值超过大单阈值
+    pub large: bool,
+    /// 用于合并 HTTP 快照与 WebSocket 推送的成交时去重
+    #[serde(skip)]
+    pub key: String,
+}
+
+impl Trade {
+    fn new(hash: &str, side: &str, px: &str, sz: &str, time: u64, large_notional: f64) -> Self {
+        let price = px.parse::<f64>().unwrap_or(0.0);
+        let size = sz.parse::<f64>().unwrap_or(0.0);
+        Self {
+            time,
+            side: side.to_string(),
+            price,
+            size,
+            large: price * size >= large_notional,
+            key: format!("{}/{}/{}/{}/{}", hash, time, side, px, sz),
+        }
+    }
+
+    pub fn from_response(trade: &RecentTradesResponse, large_notional: f64) -> Self {
+        Self::new(
+            &trade.hash,
+            &trade.side,
+            &trade.px,
+            &trade.sz,
+            trade.time,
+            large_notional,
+        )
+    }
+
+    /// WebSocket 推送的成交
+    pub fn from_ws(trade: &WsTrade, large_notional: f64) -> Self {
+        Self::new(
+            &trade.hash,
+            &trade.side,
+            &trade.px,
+            &trade.sz,
+            trade.time,
+            large_notional,
+        )
+    }
+
+    pub fn is_buy(&self) -> bool {
+        self.side == "B"
+    }
+
+    pub fn notional(&self) -> f64 {
+        self.price * self.size
+    }
+
+    pub fn local_time(&self) -> String {
+        Utc.timestamp_millis_opt(self.time as i64)
+            .unwrap()
+            .with_timezone(&Local)
+            .format("%H:%M:%S%.3f")
+            .to_string()
+    }
+}
+
+impl TableData for Trade {
+    fn header() -> Vec<impl Into<String>> {
+        vec![
+            String::from("Time"),
+            String::from("Side"),
+            String::from("Price"),
+            String::from("Size"),
+            String::from("Notional"),
+        ]
+    }
+
+    fn column_constraints() -> Vec<Constraint> {
+        vec![
+            Constraint::Percentage(20),
+            Constraint::Percentage(10),
+            Constraint::Percentage(25),
+            Constraint::Percentage(20),
+            Constraint::Percentage(25),
+        ]
+    }
+
+    fn to_rows<'a>(data: &'a [&Self]) -> Vec<Row<'a>> {
+        data.iter()
+            .map(|t| {
+                let side_color = if t.is_buy() { Color::Green } else { Color::Red };
+                let side = if t.is_buy() { "Buy" } else { "Sell" };
+                let row = Row::new(vec![
+                    Cell::from(t.local_time()),
+                    Cell::from(side).style(Style::default().fg(side_color)),
+                    Cell::from(format!("{}", t.price)),
+                    Cell::from(format!("{}", t.size)),
+                    Cell::from(format!("{:.2}", t.notional())),
+                ]);
+                if t.large {
+                    row.style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+                } else {
+                    row
+                }
+            })
+            .collect()
+    }
+
+    fn comparator(&self, other: &Self) -> std::cmp::Ordering {
+        // newest first
+        other.time.cmp(&self.time)
+    }
+}
+
+/// 盘口中的一档
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BookLevel {
+    /// bid / ask
+    pub side: String,
+    pub price: f64,
+    pub size: f64,
+    /// 从最优价到该档的累计数量
+    pub total: f64,
+    /// 该档的订单数
+    pub orders: u64,
+}
+
+impl BookLevel {
+    /// 由买卖两侧的 (价格, 数量, 订单数) 生成每侧最多 `depth` 档，两侧都从最优价开始
+    pub fn from_levels(
+        bids: impl IntoIterator<Item = (f64, f64, u64)>,
+        asks: impl IntoIterator<Item = (f64, f64, u64)>,
+        depth: usize,
+    ) -> Vec<BookLevel> {
+        let side = |side: &str, levels: Vec<(f64, f64, u64)>| {
+            let mut total = 0.0;
+            levels
+                .into_iter()
+                .take(depth)
+                .map(|(price, size, orders)| {
+                    total += size;
+                    BookLevel {
+                        side: side.to_string(),
+                        price,
+                        size,
+                        total,
+                        orders,
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut levels = side("bid", bids.into_iter().collect());
+        levels.extend(side("ask", asks.into_iter().collect()));
+        levels
+    }
+
+    /// 解析接口返回的价格及数量
+    pub fn parse(px: &str, sz: &str, n: u64) -> (f64, f64, u64) {
+        (parse_f64(px), parse_f64(sz), n)
+    }
+
+    pub fn is_bid(&self) -> bool {
+        self.side == "bid"
+    }
+}
+
+impl TableData for BookLevel {
+    fn header() -> Vec<impl Into<String>> {
+        vec![
+            String::from("Side"),
+            String::from("Price"),
+            String::from("Size"),
+            String::from("Total"),
+            String::from("Orders"),
+        ]
+    }
+
+    fn column_constraints() -> Vec<Constraint> {
+        vec![
+            Constraint::Percentage(10),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(15),
+        ]
+    }
+
+    fn to_rows<'a>(data: &'a [&Self]) -> Vec<Row<'a>> {
+        data.iter()
+            .map(|l| {
+                let color = if l.is_bid() { Color::Green } else { Color::Red };
+                Row::new(vec![
+                    Cell::from(if l.is_bid() { "Bid" } else { "Ask" }),
+                    Cell::from(format!("{}", l.price)),
+                    Cell::from(format!("{}", l.size)),
+                    Cell::from(format!("{:.4}", l.total)),
+                    Cell::from(format!("{}", l.orders)),
+                ])
+                .style(Style::default().fg(color))
+            })
+            .collect()
+    }
+
+    fn comparator(&self, other: &Self) -> std::cmp::Ordering {
+        // asks above bids, highest price first
+        other.price.total_cmp(&self.price)
+    }
+}
+
+/// 永续合约元数据及市场状态
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketInfo {
+    pub asset: String,
+    pub max_leverage: u32,
+    pub sz_decimals: u32,
+    pub mark_price: f64,
+    pub oracle_price: f64,
+    pub mid_price: Option<f64>,
+    pub prev_day_price: f64,
+    /// 24小时成交额(美元)
+    pub day_volume: f64,
+    /// 未平仓量(标的数量)
+    pub open_interest: f64,
+    /// 当前小时资金费率
+    pub funding: f64,
+    pub premium: Option<f64>,
+}
+
+impl MarketInfo {
+    /// 未平仓量(美元)
+    pub fn open_interest_notional(&self) -> f64 {
+        self.open_interest * self.mark_price
+    }
+
+    /// 相对前一日价格的24小时涨跌幅
+    pub fn change(&self) -> f64 {
+        if self.prev_day_price > 0.0 {
+            (self.mark_price - self.prev_day_price) / self.prev_day_price
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 现货交易对元数据及中间价
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotMarket {
+    /// 基础代币/计价代币，如 UBTC/USDC，下单时作为标的
+    pub pair: String,
+    pub base: String,
+    pub quote: String,
+    /// 基础代币的数量精度
+    pub sz_decimals: u32,
+    pub mid_price: Option<f64>,
+}
+
+/// K线
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Candle {
+    /// 开盘时间，毫秒时间戳
+    pub open_time: u64,
+    /// 收盘时间，毫秒时间戳
+    pub close_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// 市场排序方式
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum ScreenSort {
+    /// 按24小时涨跌幅绝对值排序
+    #[default]
+    Change,
+    /// 按24小时成交额排序
+    Volume,
+    /// 按未平仓量(美元)排序
+    Oi,
+    /// 按资金费率绝对值排序
+    Funding,
+}
+
+impl ScreenSort {
+    fn key(&self, market: &MarketInfo) -> f64 {
+        match self {
+            ScreenSort::Change => market.change().abs(),
+            ScreenSort::Volume => market.day_volume,
+            ScreenSort::Oi => market.open_interest_notional(),
+            ScreenSort::Funding => market.funding.abs(),
+        }
+    }
+
+    pub fn compare(&self, a: &MarketInfo, b: &MarketInfo) -> std::cmp::Ordering {
+        self.key(b)
+            .total_cmp(&self.key(a))
+            .then_with(|| a.asset.cmp(&b.asset))
+    }
+}
+
+/// 过滤成交额过低的市场，按指定方式排序后取前 `top` 个
+pub fn rank_markets(
+    markets: Vec<MarketInfo>,
+    sort: ScreenSort,
+    top: usize,
+    min_volume: f64,
+) -> Vec<RankedMarket> {
+    let mut markets: Vec<MarketInfo> = markets
+        .into_iter()
+        .filter(|m| m.day_volume >= min_volume)
+        .collect();
+    markets.sort_by(|a, b| sort.compare(a, b));
+    markets
+        .into_iter()
+        .take(top)
+        .enumerate()
+        .map(|(i, market)| RankedMarket {
+            rank: i + 1,
+            sort,
+            market,
+        })
+        .collect()
+}
+
+/// 带排名的市场，用于筛选器的展示
+#[derive(Serialize)]
+pub struct RankedMarket {
+    pub rank: usize,
+    #[serde(skip)]
+    pub sort: ScreenSort,
+    #[serde(flatten)]
+    pub market: MarketInfo,
+}
+
+impl TableData for RankedMarket {
+    fn header() -> Vec<impl Into<String>> {
+        vec![
+            String::from("#"),
+            String::from("Asset"),
+            String::from("Mark"),
+            String::from("24h Change"),
+            String::from("24h Volume"),
+            String::from("Open Interest"),
+            String::from("Funding (1h)"),
+        ]
+    }
+
+    fn column_constraints() -> Vec<Constraint> {
+        vec![
+            Constraint::Percentage(5),
+            Constraint::Percentage(15),
+            Constraint::Percentage(16),
+            Constraint::Percentage(16),
+            Constraint::Percentage(16),
+            Constraint::Percentage(16),
+            Constraint::Percentage(16),
+        ]
+    }
+
+    fn to_rows<'a>(data: &'a [&Self]) -> Vec<Row<'a>> {
+        data.iter()
+            .map(|r| {
+                let m = &r.market;
+                let change_color = if m.change() >= 0.0 {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                Row::new(vec![
+                    Cell::from(format!("{}", r.rank)),
+                    Cell::from(m.asset.clone()),
+                    Cell::from(format!("{}", m.mark_price)),
+                    Cell::from(format!("{:+.2}%", m.change() * 100.0))
+                        .style(Style::default().fg(change_color)),
+                    Cell::from(ui::compact(m.day_volume)),
+                    Cell::from(ui::compact(m.open_interest_notional())),
+                    Cell::from(ui::percent(m.funding)),
+                ])
+            })
+            .collect()
+    }
+
+    fn comparator(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort.compare(&self.market, &other.market)
+    }
+}
+
+/// 手续费率及 API 请求额度
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeSummary {
+    pub maker_rate: f64,
+    pub taker_rate: f64,
+    pub spot_maker_rate: Option<f64>,
+    pub spot_taker_rate: Option<f64>,
+    /// 近14日吃单成交额(美元)
+    pub taker_volume_14d: f64,
+    /// 近14日挂单成交额(美元)
+    pub maker_volume_14d: f64,
+    pub referral_discount: f64,
+    pub staking_discount: f64,
+    /// 累计成交额(美元)，决定 API 请求额度
+    pub cum_volume: f64,
+    pub requests_used: u64,
+    pub requests_cap: u64,
+}
+
+impl FeeSummary {
+    pub fn volume_14d(&self) -> f64 {
+        self.taker_volume_14d + self.maker_volume_14d
+    }
+
+    /// API 请求额度使用比例
+    pub fn requests_usage(&self) -> f64 {
+        if self.requests_cap > 0 {
+            self.requests_used as f64 / self.requests_cap as f64
+        } else {
+            1.0
+        }
+    }
+}
+
+fn parse_f64(value: &str) -> f64 {
+    value.parse::<f64>().unwrap_or(0.0)
+}
+
+/// 永续合约仓位
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    #[serde(rename = "type")]
+    pub type_string: String,
+    pub asset: String,
+    pub entry_price: Option<f64>,
+    pub leverage: u32,
+    pub liquidation_price: Option<f64>,
+    pub margin_used: f64,
+    pub position_value: f64,
+    pub return_on_equity: f64,
+    /// 带方向的仓位数量，空头为负
+    pub quantity: f64,
+    pub unrealized_pnl: f64,
+}
+
+impl From<&AssetPosition> for Position {
+    fn from(p: &AssetPosition) -> Self {
+        Self {
+            type_string: p.type_string.clone(),
+            asset: p.position.coin.clone(),
+            entry_price: p.position.entry_px.as_deref().map(parse_f64),
+            leverage: p.position.leverage.value,
+            liquidation_price: p.position.liquidation_px.as_deref().map(parse_f64),
+            margin_used: parse_f64(&p.position.margin_used),
+            position_value: parse_f64(&p.position.position_value),
+            return_on_equity: parse_f64(&p.position.return_on_equity),
+            quantity: parse_f64(&p.position.szi),
+            unrealized_pnl: parse_f64(&p.position.unrealized_pnl),
+        }
+    }
+}
+
+impl Position {
+    /// 按最新价格重新估算仓位价值、未实现盈亏及收益率，保证金等其它字段不变
+    pub fn reprice(&mut self, price: f64) {
+        let Some(entry) = self.entry_price else {
+            return;
+        };
+        self.position_value = self.quantity.abs() * price;
+        self.unrealized_pnl = self.quantity * (price - entry);
+        let initial_margin = self.quantity.abs() * entry / self.leverage.max(1) as f64;
+        if initial_margin > 0.0 {
+            self.return_on_equity = self.unrealized_pnl / initial_margin;
+        }
+    }
+}
+
+impl TableData for Position {
+    fn header() -> Vec<impl Into<String>> {
+        vec![
+            String::from("Asset"),
+            String::from("Quantity"),
+            String::from("Entry Price"),
+            String::from("Liquidation"),
+            String::from("Leverage"),
+            String::from("Margin Used"),
+            String::from("Position Value"),
+            String::from("Unrealized PnL"),
+            String::from("ROE"),
+        ]
+    }
+
+    fn column_constraints() -> Vec<Constraint> {
+        vec![
+            Constraint::Percentage(10),
+            Constraint::Percentage(11),
+            Constraint::Percentage(12),
+            Constraint::Percentage(12),
+            Constraint::Percentage(8),
+            Constraint::Percentage(12),
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+            Constraint::Percentage(9),
+        ]
+    }
+
+    fn to_rows<'a>(data: &'a [&Self]) -> Vec<Row<'a>> {
+        let or_dash = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or("-".to_string());
+        data.iter()
+            .map(|p| {
+                let color = if p.unrealized_pnl >= 0.0 {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                Row::new(vec![
+                    Cell::from(p.asset.clone()),
+                    Cell::from(format!("{}", p.quantity)),
+                    Cell::from(or_dash(p.entry_price)),
+                    Cell::from(or_dash(p.liquidation_price)),
+                    Cell::from(format!("{}x", p.leverage)),
+                    Cell::from(format!("{:.2}", p.margin_used)),
+                    Cell::from(format!("{:.2}", p.position_value)),
+                    Cell::from(format!("{:.2}", p.unrealized_pnl))
+                        .style(Style::default().fg(color)),
+                    Cell::from(format!("{:.2}%", p.return_on_equity * 100.0))
+                        .style(Style::default().fg(color)),
+                ])
+            })
+            .collect()
+    }
+
+    fn comparator(&self, other: &Self) -> std::cmp::Ordering {
+        other.position_value.total_cmp(&self.position_value)
+    }
+}
+
+/// 活跃订单
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenOrder {
+    pub asset: String,
+    pub limit_price: f64,
+    pub oid: u64,
+    pub side: String,
+    pub quantity: f64,
+    /// 下单时间，毫秒时间戳
+    pub timestamp: u64,
+}
+
+impl From<&OpenOrdersResponse> for OpenOrder {
+    fn from(o: &OpenOrdersResponse) -> Self {
+        Self {
+            asset: o.coin.clone(),
+            limit_price: parse_f64(&o.limit_px),
+            oid: o.oid,
+            side: o.side.clone(),
+            quantity: parse_f64(&o.sz),
+            timestamp: o.timestamp,
+        }
+    }
+}
+
+/// WebSocket 订单更新中的订单
+impl From<&BasicOrder> for OpenOrder {
+    fn from(o: &BasicOrder) -> Self {
+        Self {
+            asset: o.coin.clone(),
+            limit_price: parse_f64(&o.limit_px),
+            oid: o.oid,
+            side: o.side.clone(),
+            quantity: parse_f64(&o.sz),
+            timestamp: o.timestamp,
+        }
+    }
+}
+
+impl TableData for OpenOrder {
+    fn header() -> Vec<impl Into<String>> {
+        vec![
+            String::from("Asset"),
+            String::from("Limit Price"),
+            String::from("OrderID"),
+            String::from("Side"),
+            String::from("Quantity"),
+            String::from("Time"),
+        ]
+    }
+
+    fn column_constraints() -> Vec<Constraint> {
+        vec![
+            Constraint::Percentage(15),
+            Constraint::Percentage(20),
+            Constraint::Percentage(15),
+            Constraint::Percentage(10),
+            Constraint::Percentage(15),
+            Constraint::Percentage(25),
+        ]
+    }
+
+    fn to_rows<'a>(data: &'a [&Self]) -> Vec<Row<'a>> {
+        data.iter()
+            .map(|o| {
+                let color = if o.side == "B" {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                Row::new(vec![
+                    Cell::from(o.asset.clone()),
+                    Cell::from(format!("{}", o.limit_price)),
+                    Cell::from(format!("{}", o.oid)),
+                    Cell::from(o.side.clone()).style(Style::default().fg(color)),
+                    Cell::from(format!("{}", o.quantity)),
+                    Cell::from(
+                        Utc.timestamp_millis_opt(o.timestamp as i64)
+                            .unwrap()
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string(),
+                    ),
+                ])
+            })
+            .collect()
+    }
+
+    fn comparator(&self, other: &Self) -> std::cmp::Ordering {
+        // newest first
+        other.timestamp.cmp(&self.timestamp)
+    }
+}
+
+/// 现货余额
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotBalance {
+    pub asset: String,
+    pub hold: f64,
+    pub total: f64,
+    pub average_cost: Option<f64>,
+    pub pnl: Option<f64>,
+}
+
+impl SpotBalance {
+    pub fn new(token: &UserTokenBalance, cost: Option<&SpotCost>) -> Self {
+        let total = parse_f64(&token.total);
+        Self {
+            asset: token.coin.clone(),
+            hold: parse_f64(&token.hold),
+            total,
+            average_cost: cost.map(|c| c.average_cost),
+            pnl: cost.and_then(|c| c.pnl(total)),
+        }
+    }
+}
+
+/// 保证金概况
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginState {
+    #[serde(rename = "type")]
+    pub type_string: String,
+    pub account_value: f64,
+    pub total_margin: f64,
+    pub total_positions: f64,
+    pub total_value: f64,
+}
+
+impl MarginState {
+    pub fn new(type_string: &str, summary: &MarginSummary) -> Self {
+        Self {
+            type_string: type_string.to_string(),
+            account_value: parse_f64(&summary.account_value),
+            total_margin: parse_f64(&summary.total_margin_used),
+            total_positions: parse_f64(&summary.total_ntl_pos),
+            total_value: parse_f64(&summary.total_raw_usd),
+        }
+    }
+}
+
+/// 账户余额，包括现货余额、保证金概况及(可选的)仓位
+#[derive(Serialize)]
+pub struct BalanceReport {
+    pub spot: Vec<SpotBalance>,
+    pub margin: Vec<MarginState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub positions: Option<Vec<Position>>,
+    /// 平均成本计算失败的原因
+    #[serde(skip)]
+    pub cost_error: Option<String>,
+}
+
+/// 下单预览，`payload` 为待签名的请求体
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderPreview {
+    pub asset: String,
+    pub asset_index: u32,
+    pub side: String,
+    /// Gtc / Ioc / Alo，触发单为触发条件
+    pub order_type: String,
+    pub reduce_only: bool,
+    /// 取整后的价格
+    pub price: f64,
+    /// 取整后的数量
+    pub size: f64,
+    pub notional: f64,
+    pub mid: Option<f64>,
+    /// 预估占用保证金，现货订单为空
+    pub margin_required: Option<f64>,
+    /// 成交后带方向的仓位数量，现货订单为空
+    pub resulting_position: Option<f64>,
+    /// 成交后的账户杠杆
+    pub resulting_leverage: Option<f64>,
+    /// 成交后的预估强平价格
+    pub liquidation_price: Option<f64>,
+    pub payload: serde_json::Value,
+}
+
+/// 下单确认时显示的订单摘要
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderSummary {
+    pub asset: String,
+    pub side: String,
+    /// Gtc / Ioc / Alo，触发单为触发条件
+    pub order_type: String,
+    /// 取整后的价格
+    pub price: f64,
+    /// 取整后的数量
+    pub size: f64,
+    pub notional: f64,
+    pub mid: Option<f64>,
+}
+
+impl From<&OrderPreview> for OrderSummary {
+    fn from(preview: &OrderPreview) -> Self {
+        Self {
+            asset: preview.asset.clone(),
+            side: preview.side.clone(),
+            order_type: preview.order_type.clone(),
+            price: preview.price,
+            size: preview.size,
+            notional: preview.notional,
+            mid: preview.mid,
+        }
+    }
+}
+
+/// 撤单预览
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelPreview {
+    pub asset: String,
+    pub asset_index: u32,
+    pub oid: u64,
+    /// 对应的活跃订单，找不到时为 None
+    pub order: Option<OpenOrder>,
+    pub payload: serde_json::Value,
+}
+
+/// 一键平仓中单个标的的结果
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FlattenResult {
+    pub asset: String,
+    /// 平仓前带方向的仓位数量
+    pub initial_quantity: f64,
+    /// 已成交的平仓数量
+    pub closed_quantity: f64,
+    /// 平仓成交均价
+    pub average_price: Option<f64>,
+    /// 剩余带方向的仓位数量，为 0 时已全部平仓
+    pub remaining_quantity: f64,
+    /// 发送平仓订单的次数
+    pub attempts: u32,
+    /// 最近一次平仓失败的原因
+    pub error: Option<String>,
+}
+
+impl FlattenResult {
+    pub fn new(asset: &str, quantity: f64) -> Self {
+        Self {
+            asset: asset.to_string(),
+            initial_quantity: quantity,
+            closed_quantity: 0.0,
+            average_price: None,
+            remaining_quantity: quantity,
+            attempts: 0,
+            error: None,
+        }
+    }
+
+    /// 记录一次平仓订单的执行结果
+    pub fn record(&mut self, result: &OrderResult) {
+        self.attempts += 1;
+        self.error = result.error.clone();
+        if let (Some(size), Some(price)) = (result.filled_size, result.average_price) {
+            let closed = self.closed_quantity + size;
+            if closed > 0.0 {
+                self.average_price = Some(
+                    (self.average_price.unwrap_or(0.0) * self.closed_quantity + price * size)
+                        / closed,
+                );
+            }
+            self.closed_quantity = closed;
+        }
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.remaining_quantity == 0.0
+    }
+}
+
+/// 一键平仓的汇总
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FlattenReport {
+    /// 已撤销的挂单数
+    pub cancelled_orders: usize,
+    /// 撤单失败的挂单，通常是撤单前已成交或撤单请求失败
+    pub cancel_errors: Vec<String>,
+    pub positions: Vec<FlattenResult>,
+}
+
+impl FlattenReport {
+    pub fn is_flat(&self) -> bool {
+        self.positions.iter().all(FlattenResult::is_flat)
+    }
+}
+
+/// 一键平仓的预览：将撤销的挂单及平仓订单
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlattenPreview {
+    pub orders: Vec<OpenOrder>,
+    pub positions: Vec<OrderPreview>,
+}
+
+/// 定时撤单(dead man's switch)状态，交易所不提供查询，由本地记录最近一次设置
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadManSwitch {
+    /// 撤销所有挂单的时间(毫秒时间戳)，未设置时为 None
+    pub cancel_at: Option<u64>,
+    /// 最近一次设置的时间
+    pub updated_at: u64,
+}
+
+/// 下单或撤单的单个执行结果
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderResult {
+    /// success / waiting / resting / filled / error
+    pub status: String,
+    pub oid: Option<u64>,
+    pub filled_size: Option<f64>,
+    pub average_price: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl OrderResult {
+    /// 未发送或发送失败的订单
+    pub fn failed(error: &str) -> Self {
+        Self {
+            status: "error".to_string(),
+            oid: None,
+            filled_size: None,
+            average_price: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+impl From<&ExchangeDataStatus> for OrderResult {
+    fn from(status: &ExchangeDataStatus) -> Self {
+        let result = |status: &str| Self {
+            status: status.to_string(),
+            oid: None,
+            filled_size: None,
+            average_price: None,
+            error: None,
+        };
+        match status {
+            ExchangeDataStatus::Success => result("success"),
+            ExchangeDataStatus::WaitingForFill | ExchangeDataStatus::WaitingForTrigger => {
+                result("waiting")
+            }
+            ExchangeDataStatus::Resting(resting) => Self {
+                oid: Some(resting.oid),
+                ..result("resting")
+            },
+            ExchangeDataStatus::Filled(filled) => Self {
+                oid: Some(filled.oid),
+                filled_size: Some(parse_f64(&filled.total_sz)),
+                average_price: Some(parse_f64(&filled.avg_px)),
+                ..result("filled")
+            },
+            ExchangeDataStatus::Error(error) => Self {
+                error: Some(error.clone()),
+                ..result("error")
+            },
+        }
+    }
+}
+
+/// 账户成交记录
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserFill {
+    pub coin: String,
+    pub px: String,
+    pub sz: String,
+    /// B: 买入, A: 卖出
+    pub side: String,
+    pub time: u64,
+    pub fee: String,
+    #[serde(default)]
+    pub fee_token: String,
+    pub tid: u64,
+    #[serde(default)]
+    pub oid: u64,
+    #[serde(default)]
+    pub closed_pnl: String,
+    /// 下单时指定的 cloid
+    #[serde(default)]
+    pub cloid: Option<String>,
+}
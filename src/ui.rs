@@ -3,17 +3,220 @@ use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::*;
 use comfy_table::{Cell, Table};
-use hyperliquid_rust_sdk::{
-    AssetPosition, OpenOrdersResponse, UserStateResponse, UserTokenBalance,
+
+use crate::alert::{Alert, AlertEvent};
+use crate::batch::BatchResult;
+use crate::dca::{DcaExecution, DcaOutcome, DcaPlan};
+use crate::grid::{Grid, GridSpacing, GridStatus, GridUpdate};
+use crate::oco::{OcoEvent, OcoPair, OcoStatus, OcoUpdate};
+use crate::rules::{RuleEvent, RuleStatus};
+use crate::toolset::now_ms;
+use crate::trail::{TrailEvent, TrailUpdate, TrailingStop};
+use crate::types::{
+    BalanceReport, BookLevel, CancelPreview, DeadManSwitch, FeeSummary, FlattenPreview,
+    FlattenReport, MarketInfo, OpenOrder, OrderPreview, OrderResult, OrderSummary, Position,
+    PriceIndex, RankedMarket, Trade,
 };
 
-use crate::types::PriceIndex;
+/// 询问用户确认，只有输入 y 才返回 true；提示写入 stderr，不影响结构化输出
+pub fn confirm(prompt: &str) -> std::io::Result<bool> {
+    use std::io::Write;
+    eprint!("  ❓ {} [y/N] ", prompt);
+    std::io::stderr().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+pub fn draw_risk_violations(violations: &[String]) {
+    eprintln!("  ⛔ Risk limits exceeded:");
+    for violation in violations {
+        eprintln!("     - {}", violation);
+    }
+}
+
+/// 下单前的确认摘要，写入 stderr
+pub fn draw_order_confirmation(orders: &[OrderSummary]) {
+    eprintln!("{}", order_summary_table(orders.iter().cloned()));
+}
+
+/// 批量下单预览
+pub fn draw_batch_preview(previews: &[OrderPreview]) {
+    println!("  🧪 Dry run: {} orders, nothing was sent", previews.len());
+    println!(
+        "{}",
+        order_summary_table(previews.iter().map(OrderSummary::from))
+    );
+}
+
+pub fn draw_batch_results(results: &[BatchResult]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Row", "Asset", "Status", "Order ID", "Filled", "Error",
+    ]);
+    for result in results {
+        let status = match result.status.as_str() {
+            "error" => Cell::new(&result.status).fg(Color::Red),
+            _ => Cell::new(&result.status).fg(Color::Green),
+        };
+        table.add_row(vec![
+            Cell::new(result.row),
+            Cell::new(&result.asset),
+            status,
+            Cell::new(result.oid.map(|o| o.to_string()).unwrap_or_default()),
+            Cell::new(
+                result
+                    .filled_size
+                    .map(|sz| format!("{} @ {}", sz, result.average_price.unwrap_or_default()))
+                    .unwrap_or_default(),
+            ),
+            Cell::new(result.error.as_deref().unwrap_or_default()),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// 打印一键平仓预览
+pub fn draw_flatten_preview(preview: &FlattenPreview) {
+    println!("Orders to cancel: {}", preview.orders.len());
+    line();
+    if !preview.orders.is_empty() {
+        draw_orders_table(&preview.orders);
+    }
+    println!("Positions to close: {}", preview.positions.len());
+    line();
+    if !preview.positions.is_empty() {
+        println!(
+            "{}",
+            order_summary_table(preview.positions.iter().map(OrderSummary::from))
+        );
+    }
+}
+
+/// 打印一键平仓结果
+pub fn draw_flatten_report(report: &FlattenReport) {
+    println!("Flatten result:");
+    line();
+    println!("  🟢 Cancelled {} orders", report.cancelled_orders);
+    for error in &report.cancel_errors {
+        println!("  🟠 Failed to cancel {}", error);
+    }
+    if report.positions.is_empty() {
+        println!("  ⚪ No positions to close");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Asset",
+        "Position",
+        "Closed",
+        "Avg Price",
+        "Remaining",
+        "Attempts",
+        "Status",
+    ]);
+    for position in &report.positions {
+        let status = if position.is_flat() {
+            Cell::new("closed").fg(Color::Green)
+        } else {
+            Cell::new(format!(
+                "open: {}",
+                position.error.as_deref().unwrap_or_default()
+            ))
+            .fg(Color::Red)
+        };
+        table.add_row(vec![
+            Cell::new(&position.asset),
+            Cell::new(position.initial_quantity),
+            Cell::new(position.closed_quantity),
+            Cell::new(
+                position
+                    .average_price
+                    .map(|p| format!("{:.2}", p))
+                    .unwrap_or_default(),
+            ),
+            Cell::new(position.remaining_quantity),
+            Cell::new(position.attempts),
+            status,
+        ]);
+    }
+    println!("{table}");
+    if report.is_flat() {
+        println!("  🟢 All positions closed");
+    } else {
+        let open = report.positions.iter().filter(|p| !p.is_flat()).count();
+        println!("  🔴 {} positions still open", open);
+    }
+}
+
+fn order_summary_table(orders: impl IntoIterator<Item = OrderSummary>) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Asset", "Side", "Type", "Size", "Price", "Notional", "From Mid",
+    ]);
+    for order in orders {
+        let side = if order.side == "buy" {
+            Cell::new("BUY").fg(Color::Green)
+        } else {
+            Cell::new("SELL")
+                .fg(Color::Red)
+                .add_attribute(Attribute::Bold)
+        };
+        let from_mid = order
+            .mid
+            .filter(|mid| *mid > 0.0)
+            .map(|mid| format!("{:+.2}%", (order.price - mid) / mid * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        table.add_row(vec![
+            Cell::new(&order.asset),
+            side,
+            Cell::new(&order.order_type),
+            Cell::new(order.size),
+            Cell::new(order.price),
+            Cell::new(format!("{:.2}", order.notional)),
+            Cell::new(from_mid),
+        ]);
+    }
+    table
+}
 
 pub fn line() {
     println!("--------------------------------------------------------------------------------");
 }
 
-pub fn draw_all_mids_table(mids: &Vec<PriceIndex>) {
+/// 以 K/M/B 为单位格式化大额数值
+pub fn compact(value: f64) -> String {
+    let abs = value.abs();
+    if abs >= 1e9 {
+        format!("{:.2}B", value / 1e9)
+    } else if abs >= 1e6 {
+        format!("{:.2}M", value / 1e6)
+    } else if abs >= 1e3 {
+        format!("{:.2}K", value / 1e3)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// 将费率格式化为百分比
+pub fn percent(rate: f64) -> String {
+    format!("{:.4}%", rate * 100.0)
+}
+
+pub fn draw_all_mids_table(mids: &[PriceIndex]) {
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
@@ -46,14 +249,14 @@ pub fn draw_all_mids_table(mids: &Vec<PriceIndex>) {
             row2.clear();
         }
     }
-    if row1.len() > 0 {
+    if !row1.is_empty() {
         table.add_row(row1);
         table.add_row(row2);
     }
     println!("{table}");
 }
 
-pub fn draw_user_positions_table(positions: Vec<AssetPosition>) {
+pub fn draw_user_positions_table(positions: &[Position]) {
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
@@ -72,27 +275,25 @@ pub fn draw_user_positions_table(positions: Vec<AssetPosition>) {
         "Unrealized PnL",
     ]);
 
+    let or_dash = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or("-".to_string());
     positions.iter().for_each(|p| {
         table.add_row(vec![
-            &p.type_string,
-            &p.position.coin,
-            p.position.entry_px.as_ref().unwrap_or(&"-".to_string()),
-            &format!("{}", p.position.leverage.value),
-            p.position
-                .liquidation_px
-                .as_ref()
-                .unwrap_or(&"-".to_string()),
-            &p.position.margin_used,
-            &p.position.position_value,
-            &p.position.return_on_equity,
-            &p.position.szi,
-            &p.position.unrealized_pnl,
+            p.type_string.clone(),
+            p.asset.clone(),
+            or_dash(p.entry_price),
+            format!("{}", p.leverage),
+            or_dash(p.liquidation_price),
+            p.margin_used.to_string(),
+            p.position_value.to_string(),
+            p.return_on_equity.to_string(),
+            p.quantity.to_string(),
+            p.unrealized_pnl.to_string(),
         ]);
     });
     println!("{table}");
 }
 
-pub fn draw_orders_table(orders: Vec<OpenOrdersResponse>) {
+pub fn draw_orders_table(orders: &[OpenOrder]) {
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
@@ -114,22 +315,909 @@ pub fn draw_orders_table(orders: Vec<OpenOrdersResponse>) {
             .format("%Y-%m-%d %H:%M:%S")
             .to_string();
         table.add_row(vec![
-            &o.coin,
-            &o.limit_px,
-            &format!("{}", o.oid),
-            &o.side,
-            &o.sz,
-            &time,
+            o.asset.clone(),
+            o.limit_price.to_string(),
+            format!("{}", o.oid),
+            o.side.clone(),
+            o.quantity.to_string(),
+            time,
+        ]);
+    });
+    println!("{table}");
+}
+
+/// 打印定时撤单状态
+pub fn draw_dead_man_switch(status: &DeadManSwitch) {
+    let Some(cancel_at) = status.cancel_at else {
+        println!("  ⚪ No scheduled cancel");
+        return;
+    };
+    let time = Utc
+        .timestamp_millis_opt(cancel_at as i64)
+        .unwrap()
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S");
+    let now = now_ms();
+    if cancel_at > now {
+        println!(
+            "  ⏰ Dead man's switch: all open orders will be cancelled at {} (in {}s)",
+            time,
+            (cancel_at - now).div_ceil(1000)
+        );
+    } else {
+        println!(
+            "  🟠 Dead man's switch triggered at {}, open orders were cancelled",
+            time
+        );
+    }
+}
+
+/// 打印 OCO 的一次检查结果，没有变化时不打印
+pub fn draw_oco_update(update: &OcoUpdate) {
+    let pair = &update.pair;
+    match update.event {
+        OcoEvent::Watching => {}
+        OcoEvent::Resized => println!(
+            "  🟠 OCO #{} {}: filled {} of {}, remaining order resized to {}",
+            pair.id,
+            pair.asset,
+            pair.filled(),
+            pair.quantity,
+            pair.limit.open_size.max(pair.stop.open_size)
+        ),
+        OcoEvent::Filled => println!(
+            "  🟢 OCO #{} {}: filled {} of {} (limit {}, stop {}), other order cancelled",
+            pair.id,
+            pair.asset,
+            pair.filled(),
+            pair.quantity,
+            pair.limit.filled,
+            pair.stop.filled
+        ),
+        OcoEvent::Cancelled => println!(
+            "  ⚪ OCO #{} {}: both orders cancelled without fills",
+            pair.id, pair.asset
+        ),
+    }
+}
+
+/// 打印所有 OCO
+pub fn draw_oco_table(pairs: &[OcoPair]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "ID", "Asset", "Side", "Quantity", "Limit", "Stop", "Filled", "Status",
+    ]);
+    for pair in pairs {
+        let leg = |price: f64, oid: u64, open: f64| {
+            if open > 0.0 {
+                format!("{} (#{}, open {})", price, oid, open)
+            } else {
+                price.to_string()
+            }
+        };
+        let stop = match pair.stop_limit {
+            Some(limit) => format!(
+                "{} → {}",
+                leg(pair.stop.price, pair.stop.oid, pair.stop.open_size),
+                limit
+            ),
+            None => leg(pair.stop.price, pair.stop.oid, pair.stop.open_size),
+        };
+        let status = match pair.status {
+            OcoStatus::Active => Cell::new("active").fg(Color::Yellow),
+            OcoStatus::Filled => Cell::new("filled").fg(Color::Green),
+            OcoStatus::Cancelled => Cell::new("cancelled"),
+        };
+        table.add_row(vec![
+            Cell::new(pair.id),
+            Cell::new(&pair.asset),
+            if pair.is_buy {
+                Cell::new("BUY").fg(Color::Green)
+            } else {
+                Cell::new("SELL").fg(Color::Red)
+            },
+            Cell::new(pair.quantity),
+            Cell::new(leg(pair.limit.price, pair.limit.oid, pair.limit.open_size)),
+            Cell::new(stop),
+            Cell::new(pair.filled()),
+            status,
+        ]);
+    }
+    println!("{table}");
+}
+
+/// 打印网格的一次检查结果，没有成交时不输出
+pub fn draw_grid_update(update: &GridUpdate) {
+    let grid = &update.grid;
+    for fill in &update.fills {
+        let side = if fill.is_buy { "buy" } else { "sell" };
+        let next = if fill.is_buy { "sell" } else { "buy" };
+        match fill.profit {
+            Some(profit) => println!(
+                "  💰 {} {} {} @ {} filled, profit {:.2}, {} placed at {}; realized {:.2} over {} round trips",
+                grid.asset,
+                side,
+                fill.quantity,
+                fill.price,
+                profit,
+                next,
+                fill.next_price,
+                grid.realized_profit,
+                grid.round_trips
+            ),
+            None => println!(
+                "  🟢 {} {} {} @ {} filled, {} placed at {}",
+                grid.asset, side, fill.quantity, fill.price, next, fill.next_price
+            ),
+        }
+    }
+    for price in &update.replaced {
+        println!(
+            "  🟠 {} order at {} is no longer on the book, placed again",
+            grid.asset, price
+        );
+    }
+    for error in &update.errors {
+        eprintln!(
+            "  🔴 {} order {} failed, retrying at next check",
+            grid.asset, error
+        );
+    }
+}
+
+/// 打印所有网格
+pub fn draw_grids_table(grids: &[Grid]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Asset",
+        "Range",
+        "Levels",
+        "Size",
+        "Investment",
+        "Buys / Sells",
+        "Round Trips",
+        "Profit",
+        "Fees",
+        "Net",
+        "Status",
+    ]);
+    for grid in grids {
+        let buys = grid.orders.iter().filter(|o| o.is_buy).count();
+        let net = grid.net_profit();
+        let status = match grid.status {
+            GridStatus::Running => Cell::new("running").fg(Color::Yellow),
+            GridStatus::Stopped => Cell::new("stopped"),
+        };
+        table.add_row(vec![
+            Cell::new(&grid.asset),
+            Cell::new(format!("{} - {}", grid.lower, grid.upper)),
+            Cell::new(format!(
+                "{}{}",
+                grid.prices.len(),
+                match grid.spacing {
+                    GridSpacing::Arithmetic => "",
+                    GridSpacing::Geometric => " (geometric)",
+                }
+            )),
+            Cell::new(grid.quantity),
+            Cell::new(grid.investment),
+            Cell::new(format!("{} / {}", buys, grid.orders.len() - buys)),
+            Cell::new(grid.round_trips),
+            Cell::new(format!("{:.2}", grid.realized_profit)),
+            Cell::new(format!("{:.2}", grid.fees)),
+            if net >= 0.0 {
+                Cell::new(format!("{:.2}", net)).fg(Color::Green)
+            } else {
+                Cell::new(format!("{:.2}", net)).fg(Color::Red)
+            },
+            status,
+        ]);
+    }
+    println!("{table}");
+}
+
+/// 打印追踪止损的一次检查结果
+pub fn draw_trail_update(update: &TrailUpdate) {
+    let trail = &update.trail;
+    let price = update.price.map(|p| p.to_string()).unwrap_or_default();
+    match update.event {
+        TrailEvent::Waiting => println!(
+            "  ⏳ {} {}: waiting for activation at {}",
+            trail.asset,
+            price,
+            trail.activation.unwrap_or_default()
+        ),
+        TrailEvent::Unchanged => println!(
+            "  ⚪ {} {}: extreme {}, stop {}",
+            trail.asset,
+            price,
+            trail.extreme.unwrap_or_default(),
+            trail.stop_price.unwrap_or_default()
+        ),
+        TrailEvent::Moved => println!(
+            "  🟢 {} {}: stop moved to {} for {}, Order ID: {}",
+            trail.asset,
+            price,
+            trail.stop_price.unwrap_or_default(),
+            trail.size,
+            trail.oid.map(|o| o.to_string()).unwrap_or_default()
+        ),
+        TrailEvent::Closed => println!(
+            "  🏁 {} position closed, trailing stop finished",
+            trail.asset
+        ),
+    }
+}
+
+/// 打印所有追踪止损
+pub fn draw_trails_table(trails: &[TrailingStop]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Asset",
+        "Side",
+        "Distance",
+        "Activation",
+        "Extreme",
+        "Stop",
+        "Size",
+        "Order ID",
+    ]);
+    let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or("-".to_string());
+    for trail in trails {
+        table.add_row(vec![
+            Cell::new(&trail.asset),
+            if trail.is_long {
+                Cell::new("LONG").fg(Color::Green)
+            } else {
+                Cell::new("SHORT").fg(Color::Red)
+            },
+            Cell::new(trail.distance),
+            Cell::new(optional(trail.activation)),
+            Cell::new(optional(trail.extreme)),
+            Cell::new(optional(trail.stop_price)),
+            Cell::new(trail.size),
+            Cell::new(trail.oid.map(|o| o.to_string()).unwrap_or("-".to_string())),
+        ]);
+    }
+    println!("{table}");
+}
+
+fn local_time(millis: u64) -> String {
+    Utc.timestamp_millis_opt(millis as i64)
+        .unwrap()
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// 以最大的整数单位显示间隔，如 1d、12h、30m
+pub(crate) fn interval(seconds: u64) -> String {
+    match seconds {
+        s if s > 0 && s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s > 0 && s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s > 0 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// 打印所有定投计划
+pub fn draw_dca_plans(plans: &[DcaPlan]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "ID",
+        "Asset",
+        "Market",
+        "USD",
+        "Every",
+        "At",
+        "Max Price",
+        "Slippage",
+        "Next Run",
+    ]);
+    for plan in plans {
+        table.add_row(vec![
+            Cell::new(plan.id),
+            Cell::new(&plan.asset),
+            Cell::new(if plan.spot { "spot" } else { "perp" }),
+            Cell::new(plan.usd),
+            Cell::new(interval(plan.every)),
+            Cell::new(plan.at.as_deref().unwrap_or("-")),
+            Cell::new(
+                plan.max_price
+                    .map(|p| p.to_string())
+                    .unwrap_or("-".to_string()),
+            ),
+            Cell::new(format!("{}%", plan.slippage * 100.0)),
+            Cell::new(local_time(plan.next_run)),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// 打印一次定投的执行结果
+pub fn draw_dca_execution(execution: &DcaExecution) {
+    let reason = execution.reason.as_deref().unwrap_or_default();
+    match execution.outcome {
+        DcaOutcome::Filled => println!(
+            "  🟢 DCA #{} bought {} {} @ {} for {} USDC, Order ID: {}",
+            execution.plan_id,
+            execution.quantity.unwrap_or_default(),
+            execution.asset,
+            execution.price.unwrap_or_default(),
+            execution.usd,
+            execution.oid.unwrap_or_default()
+        ),
+        DcaOutcome::Skipped => println!(
+            "  🟠 DCA #{} {} skipped: {}",
+            execution.plan_id, execution.asset, reason
+        ),
+        DcaOutcome::Failed => println!(
+            "  🔴 DCA #{} {} failed: {}",
+            execution.plan_id, execution.asset, reason
+        ),
+    }
+}
+
+/// 打印定投执行记录
+pub fn draw_dca_history(executions: &[DcaExecution]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Time", "Plan", "Asset", "USD", "Outcome", "Quantity", "Price", "Reason",
+    ]);
+    let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or("-".to_string());
+    for execution in executions {
+        let outcome = match execution.outcome {
+            DcaOutcome::Filled => Cell::new("filled").fg(Color::Green),
+            DcaOutcome::Skipped => Cell::new("skipped").fg(Color::Yellow),
+            DcaOutcome::Failed => Cell::new("failed").fg(Color::Red),
+        };
+        table.add_row(vec![
+            Cell::new(local_time(execution.time)),
+            Cell::new(execution.plan_id),
+            Cell::new(&execution.asset),
+            Cell::new(execution.usd),
+            outcome,
+            Cell::new(optional(execution.quantity)),
+            Cell::new(optional(execution.price)),
+            Cell::new(execution.reason.as_deref().unwrap_or("")),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// 打印所有提醒
+pub fn draw_alerts_table(alerts: &[Alert]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "ID",
+        "Condition",
+        "Notify",
+        "Cooldown",
+        "Fired",
+        "Last Fired",
+    ]);
+    for alert in alerts {
+        let channels: Vec<String> = alert.channels.iter().map(|c| c.to_string()).collect();
+        table.add_row(vec![
+            Cell::new(alert.id),
+            Cell::new(alert.describe()),
+            Cell::new(channels.join("\n")),
+            Cell::new(interval(alert.cooldown)),
+            Cell::new(alert.fired),
+            Cell::new(alert.last_fired.map(local_time).unwrap_or("-".to_string())),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// 打印一次触发的提醒及发送失败的通知
+pub fn draw_alert_event(event: &AlertEvent) {
+    println!(
+        "  🔔 [{}] Alert #{}: {}",
+        local_time(event.time),
+        event.id,
+        event.message
+    );
+    for error in &event.errors {
+        println!("  🔴 Failed to notify {}", error);
+    }
+}
+
+/// 打印所有规则的当前状态
+pub fn draw_rules_table(statuses: &[RuleStatus]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Rule",
+        "When",
+        "Then",
+        "Value",
+        "Met",
+        "Repeat",
+        "Fired",
+        "Last Fired",
+    ]);
+    for status in statuses {
+        let done = !status.repeat && status.fired > 0;
+        table.add_row(vec![
+            Cell::new(&status.name),
+            Cell::new(&status.condition),
+            Cell::new(&status.action),
+            Cell::new(
+                status
+                    .value
+                    .map(|v| ((v * 1e8).round() / 1e8).to_string())
+                    .unwrap_or("-".to_string()),
+            ),
+            if status.met {
+                Cell::new("yes").fg(Color::Green)
+            } else {
+                Cell::new("no")
+            },
+            Cell::new(if status.repeat { "yes" } else { "no" }),
+            if done {
+                Cell::new(format!("{} (done)", status.fired)).fg(Color::DarkGrey)
+            } else {
+                Cell::new(status.fired)
+            },
+            Cell::new(status.last_fired.map(local_time).unwrap_or("-".to_string())),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// 打印一条规则的执行结果
+pub fn draw_rule_event(event: &RuleEvent) {
+    println!(
+        "  ⚡ [{}] Rule {}: {} ({}) → {}",
+        local_time(event.time),
+        event.rule,
+        event.condition,
+        event
+            .value
+            .map(|v| ((v * 1e8).round() / 1e8).to_string())
+            .unwrap_or("-".to_string()),
+        event.action
+    );
+    if !event.results.is_empty() {
+        draw_order_results(&event.results);
+    } else if let Some(error) = &event.error {
+        println!("  🔴 Failure: {}", error);
+    }
+}
+
+/// 打印下单结果
+pub fn draw_order_results(results: &[OrderResult]) {
+    println!("Operation result: ");
+    line();
+    for result in results {
+        match result.status.as_str() {
+            "success" => println!("  🟢 Success"),
+            "waiting" => println!("  🟠 Waiting for fill/trigger"),
+            "filled" => println!(
+                "  🟢 Filled: {} @ {}, Order ID: {}",
+                result.filled_size.unwrap_or_default(),
+                result.average_price.unwrap_or_default(),
+                result.oid.unwrap_or_default(),
+            ),
+            "resting" => println!("  🟢 Order ID: {}", result.oid.unwrap_or_default()),
+            _ => println!(
+                "  🔴 Failure: {}",
+                result.error.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// 打印撤单结果
+pub fn draw_cancel_results(results: &[OrderResult]) {
+    for result in results {
+        match result.status.as_str() {
+            "success" => println!("  🟢 Success"),
+            "filled" => println!(
+                "  🟢 Success: {}@{} by {}",
+                result.oid.unwrap_or_default(),
+                result.average_price.unwrap_or_default(),
+                result.filled_size.unwrap_or_default(),
+            ),
+            "waiting" => println!("  🟡 Wating for Fill/Trigger.."),
+            "resting" => println!("  🔄 Resting Order: {}", result.oid.unwrap_or_default()),
+            _ => println!(
+                "  🔴 Error: {}",
+                result.error.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+pub fn draw_payload(payload: &serde_json::Value) {
+    println!("Payload (not signed, not sent):");
+    line();
+    println!(
+        "{}",
+        serde_json::to_string_pretty(payload).unwrap_or_default()
+    );
+}
+
+pub fn draw_order_preview(preview: &OrderPreview) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    println!(
+        "  🧪 Dry run: {} {}",
+        preview.side.to_uppercase(),
+        preview.asset
+    );
+    let na = || "-".to_string();
+    let side_color = if preview.side == "buy" {
+        Color::Green
+    } else {
+        Color::Red
+    };
+    table
+        .add_row(vec![
+            Cell::new("Asset"),
+            Cell::new(format!("{} (#{})", preview.asset, preview.asset_index)),
+        ])
+        .add_row(vec![
+            Cell::new("Side"),
+            Cell::new(&preview.side).fg(side_color),
+        ])
+        .add_row(vec![Cell::new("Type"), Cell::new(&preview.order_type)])
+        .add_row(vec![
+            Cell::new("Reduce Only"),
+            Cell::new(preview.reduce_only),
+        ])
+        .add_row(vec![Cell::new("Price"), Cell::new(preview.price)])
+        .add_row(vec![Cell::new("Size"), Cell::new(preview.size)])
+        .add_row(vec![
+            Cell::new("Notional"),
+            Cell::new(format!("{:.2}", preview.notional)),
+        ])
+        .add_row(vec![
+            Cell::new("Mid Price"),
+            Cell::new(preview.mid.map(|p| p.to_string()).unwrap_or_else(na)),
+        ])
+        .add_row(vec![
+            Cell::new("Est. Margin"),
+            Cell::new(
+                preview
+                    .margin_required
+                    .map(|m| format!("{:.2}", m))
+                    .unwrap_or_else(na),
+            ),
+        ])
+        .add_row(vec![
+            Cell::new("Resulting Position"),
+            Cell::new(
+                preview
+                    .resulting_position
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(na),
+            ),
+        ])
+        .add_row(vec![
+            Cell::new("Resulting Leverage"),
+            Cell::new(
+                preview
+                    .resulting_leverage
+                    .map(|l| format!("{:.2}x", l))
+                    .unwrap_or_else(na),
+            ),
+        ])
+        .add_row(vec![
+            Cell::new("Est. Liquidation"),
+            Cell::new(
+                preview
+                    .liquidation_price
+                    .map(|p| format!("{:.2}", p))
+                    .unwrap_or_else(na),
+            ),
+        ]);
+    println!("{table}");
+    draw_payload(&preview.payload);
+}
+
+pub fn draw_cancel_preview(preview: &CancelPreview) {
+    println!(
+        "  🧪 Dry run: cancel order {} on {} (#{})",
+        preview.oid, preview.asset, preview.asset_index
+    );
+    match &preview.order {
+        Some(order) => draw_orders_table(std::slice::from_ref(order)),
+        None => println!("  🟠 Order {} is not open", preview.oid),
+    }
+    draw_payload(&preview.payload);
+}
+
+pub fn draw_trades_table(asset: &str, trades: &[Trade]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    println!("{} Recent Trades:", asset);
+    table.set_header(vec!["Time", "Side", "Price", "Size", "Notional"]);
+
+    trades.iter().for_each(|t| {
+        let (side, color) = if t.is_buy() {
+            ("Buy", Color::Green)
+        } else {
+            ("Sell", Color::Red)
+        };
+        let mut cells = vec![
+            Cell::from(t.local_time()),
+            Cell::from(side).fg(color),
+            Cell::from(format!("{}", t.price)),
+            Cell::from(format!("{}", t.size)),
+            Cell::from(format!("{:.2}", t.notional())),
+        ];
+        if t.large {
+            cells = cells
+                .into_iter()
+                .map(|c| c.add_attributes(vec![Attribute::Bold, Attribute::Reverse]))
+                .collect();
+        }
+        table.add_row(cells);
+    });
+    println!("{table}");
+}
+
+pub fn draw_book_table(asset: &str, levels: &[BookLevel]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    println!("{} Order Book:", asset);
+    table.set_header(vec!["Side", "Price", "Size", "Total", "Orders"]);
+
+    levels.iter().for_each(|l| {
+        let (side, color) = if l.is_bid() {
+            ("Bid", Color::Green)
+        } else {
+            ("Ask", Color::Red)
+        };
+        table.add_row(vec![
+            Cell::from(side).fg(color),
+            Cell::from(format!("{}", l.price)).fg(color),
+            Cell::from(format!("{}", l.size)),
+            Cell::from(format!("{}", l.total)),
+            Cell::from(l.orders),
+        ]);
+    });
+    println!("{table}");
+}
+
+pub fn draw_market_info(market: &MarketInfo) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    println!("{} Market Info:", market.asset);
+    let na = || "-".to_string();
+    table
+        .add_row(vec![
+            "Max Leverage".to_string(),
+            format!("{}x", market.max_leverage),
+        ])
+        .add_row(vec![
+            "Size Decimals".to_string(),
+            format!("{}", market.sz_decimals),
+        ])
+        .add_row(vec![
+            "Mark Price".to_string(),
+            format!("{}", market.mark_price),
+        ])
+        .add_row(vec![
+            "Oracle Price".to_string(),
+            format!("{}", market.oracle_price),
+        ])
+        .add_row(vec![
+            "Mid Price".to_string(),
+            market
+                .mid_price
+                .map(|p| format!("{}", p))
+                .unwrap_or_else(na),
+        ])
+        .add_row(vec!["24h Volume".to_string(), compact(market.day_volume)])
+        .add_row(vec![
+            "Open Interest".to_string(),
+            format!(
+                "{} ({})",
+                market.open_interest,
+                compact(market.open_interest_notional())
+            ),
+        ])
+        .add_row(vec!["Funding (1h)".to_string(), percent(market.funding)])
+        .add_row(vec![
+            "Premium".to_string(),
+            market.premium.map(percent).unwrap_or_else(na),
+        ]);
+    println!("{table}");
+}
+
+pub fn draw_markets_table(markets: &[MarketInfo]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Asset",
+        "Max Lev",
+        "Sz Dec",
+        "Mark",
+        "Oracle",
+        "Mid",
+        "24h Volume",
+        "Open Interest",
+        "Funding (1h)",
+        "Premium",
+    ]);
+
+    markets.iter().for_each(|m| {
+        table.add_row(vec![
+            m.asset.clone(),
+            format!("{}x", m.max_leverage),
+            format!("{}", m.sz_decimals),
+            format!("{}", m.mark_price),
+            format!("{}", m.oracle_price),
+            m.mid_price
+                .map(|p| format!("{}", p))
+                .unwrap_or_else(|| "-".to_string()),
+            compact(m.day_volume),
+            compact(m.open_interest_notional()),
+            percent(m.funding),
+            m.premium.map(percent).unwrap_or_else(|| "-".to_string()),
+        ]);
+    });
+    println!("{table}");
+}
+
+pub fn draw_screen_table(ranked: &[RankedMarket]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "#",
+        "Asset",
+        "Mark",
+        "24h Change",
+        "24h Volume",
+        "Open Interest",
+        "Funding (1h)",
+    ]);
+
+    ranked.iter().for_each(|r| {
+        let m = &r.market;
+        let change_color = if m.change() >= 0.0 {
+            Color::Green
+        } else {
+            Color::Red
+        };
+        table.add_row(vec![
+            Cell::from(r.rank),
+            Cell::from(&m.asset),
+            Cell::from(m.mark_price),
+            Cell::from(format!("{:+.2}%", m.change() * 100.0)).fg(change_color),
+            Cell::from(compact(m.day_volume)),
+            Cell::from(compact(m.open_interest_notional())),
+            Cell::from(percent(m.funding)),
         ]);
     });
     println!("{table}");
 }
 
-pub fn draw_balance_table(
-    state: UserStateResponse,
-    tokens: Vec<UserTokenBalance>,
-    all_details: bool,
-) {
+pub fn draw_fees_table(fees: &FeeSummary) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    println!("Fees:");
+    let na = || "-".to_string();
+    table
+        .set_header(vec!["Market", "Maker", "Taker"])
+        .add_row(vec![
+            "Perp".to_string(),
+            percent(fees.maker_rate),
+            percent(fees.taker_rate),
+        ])
+        .add_row(vec![
+            "Spot".to_string(),
+            fees.spot_maker_rate.map(percent).unwrap_or_else(na),
+            fees.spot_taker_rate.map(percent).unwrap_or_else(na),
+        ]);
+    println!("{table}");
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table
+        .add_row(vec!["14d Volume".to_string(), compact(fees.volume_14d())])
+        .add_row(vec![
+            "14d Taker Volume".to_string(),
+            compact(fees.taker_volume_14d),
+        ])
+        .add_row(vec![
+            "14d Maker Volume".to_string(),
+            compact(fees.maker_volume_14d),
+        ])
+        .add_row(vec![
+            "Staking Discount".to_string(),
+            format!("{:.2}%", fees.staking_discount * 100.0),
+        ])
+        .add_row(vec![
+            "Referral Discount".to_string(),
+            format!("{:.2}%", fees.referral_discount * 100.0),
+        ]);
+    println!("{table}");
+
+    line();
+
+    println!("API Rate Limit:");
+    let usage_color = match fees.requests_usage() {
+        u if u >= 0.9 => Color::Red,
+        u if u >= 0.7 => Color::Yellow,
+        _ => Color::Green,
+    };
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table
+        .set_header(vec![
+            "Requests Used",
+            "Requests Cap",
+            "Usage",
+            "Cumulative Volume",
+        ])
+        .add_row(vec![
+            Cell::from(fees.requests_used),
+            Cell::from(fees.requests_cap),
+            Cell::from(format!("{:.2}%", fees.requests_usage() * 100.0)).fg(usage_color),
+            Cell::from(compact(fees.cum_volume)),
+        ]);
+    println!("{table}");
+}
+
+pub fn draw_balance_table(report: &BalanceReport) {
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
@@ -139,9 +1227,26 @@ pub fn draw_balance_table(
     // Spot Token Balance
     println!("Spot Tokens:");
     table.set_header(vec!["Asset", "Hold", "Total", "Average Cost", "PnL"]);
-    // TODO: Implement average cost calculation
-    tokens.iter().for_each(|token| {
-        table.add_row(vec![&token.coin, &token.hold, &token.total, "0.0", "0.0"]);
+    report.spot.iter().for_each(|token| {
+        let average_cost = token
+            .average_cost
+            .map(|cost| Cell::from(format!("{:.6}", cost)))
+            .unwrap_or(Cell::from("-"));
+        let pnl = match token.pnl {
+            Some(pnl) => Cell::from(format!("{:.2}", pnl)).fg(if pnl >= 0.0 {
+                Color::Green
+            } else {
+                Color::Red
+            }),
+            None => Cell::from("-"),
+        };
+        table.add_row(vec![
+            Cell::from(&token.asset),
+            Cell::from(token.hold),
+            Cell::from(token.total),
+            average_cost,
+            pnl,
+        ]);
     });
     println!("{table}");
 
@@ -154,34 +1259,28 @@ pub fn draw_balance_table(
         .load_preset(UTF8_FULL)
         .apply_modifier(UTF8_ROUND_CORNERS)
         .set_content_arrangement(ContentArrangement::Dynamic);
-    table
-        .set_header(vec![
-            "Type",
-            "Account Value",
-            "Total Margin",
-            "Total Positions",
-            "Total Value",
-        ])
-        .add_row(vec![
-            "Isolated",
-            &state.margin_summary.account_value,
-            &state.margin_summary.total_margin_used,
-            &state.margin_summary.total_ntl_pos,
-            &state.margin_summary.total_raw_usd,
-        ])
-        .add_row(vec![
-            "Cross",
-            &state.cross_margin_summary.account_value,
-            &state.cross_margin_summary.total_margin_used,
-            &state.cross_margin_summary.total_ntl_pos,
-            &state.cross_margin_summary.total_raw_usd,
+    table.set_header(vec![
+        "Type",
+        "Account Value",
+        "Total Margin",
+        "Total Positions",
+        "Total Value",
+    ]);
+    report.margin.iter().for_each(|m| {
+        table.add_row(vec![
+            m.type_string.clone(),
+            m.account_value.to_string(),
+            m.total_margin.to_string(),
+            m.total_positions.to_string(),
+            m.total_value.to_string(),
         ]);
+    });
     println!("{table}");
 
     // by default, user positions are emitted
-    if all_details {
-        if state.asset_positions.len() > 0 {
-            draw_user_positions_table(state.asset_positions);
+    if let Some(positions) = &report.positions {
+        if !positions.is_empty() {
+            draw_user_positions_table(positions);
         } else {
             println!("No holding positions")
         }